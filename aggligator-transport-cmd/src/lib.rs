@@ -0,0 +1,341 @@
+#![warn(missing_docs)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/surban/aggligator/master/.misc/aggligator.png",
+    html_favicon_url = "https://raw.githubusercontent.com/surban/aggligator/master/.misc/aggligator.png",
+    issue_tracker_base_url = "https://github.com/surban/aggligator/issues/"
+)]
+
+//! [Aggligator](aggligator) transport: subprocesses and standard IO
+//!
+//! The [`CommandConnector`] spawns a command for each link and uses its standard
+//! input and output as the link.
+//! For example, the command `ssh host nc localhost 5800` establishes a link to
+//! a server that is only listening on the loopback interface of `host`.
+//! Using multiple commands, for example over different SSH jump hosts,
+//! allows bonding these paths without opening any listening ports.
+//!
+//! The [`StdioAcceptor`] serves a single link over the standard input and output
+//! of the current process.
+
+use async_trait::async_trait;
+use futures::future;
+use std::{
+    any::Any,
+    cmp::Ordering,
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    io::{Error, ErrorKind, Result},
+    pin::Pin,
+    process::Stdio,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tokio::{
+    io::{stdin, stdout, AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader, ReadBuf},
+    process::{Child, ChildStdout, Command},
+    sync::{mpsc, oneshot, watch},
+};
+
+use aggligator::{
+    control::Direction,
+    io::{IoBox, StreamBox},
+    transport::{AcceptedStreamBox, AcceptingTransport, ConnectingTransport, LinkTag, LinkTagBox},
+};
+
+static NAME: &str = "cmd";
+
+/// Link tag for a link over a spawned command.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CommandLinkTag {
+    /// Command line.
+    pub command: String,
+}
+
+impl fmt::Display for CommandLinkTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "-> {}", &self.command)
+    }
+}
+
+impl CommandLinkTag {
+    /// Creates a new link tag for a link over a spawned command.
+    pub fn new(command: impl Into<String>) -> Self {
+        Self { command: command.into() }
+    }
+}
+
+impl LinkTag for CommandLinkTag {
+    fn transport_name(&self) -> &str {
+        NAME
+    }
+
+    fn direction(&self) -> Direction {
+        Direction::Outgoing
+    }
+
+    fn user_data(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn box_clone(&self) -> LinkTagBox {
+        Box::new(self.clone())
+    }
+
+    fn dyn_cmp(&self, other: &dyn LinkTag) -> Ordering {
+        let other = other.as_any().downcast_ref::<Self>().unwrap();
+        Ord::cmp(self, other)
+    }
+
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        Hash::hash(self, &mut state)
+    }
+}
+
+/// Transport for outgoing links over the standard input and output of spawned commands.
+///
+/// Each command line is executed by the system shell, i.e. `sh -c` on Unix
+/// and `cmd /C` on Windows.
+/// The command is killed when its link terminates and respawned
+/// when the link is reconnected.
+/// The standard error output of the command is logged.
+///
+/// This transport is IO-stream based.
+#[derive(Debug, Clone)]
+pub struct CommandConnector {
+    commands: Vec<String>,
+}
+
+impl fmt::Display for CommandConnector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.commands.len() > 1 {
+            write!(f, "[{}]", self.commands.join(", "))
+        } else {
+            write!(f, "{}", &self.commands[0])
+        }
+    }
+}
+
+impl CommandConnector {
+    /// Create a new transport for outgoing links over spawned commands.
+    ///
+    /// A link is established over each command line specified in `commands`.
+    pub fn new(commands: impl IntoIterator<Item = impl Into<String>>) -> Result<Self> {
+        let commands: Vec<_> = commands.into_iter().map(|command| command.into()).collect();
+
+        if commands.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "at least one command is required"));
+        }
+
+        Ok(Self { commands })
+    }
+
+    /// The command lines.
+    pub fn commands(&self) -> &[String] {
+        &self.commands
+    }
+
+    /// Builds the shell command for executing the command line.
+    fn shell(command: &str) -> Command {
+        #[cfg(windows)]
+        let mut cmd = {
+            let mut cmd = Command::new("cmd");
+            cmd.arg("/C");
+            cmd
+        };
+        #[cfg(not(windows))]
+        let mut cmd = {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c");
+            cmd
+        };
+
+        cmd.arg(command);
+        cmd
+    }
+}
+
+#[async_trait]
+impl ConnectingTransport for CommandConnector {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    async fn link_tags(&self, tx: watch::Sender<HashSet<LinkTagBox>>) -> Result<()> {
+        let tags: HashSet<LinkTagBox> =
+            self.commands.iter().map(|command| Box::new(CommandLinkTag::new(command)) as LinkTagBox).collect();
+        tx.send_replace(tags);
+
+        future::pending().await
+    }
+
+    async fn connect(&self, tag: &dyn LinkTag) -> Result<StreamBox> {
+        let tag: &CommandLinkTag = tag.as_any().downcast_ref().unwrap();
+
+        let mut child = Self::shell(&tag.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        tracing::debug!(command =% tag.command, pid =? child.id(), "spawned command");
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let stderr = child.stderr.take().unwrap();
+        let command = tag.command.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                tracing::debug!(%command, "stderr: {line}");
+            }
+        });
+
+        Ok(IoBox::new(ChildReader { stdout, _child: child }, stdin).into())
+    }
+}
+
+/// Standard output of a child process that is killed when this is dropped.
+struct ChildReader {
+    stdout: ChildStdout,
+    _child: Child,
+}
+
+impl AsyncRead for ChildReader {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().stdout).poll_read(cx, buf)
+    }
+}
+
+/// Link tag for a link over standard input and output.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StdioLinkTag;
+
+impl fmt::Display for StdioLinkTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<- stdio")
+    }
+}
+
+impl LinkTag for StdioLinkTag {
+    fn transport_name(&self) -> &str {
+        NAME
+    }
+
+    fn direction(&self) -> Direction {
+        Direction::Incoming
+    }
+
+    fn user_data(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn box_clone(&self) -> LinkTagBox {
+        Box::new(self.clone())
+    }
+
+    fn dyn_cmp(&self, other: &dyn LinkTag) -> Ordering {
+        let other = other.as_any().downcast_ref::<Self>().unwrap();
+        Ord::cmp(self, other)
+    }
+
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        Hash::hash(self, &mut state)
+    }
+}
+
+/// Transport for a single incoming link over the standard input and output of this process.
+///
+/// This is useful for serving a link from a process spawned by `inetd` or SSH.
+/// Since standard input and output can only be used once, the transport terminates
+/// when the link has been closed.
+/// Nothing else may be written to standard output while this transport is in use.
+///
+/// This transport is IO-stream based.
+#[derive(Debug, Default)]
+pub struct StdioAcceptor {
+    used: Mutex<bool>,
+}
+
+impl fmt::Display for StdioAcceptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "stdio")
+    }
+}
+
+impl StdioAcceptor {
+    /// Creates a new transport for an incoming link over standard input and output.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AcceptingTransport for StdioAcceptor {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    async fn listen(&self, tx: mpsc::Sender<AcceptedStreamBox>) -> Result<()> {
+        if std::mem::replace(&mut *self.used.lock().unwrap(), true) {
+            return Err(Error::new(ErrorKind::AddrInUse, "standard input and output have already been used"));
+        }
+
+        let (closed_tx, closed_rx) = oneshot::channel();
+        let closed_tx = Arc::new(closed_tx);
+        let io = IoBox::new(
+            Guarded { inner: stdin(), _guard: closed_tx.clone() },
+            Guarded { inner: stdout(), _guard: closed_tx },
+        );
+
+        if tx.send(AcceptedStreamBox::new(io.into(), StdioLinkTag)).await.is_ok() {
+            // Wait until the link has released standard input and output.
+            let _ = closed_rx.await;
+            tracing::debug!("standard input and output released");
+        }
+
+        Ok(())
+    }
+}
+
+/// IO stream half that notifies when both halves have been dropped.
+struct Guarded<T> {
+    inner: T,
+    _guard: Arc<oneshot::Sender<()>>,
+}
+
+impl<T> AsyncRead for Guarded<T>
+where
+    T: AsyncRead + Unpin,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl<T> AsyncWrite for Guarded<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,73 @@
+//! Command transport tests.
+
+#![cfg(unix)]
+
+use futures::join;
+use std::{future::IntoFuture, path::PathBuf, process::Command};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncReadExt, AsyncWriteExt},
+};
+
+use aggligator::{cfg::Cfg, connect::Server, transport::Connector};
+use aggligator_transport_cmd::{CommandConnector, CommandLinkTag};
+
+fn fifo(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("aggligator-cmd-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    assert!(Command::new("mkfifo").arg(&path).status().unwrap().success());
+    path
+}
+
+/// Establishes a link over a command that relays data through named pipes.
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn command() {
+    const DATA: &[u8] = b"Hello over a command!";
+
+    let to_client = fifo("to-client");
+    let to_server = fifo("to-server");
+    let command = format!("cat {} & exec cat > {}", to_client.display(), to_server.display());
+
+    let mut connector = Connector::new();
+    connector.add(CommandConnector::new([command.clone()]).unwrap());
+    let outgoing = connector.channel().unwrap();
+
+    let server_task = async {
+        let mut options = OpenOptions::new();
+        options.write(true);
+        let (write, read) = join!(options.open(&to_client), File::open(&to_server));
+        let (write, read) = (write.unwrap(), read.unwrap());
+
+        let server = Server::new(Cfg::default());
+        let mut listener = server.listen().unwrap();
+        server.add_incoming_io(read, write, "fifo", &[]).await.unwrap();
+
+        let (task, ch, _control) = listener.next().await.unwrap().accept();
+        tokio::spawn(task.into_future());
+
+        let mut stream = ch.into_stream();
+        let mut buf = vec![0; DATA.len()];
+        stream.read_exact(&mut buf).await.unwrap();
+        stream.write_all(&buf).await.unwrap();
+        stream.flush().await.unwrap();
+    };
+
+    let client_task = async {
+        let mut stream = outgoing.await.unwrap().into_stream();
+        stream.write_all(DATA).await.unwrap();
+        stream.flush().await.unwrap();
+
+        let mut buf = vec![0; DATA.len()];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, DATA);
+
+        let links = connector.control().links();
+        let tag = links[0].tag().as_any().downcast_ref::<CommandLinkTag>().unwrap();
+        assert_eq!(tag.command, command);
+    };
+
+    join!(server_task, client_task);
+
+    let _ = std::fs::remove_file(&to_client);
+    let _ = std::fs::remove_file(&to_server);
+}
@@ -0,0 +1,531 @@
+#![warn(missing_docs)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/surban/aggligator/master/.misc/aggligator.png",
+    html_favicon_url = "https://raw.githubusercontent.com/surban/aggligator/master/.misc/aggligator.png",
+    issue_tracker_base_url = "https://github.com/surban/aggligator/issues/"
+)]
+
+//! [Aggligator](aggligator) transport: QUIC
+//!
+//! Each link is a bidirectional stream of a separate QUIC connection.
+//! Since QUIC runs over UDP, a lost packet does not block delivery of data
+//! on other links, and each link is encrypted and authenticated by TLS.
+//!
+//! Like the [TCP transport](aggligator_transport_tcp), the [connector](QuicConnector)
+//! establishes a separate link from each local network interface to each IP address
+//! of the target.
+//!
+//! The TLS configuration is provided using [`quinn::ClientConfig`] and [`quinn::ServerConfig`].
+
+use async_trait::async_trait;
+use futures::{future, FutureExt};
+use quinn::{ClientConfig, Endpoint, EndpointConfig, Incoming, ServerConfig, TokioRuntime};
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::{
+    any::Any,
+    cmp::Ordering,
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    io::{Error, ErrorKind, Result},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    sync::{mpsc, watch},
+    time::{sleep, timeout},
+};
+
+use aggligator::{
+    control::Direction,
+    io::{IoBox, StreamBox},
+    transport::{AcceptedStreamBox, AcceptingTransport, ConnectingTransport, LinkTag, LinkTagBox},
+    Link,
+};
+use aggligator_transport_tcp::util::{self, NetworkInterface};
+pub use aggligator_transport_tcp::IpVersion;
+
+#[doc(no_inline)]
+pub use quinn;
+
+static NAME: &str = "quic";
+
+/// Data sent by the connecting side over a newly opened stream.
+///
+/// QUIC streams are only announced to the remote endpoint once data is sent over them,
+/// but the accepting side speaks first in the Aggligator link handshake.
+const STREAM_MAGIC: &[u8] = b"AGGLIGATOR QUIC\n";
+
+/// Timeout for the connecting side to open the stream of a link.
+const STREAM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Link tag for QUIC link.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct QuicLinkTag {
+    /// Local interface name.
+    pub interface: Option<Vec<u8>>,
+    /// Remote address.
+    pub remote: SocketAddr,
+    /// Link direction.
+    pub direction: Direction,
+}
+
+impl fmt::Display for QuicLinkTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dir = match self.direction {
+            Direction::Incoming => "<-",
+            Direction::Outgoing => "->",
+        };
+        write!(
+            f,
+            "{:16} {dir} {}",
+            String::from_utf8_lossy(self.interface.as_deref().unwrap_or_default()),
+            self.remote
+        )
+    }
+}
+
+impl QuicLinkTag {
+    /// Creates a new link tag for a QUIC link.
+    pub fn new(interface: Option<&[u8]>, remote: SocketAddr, direction: Direction) -> Self {
+        Self { interface: interface.map(|iface| iface.to_vec()), remote, direction }
+    }
+}
+
+impl LinkTag for QuicLinkTag {
+    fn transport_name(&self) -> &str {
+        NAME
+    }
+
+    fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    fn user_data(&self) -> Vec<u8> {
+        self.interface.clone().unwrap_or_default()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn box_clone(&self) -> LinkTagBox {
+        Box::new(self.clone())
+    }
+
+    fn dyn_cmp(&self, other: &dyn LinkTag) -> Ordering {
+        let other = other.as_any().downcast_ref::<Self>().unwrap();
+        Ord::cmp(self, other)
+    }
+
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        Hash::hash(self, &mut state)
+    }
+}
+
+/// Creates a UDP socket for connecting to `remote`, optionally bound to the specified interface.
+fn client_socket(interface: Option<&[u8]>, remote: SocketAddr) -> Result<UdpSocket> {
+    let socket = Socket::new(Domain::for_address(remote), Type::DGRAM, Some(Protocol::UDP))?;
+
+    let unspecified = match remote {
+        SocketAddr::V4(_) => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0),
+        SocketAddr::V6(_) => SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0),
+    };
+
+    let local = match interface {
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        Some(interface) => {
+            socket.bind_device(Some(interface))?;
+            unspecified
+        }
+        #[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+        Some(interface) => SocketAddr::new(util::interface_ip_for_target(interface, remote.ip())?, 0),
+        None => unspecified,
+    };
+    socket.bind(&SockAddr::from(local))?;
+
+    Ok(socket.into())
+}
+
+/// QUIC transport for outgoing connections.
+///
+/// This transport is IO-stream based.
+#[derive(Clone)]
+pub struct QuicConnector {
+    hosts: Vec<String>,
+    server_name: String,
+    client_cfg: ClientConfig,
+    ip_version: IpVersion,
+    resolve_interval: Duration,
+    multi_interface: bool,
+    interface_filter: Arc<dyn Fn(&NetworkInterface) -> bool + Send + Sync>,
+}
+
+impl fmt::Debug for QuicConnector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("QuicConnector")
+            .field("hosts", &self.hosts)
+            .field("server_name", &self.server_name)
+            .field("ip_version", &self.ip_version)
+            .field("resolve_interval", &self.resolve_interval)
+            .field("multi_interface", &self.multi_interface)
+            .finish()
+    }
+}
+
+impl fmt::Display for QuicConnector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.hosts.len() > 1 {
+            write!(f, "[{}]", self.hosts.join(", "))
+        } else {
+            write!(f, "{}", &self.hosts[0])
+        }
+    }
+}
+
+impl QuicConnector {
+    /// Create a new QUIC transport for outgoing connections.
+    ///
+    /// `hosts` can contain IP addresses and hostnames, including port numbers.
+    /// If an entry does not specify a port number, the `default_port` is used.
+    ///
+    /// The certificate presented by the server is verified using `client_cfg`
+    /// and must be valid for `server_name`.
+    ///
+    /// It is checked at creation that `hosts` resolves to at least one IP address.
+    ///
+    /// Host name resolution is retried periodically, thus DNS updates will be taken
+    /// into account without the need to recreate this transport.
+    pub async fn new(
+        hosts: impl IntoIterator<Item = String>, default_port: u16, server_name: impl Into<String>,
+        client_cfg: ClientConfig,
+    ) -> Result<Self> {
+        let this = Self::unresolved(hosts, default_port, server_name, client_cfg).await?;
+
+        let addrs = this.resolve().await;
+        if addrs.is_empty() {
+            return Err(Error::new(ErrorKind::NotFound, "cannot resolve IP address of host"));
+        }
+        tracing::info!(%this, ?addrs, "hosts initially resolved");
+
+        Ok(this)
+    }
+
+    /// Create a new QUIC transport for outgoing connections without checking that at least one host can be resolved.
+    ///
+    /// `hosts` can contain IP addresses and hostnames, including port numbers.
+    /// If an entry does not specify a port number, the `default_port` is used.
+    ///
+    /// The certificate presented by the server is verified using `client_cfg`
+    /// and must be valid for `server_name`.
+    ///
+    /// Host name resolution is retried periodically, thus DNS updates will be taken
+    /// into account without the need to recreate this transport.
+    pub async fn unresolved(
+        hosts: impl IntoIterator<Item = String>, default_port: u16, server_name: impl Into<String>,
+        client_cfg: ClientConfig,
+    ) -> Result<Self> {
+        let mut hosts: Vec<_> = hosts.into_iter().collect();
+
+        if hosts.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "at least one host is required"));
+        }
+
+        for host in &mut hosts {
+            if !host.contains(':') {
+                host.push_str(&format!(":{default_port}"));
+            }
+        }
+
+        Ok(Self {
+            hosts,
+            server_name: server_name.into(),
+            client_cfg,
+            ip_version: IpVersion::Both,
+            resolve_interval: Duration::from_secs(10),
+            multi_interface: !cfg!(target_os = "android"),
+            interface_filter: Arc::new(|_| true),
+        })
+    }
+
+    /// Sets the IP version used for connecting.
+    pub fn set_ip_version(&mut self, ip_version: IpVersion) {
+        self.ip_version = ip_version;
+    }
+
+    /// Sets the interval for re-resolving the hostname and checking for changed network interfaces.
+    pub fn set_resolve_interval(&mut self, resolve_interval: Duration) {
+        self.resolve_interval = resolve_interval;
+    }
+
+    /// Sets whether all available local interfaces should be used for connecting.
+    ///
+    /// If this is true (default for non-Android platforms), a separate link is
+    /// established for each pair of server IP and local interface. Each outgoing socket
+    /// is explicitly bound to a local interface.
+    ///
+    /// If this is false (default for Android platform), one link is established for
+    /// each server IP. The operating system automatically assigns a local interface
+    /// for the outgoing socket.
+    pub fn set_multi_interface(&mut self, multi_interface: bool) {
+        self.multi_interface = multi_interface;
+    }
+
+    /// Sets the local interface filter.
+    ///
+    /// It is only used when multi interface is enabled.
+    ///
+    /// The provided function is called for each discoved local interface and should
+    /// return whether the interface should be used for establishing links.
+    ///
+    /// By default all local interfaces are used.
+    pub fn set_interface_filter(
+        &mut self, interface_filter: impl Fn(&NetworkInterface) -> bool + Send + Sync + 'static,
+    ) {
+        self.interface_filter = Arc::new(interface_filter);
+    }
+
+    /// Resolve target to socket addresses.
+    async fn resolve(&self) -> Vec<SocketAddr> {
+        util::resolve_hosts(&self.hosts, self.ip_version).await
+    }
+}
+
+#[async_trait]
+impl ConnectingTransport for QuicConnector {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    async fn link_tags(&self, tx: watch::Sender<HashSet<LinkTagBox>>) -> Result<()> {
+        loop {
+            let interfaces: Option<Vec<NetworkInterface>> = match self.multi_interface {
+                true => Some(
+                    util::local_interfaces()?
+                        .into_iter()
+                        .filter(|iface| (self.interface_filter)(iface))
+                        .collect(),
+                ),
+                false => None,
+            };
+
+            let mut tags: HashSet<LinkTagBox> = HashSet::new();
+            for addr in self.resolve().await {
+                match &interfaces {
+                    Some(interfaces) => {
+                        for iface in util::interface_names_for_target(interfaces, addr) {
+                            let tag = QuicLinkTag::new(Some(&iface), addr, Direction::Outgoing);
+                            tags.insert(Box::new(tag));
+                        }
+                    }
+                    None => {
+                        let tag = QuicLinkTag::new(None, addr, Direction::Outgoing);
+                        tags.insert(Box::new(tag));
+                    }
+                }
+            }
+
+            tx.send_if_modified(|v| {
+                if *v != tags {
+                    *v = tags;
+                    true
+                } else {
+                    false
+                }
+            });
+
+            sleep(self.resolve_interval).await;
+        }
+    }
+
+    async fn connect(&self, tag: &dyn LinkTag) -> Result<StreamBox> {
+        let tag: &QuicLinkTag = tag.as_any().downcast_ref().unwrap();
+
+        // Each link uses its own endpoint, so that its socket can be bound to the interface.
+        // The endpoint is kept running by the connection.
+        let socket = client_socket(tag.interface.as_deref(), tag.remote)?;
+        let endpoint = Endpoint::new(EndpointConfig::default(), None, socket, Arc::new(TokioRuntime))?;
+
+        let connection = endpoint
+            .connect_with(self.client_cfg.clone(), tag.remote, &self.server_name)
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?
+            .await?;
+
+        let (mut send, recv) = connection.open_bi().await?;
+        AsyncWriteExt::write_all(&mut send, STREAM_MAGIC).await?;
+
+        Ok(IoBox::new(recv, send).into())
+    }
+
+    async fn link_filter(&self, new: &Link<LinkTagBox>, existing: &[Link<LinkTagBox>]) -> bool {
+        let Some(new_tag) = new.tag().as_any().downcast_ref::<QuicLinkTag>() else { return true };
+
+        let intro = format!(
+            "Judging {} QUIC link {} {} ({}) on {}",
+            new.direction(),
+            match new.direction() {
+                Direction::Incoming => "from",
+                Direction::Outgoing => "to",
+            },
+            new_tag.remote,
+            String::from_utf8_lossy(new.remote_user_data()),
+            String::from_utf8_lossy(new_tag.interface.as_deref().unwrap_or(b"any interface"))
+        );
+
+        match existing.iter().find(|link| {
+            let Some(tag) = link.tag().as_any().downcast_ref::<QuicLinkTag>() else { return false };
+            tag.interface == new_tag.interface && link.remote_user_data() == new.remote_user_data()
+        }) {
+            Some(other) => {
+                let other_tag = other.tag().as_any().downcast_ref::<QuicLinkTag>().unwrap();
+                tracing::debug!("{intro} => link {} is redundant, rejecting.", other_tag.remote);
+                false
+            }
+            None => {
+                tracing::debug!("{intro} => accepted.");
+                true
+            }
+        }
+    }
+}
+
+/// QUIC transport for incoming connections.
+///
+/// This transport is IO-stream based.
+#[derive(Debug)]
+pub struct QuicAcceptor {
+    endpoints: Vec<Endpoint>,
+}
+
+impl fmt::Display for QuicAcceptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let addrs: Vec<_> = self.local_addrs().iter().map(|addr| addr.to_string()).collect();
+        if addrs.len() > 1 {
+            write!(f, "[{}]", addrs.join(", "))
+        } else {
+            write!(f, "{}", addrs[0])
+        }
+    }
+}
+
+impl QuicAcceptor {
+    /// Create a new QUIC transport listening for incoming connections.
+    ///
+    /// It listens on the local addresses specified in `addrs` and presents
+    /// the certificate from `server_cfg` to connecting clients.
+    pub async fn new(addrs: impl IntoIterator<Item = SocketAddr>, server_cfg: ServerConfig) -> Result<Self> {
+        let mut endpoints = Vec::new();
+
+        for addr in addrs {
+            let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+            if addr.is_ipv6() {
+                let _ = socket.set_only_v6(false);
+            }
+            socket.bind(&SockAddr::from(addr))?;
+
+            endpoints.push(Endpoint::new(
+                EndpointConfig::default(),
+                Some(server_cfg.clone()),
+                socket.into(),
+                Arc::new(TokioRuntime),
+            )?);
+        }
+
+        Self::from_endpoints(endpoints)
+    }
+
+    /// Create a new QUIC transport for incoming connections using the specified QUIC endpoints.
+    ///
+    /// The endpoints must have a server configuration.
+    pub fn from_endpoints(endpoints: impl IntoIterator<Item = Endpoint>) -> Result<Self> {
+        let endpoints: Vec<_> = endpoints.into_iter().collect();
+
+        if endpoints.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "at least one endpoint is required"));
+        }
+
+        Ok(Self { endpoints })
+    }
+
+    /// The local addresses the transport is listening on.
+    pub fn local_addrs(&self) -> Vec<SocketAddr> {
+        self.endpoints.iter().filter_map(|endpoint| endpoint.local_addr().ok()).collect()
+    }
+
+    /// Establishes the connection of an incoming link and accepts its stream.
+    async fn accept_link(incoming: Incoming) -> Result<AcceptedStreamBox> {
+        let mut remote = incoming.remote_address();
+        let local_ip = incoming.local_ip();
+
+        let connection = incoming.await?;
+        let (send, mut recv) = timeout(STREAM_TIMEOUT, connection.accept_bi())
+            .await
+            .map_err(|_| Error::new(ErrorKind::TimedOut, "timeout waiting for stream"))??;
+
+        let mut magic = [0; STREAM_MAGIC.len()];
+        timeout(STREAM_TIMEOUT, AsyncReadExt::read_exact(&mut recv, &mut magic))
+            .await
+            .map_err(|_| Error::new(ErrorKind::TimedOut, "timeout waiting for stream"))??;
+        if magic != STREAM_MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "invalid stream header"));
+        }
+
+        // Use proper IPv4 addresses.
+        util::use_proper_ipv4(&mut remote);
+
+        // Find local interface.
+        let interface = match local_ip {
+            Some(local_ip) => {
+                let local_ip = local_ip.to_canonical();
+                match util::local_interface_for_ip(local_ip)? {
+                    Some(interface) => Some(interface),
+                    None => {
+                        return Err(Error::new(
+                            ErrorKind::NotFound,
+                            format!("interface for incoming connection from {remote} to {local_ip} not found"),
+                        ))
+                    }
+                }
+            }
+            None => None,
+        };
+
+        tracing::debug!(%remote, interface =% String::from_utf8_lossy(interface.as_deref().unwrap_or_default()), "Accepted QUIC connection");
+        let tag = QuicLinkTag::new(interface.as_deref(), remote, Direction::Incoming);
+
+        Ok(AcceptedStreamBox::new(IoBox::new(recv, send).into(), tag))
+    }
+}
+
+#[async_trait]
+impl AcceptingTransport for QuicAcceptor {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    async fn listen(&self, tx: mpsc::Sender<AcceptedStreamBox>) -> Result<()> {
+        loop {
+            // Accept incoming connection.
+            let (res, _, _) =
+                future::select_all(self.endpoints.iter().map(|endpoint| endpoint.accept().boxed())).await;
+            let Some(incoming) = res else {
+                return Err(Error::new(ErrorKind::ConnectionAborted, "QUIC endpoint was closed"));
+            };
+
+            // Perform QUIC handshake without blocking acceptance of other connections.
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let remote = incoming.remote_address();
+                match Self::accept_link(incoming).await {
+                    Ok(accepted) => {
+                        let _ = tx.send(accepted).await;
+                    }
+                    Err(err) => tracing::warn!(%remote, %err, "cannot accept QUIC connection"),
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! QUIC transport tests over loopback.
+
+use futures::join;
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use aggligator::transport::{Acceptor, Connector};
+use aggligator_transport_quic::{
+    quinn::{
+        rustls::{
+            pki_types::{CertificateDer, PrivatePkcs8KeyDer},
+            RootCertStore,
+        },
+        ClientConfig, ServerConfig,
+    },
+    QuicAcceptor, QuicConnector,
+};
+
+/// Creates configurations using a self-signed certificate for `localhost`.
+fn configs() -> (ServerConfig, ClientConfig) {
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+    let key = PrivatePkcs8KeyDer::from(cert.signing_key.serialize_der());
+    let cert = CertificateDer::from(cert.cert);
+
+    let server_cfg = ServerConfig::with_single_cert(vec![cert.clone()], key.into()).unwrap();
+
+    let mut roots = RootCertStore::empty();
+    roots.add(cert).unwrap();
+    let client_cfg = ClientConfig::with_root_certificates(Arc::new(roots)).unwrap();
+
+    (server_cfg, client_cfg)
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn loopback() {
+    const DATA: &[u8] = b"Hello over QUIC!";
+
+    let (server_cfg, client_cfg) = configs();
+
+    let quic_acceptor =
+        QuicAcceptor::new([SocketAddr::from((Ipv4Addr::LOCALHOST, 0))], server_cfg).await.unwrap();
+    let port = quic_acceptor.local_addrs()[0].port();
+    let acceptor = Acceptor::new();
+    acceptor.add(quic_acceptor);
+
+    let mut quic_connector =
+        QuicConnector::new([Ipv4Addr::LOCALHOST.to_string()], port, "localhost", client_cfg).await.unwrap();
+    quic_connector.set_multi_interface(false);
+    let mut connector = Connector::new();
+    connector.add(quic_connector);
+    let outgoing = connector.channel().unwrap();
+
+    let server_task = async {
+        let (ch, _control) = acceptor.accept().await.unwrap();
+        let mut stream = ch.into_stream();
+
+        let mut buf = vec![0; DATA.len()];
+        stream.read_exact(&mut buf).await.unwrap();
+        stream.write_all(&buf).await.unwrap();
+        stream.flush().await.unwrap();
+    };
+
+    let client_task = async {
+        let mut stream = outgoing.await.unwrap().into_stream();
+        stream.write_all(DATA).await.unwrap();
+        stream.flush().await.unwrap();
+
+        let mut buf = vec![0; DATA.len()];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, DATA);
+
+        let links = connector.control().links();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].tag().transport_name(), "quic");
+    };
+
+    join!(server_task, client_task);
+}
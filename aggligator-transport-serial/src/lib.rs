@@ -0,0 +1,371 @@
+#![warn(missing_docs)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/surban/aggligator/master/.misc/aggligator.png",
+    html_favicon_url = "https://raw.githubusercontent.com/surban/aggligator/master/.misc/aggligator.png",
+    issue_tracker_base_url = "https://github.com/surban/aggligator/issues/"
+)]
+
+//! [Aggligator](aggligator) transport: serial ports
+//!
+//! Each configured serial device, for example `/dev/ttyUSB0`, `/dev/ttyACM0`
+//! or a pseudo terminal, provides one link.
+//! Both ends of a serial line must be configured with the same baud rate and flow control.
+//!
+//! Since a serial line does not provide connections, data is framed using the
+//! [integrity codec](aggligator::io::IntegrityCodec), which detects corrupted and lost data.
+//! When this happens the link fails and the device is reopened with its buffers cleared,
+//! so that a new link can be established over it.
+//! The [`SerialConnector`] reconnects using the reconnect delay of the
+//! [connector](aggligator::transport::Connector) and the [`SerialAcceptor`] reopens
+//! the device after its [reopen delay](SerialAcceptor::set_reopen_delay).
+
+use async_trait::async_trait;
+use futures::future;
+use std::{
+    any::Any,
+    cmp::Ordering,
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    io::{split, AsyncRead, AsyncWrite, ReadBuf},
+    sync::{mpsc, oneshot, watch},
+    time::sleep,
+};
+use tokio_serial::{ClearBuffer, SerialPort, SerialPortBuilderExt, SerialStream};
+
+use aggligator::{
+    control::Direction,
+    io::{IoBox, StreamBox},
+    transport::{AcceptedStreamBox, AcceptingTransport, ConnectingTransport, LinkTag, LinkTagBox},
+};
+
+pub use tokio_serial::FlowControl;
+
+static NAME: &str = "serial";
+
+/// Default interval for checking the presence of serial devices.
+const PROBE_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Link tag for serial link.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SerialLinkTag {
+    /// Path of the serial device.
+    pub device: PathBuf,
+    /// Link direction.
+    pub direction: Direction,
+}
+
+impl fmt::Display for SerialLinkTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dir = match self.direction {
+            Direction::Incoming => "<-",
+            Direction::Outgoing => "->",
+        };
+        write!(f, "{dir} {}", self.device.display())
+    }
+}
+
+impl SerialLinkTag {
+    /// Creates a new link tag for a serial link.
+    pub fn new(device: impl Into<PathBuf>, direction: Direction) -> Self {
+        Self { device: device.into(), direction }
+    }
+}
+
+impl LinkTag for SerialLinkTag {
+    fn transport_name(&self) -> &str {
+        NAME
+    }
+
+    fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    fn user_data(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn box_clone(&self) -> LinkTagBox {
+        Box::new(self.clone())
+    }
+
+    fn dyn_cmp(&self, other: &dyn LinkTag) -> Ordering {
+        let other = other.as_any().downcast_ref::<Self>().unwrap();
+        Ord::cmp(self, other)
+    }
+
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        Hash::hash(self, &mut state)
+    }
+}
+
+/// Serial port settings.
+#[derive(Debug, Clone)]
+struct Settings {
+    baud_rate: u32,
+    flow_control: FlowControl,
+}
+
+impl Settings {
+    /// Opens the serial device and discards all data buffered in it.
+    ///
+    /// This removes leftovers of a previous link.
+    fn open(&self, device: &Path) -> Result<SerialStream> {
+        let stream = tokio_serial::new(device.to_string_lossy(), self.baud_rate)
+            .flow_control(self.flow_control)
+            .open_native_async()?;
+        stream.clear(ClearBuffer::All)?;
+        Ok(stream)
+    }
+}
+
+fn check_devices(devices: &[PathBuf]) -> Result<()> {
+    if devices.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "at least one serial device is required"));
+    }
+    Ok(())
+}
+
+fn fmt_devices(f: &mut fmt::Formatter, devices: &[PathBuf]) -> fmt::Result {
+    let devices: Vec<_> = devices.iter().map(|device| device.display().to_string()).collect();
+    if devices.len() > 1 {
+        write!(f, "[{}]", devices.join(", "))
+    } else {
+        write!(f, "{}", devices[0])
+    }
+}
+
+/// Serial transport for outgoing links.
+///
+/// One link is established over each present serial device.
+///
+/// This transport is IO-stream based.
+#[derive(Debug, Clone)]
+pub struct SerialConnector {
+    devices: Vec<PathBuf>,
+    settings: Settings,
+    probe_interval: Duration,
+}
+
+impl fmt::Display for SerialConnector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_devices(f, &self.devices)
+    }
+}
+
+impl SerialConnector {
+    /// Create a new serial transport for outgoing links.
+    ///
+    /// A link is established over each device specified in `devices`,
+    /// using the specified baud rate.
+    pub fn new(devices: impl IntoIterator<Item = impl Into<PathBuf>>, baud_rate: u32) -> Result<Self> {
+        let devices: Vec<_> = devices.into_iter().map(|device| device.into()).collect();
+        check_devices(&devices)?;
+
+        Ok(Self {
+            devices,
+            settings: Settings { baud_rate, flow_control: FlowControl::None },
+            probe_interval: PROBE_INTERVAL,
+        })
+    }
+
+    /// The serial devices.
+    pub fn devices(&self) -> &[PathBuf] {
+        &self.devices
+    }
+
+    /// Sets the flow control mode of the serial devices.
+    ///
+    /// By default no flow control is used.
+    pub fn set_flow_control(&mut self, flow_control: FlowControl) {
+        self.settings.flow_control = flow_control;
+    }
+
+    /// Sets the interval for checking whether the serial devices are present.
+    ///
+    /// This allows links to be established over serial devices that are plugged in later.
+    pub fn set_probe_interval(&mut self, probe_interval: Duration) {
+        self.probe_interval = probe_interval;
+    }
+}
+
+#[async_trait]
+impl ConnectingTransport for SerialConnector {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    async fn link_tags(&self, tx: watch::Sender<HashSet<LinkTagBox>>) -> Result<()> {
+        loop {
+            let tags: HashSet<LinkTagBox> = self
+                .devices
+                .iter()
+                .filter(|device| device.exists())
+                .map(|device| Box::new(SerialLinkTag::new(device, Direction::Outgoing)) as LinkTagBox)
+                .collect();
+
+            tx.send_if_modified(|v| {
+                if *v != tags {
+                    *v = tags;
+                    true
+                } else {
+                    false
+                }
+            });
+
+            sleep(self.probe_interval).await;
+        }
+    }
+
+    async fn connect(&self, tag: &dyn LinkTag) -> Result<StreamBox> {
+        let tag: &SerialLinkTag = tag.as_any().downcast_ref().unwrap();
+
+        let stream = self.settings.open(&tag.device)?;
+        let (rh, wh) = split(stream);
+        Ok(IoBox::new(rh, wh).into())
+    }
+}
+
+/// Serial transport for incoming links.
+///
+/// Each serial device is opened and provides an incoming link.
+/// When the link terminates, the device is reopened to accept the next link.
+///
+/// This transport is IO-stream based.
+#[derive(Debug, Clone)]
+pub struct SerialAcceptor {
+    devices: Vec<PathBuf>,
+    settings: Settings,
+    reopen_delay: Duration,
+}
+
+impl fmt::Display for SerialAcceptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_devices(f, &self.devices)
+    }
+}
+
+impl SerialAcceptor {
+    /// Create a new serial transport for incoming links.
+    ///
+    /// It accepts links over each device specified in `devices`,
+    /// using the specified baud rate.
+    pub fn new(devices: impl IntoIterator<Item = impl Into<PathBuf>>, baud_rate: u32) -> Result<Self> {
+        let devices: Vec<_> = devices.into_iter().map(|device| device.into()).collect();
+        check_devices(&devices)?;
+
+        Ok(Self {
+            devices,
+            settings: Settings { baud_rate, flow_control: FlowControl::None },
+            reopen_delay: Duration::from_secs(1),
+        })
+    }
+
+    /// The serial devices.
+    pub fn devices(&self) -> &[PathBuf] {
+        &self.devices
+    }
+
+    /// Sets the flow control mode of the serial devices.
+    ///
+    /// By default no flow control is used.
+    pub fn set_flow_control(&mut self, flow_control: FlowControl) {
+        self.settings.flow_control = flow_control;
+    }
+
+    /// Sets the delay before a serial device is reopened after its link has
+    /// terminated or opening it has failed.
+    ///
+    /// By default this is one second.
+    pub fn set_reopen_delay(&mut self, reopen_delay: Duration) {
+        self.reopen_delay = reopen_delay;
+    }
+
+    /// Accepts links over the specified serial device.
+    async fn listen_device(&self, device: &Path, tx: &mpsc::Sender<AcceptedStreamBox>) {
+        loop {
+            match self.settings.open(device) {
+                Ok(stream) => {
+                    tracing::debug!(device =% device.display(), "opened serial device");
+
+                    let (closed_tx, closed_rx) = oneshot::channel();
+                    let closed_tx = Arc::new(closed_tx);
+                    let (rh, wh) = split(stream);
+                    let io = IoBox::new(
+                        Guarded { inner: rh, _guard: closed_tx.clone() },
+                        Guarded { inner: wh, _guard: closed_tx },
+                    );
+
+                    let tag = SerialLinkTag::new(device, Direction::Incoming);
+                    if tx.send(AcceptedStreamBox::new(io.into(), tag)).await.is_err() {
+                        return;
+                    }
+
+                    // Wait until the link has released the device.
+                    let _ = closed_rx.await;
+                    tracing::debug!(device =% device.display(), "serial device released");
+                }
+                Err(err) => tracing::debug!(device =% device.display(), %err, "cannot open serial device"),
+            }
+
+            sleep(self.reopen_delay).await;
+        }
+    }
+}
+
+#[async_trait]
+impl AcceptingTransport for SerialAcceptor {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    async fn listen(&self, tx: mpsc::Sender<AcceptedStreamBox>) -> Result<()> {
+        future::join_all(self.devices.iter().map(|device| self.listen_device(device, &tx))).await;
+        Ok(())
+    }
+}
+
+/// IO stream half that notifies when both halves have been dropped.
+struct Guarded<T> {
+    inner: T,
+    _guard: Arc<oneshot::Sender<()>>,
+}
+
+impl<T> AsyncRead for Guarded<T>
+where
+    T: AsyncRead + Unpin,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl<T> AsyncWrite for Guarded<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,103 @@
+//! Serial transport tests over pseudo terminals.
+
+#![cfg(unix)]
+
+use futures::join;
+use std::{path::PathBuf, time::Duration};
+use tokio::{
+    io::{copy_bidirectional, AsyncReadExt, AsyncWriteExt},
+    time::timeout,
+};
+use tokio_serial::{SerialPort, SerialStream};
+
+use aggligator::{
+    cfg::Cfg,
+    transport::{AcceptorBuilder, ConnectorBuilder},
+};
+use aggligator_transport_serial::{SerialAcceptor, SerialConnector};
+
+const BAUD_RATE: u32 = 115_200;
+
+/// Creates a serial line between two pseudo terminals.
+///
+/// Returns the device paths of both ends and the terminal handles, which must be kept open.
+fn serial_line() -> ([PathBuf; 2], [SerialStream; 2]) {
+    let (mut master_a, slave_a) = SerialStream::pair().unwrap();
+    let (mut master_b, slave_b) = SerialStream::pair().unwrap();
+    tokio::spawn(async move {
+        let _ = copy_bidirectional(&mut master_a, &mut master_b).await;
+    });
+
+    let paths = [slave_a.name().unwrap().into(), slave_b.name().unwrap().into()];
+    (paths, [slave_a, slave_b])
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn reconnect_after_corruption() {
+    const DATA: &[u8] = b"Hello over a serial line!";
+
+    let ([client_dev, server_dev], [mut client_pty, _server_pty]) = serial_line();
+
+    let cfg = Cfg { link_ping_timeout: Duration::from_secs(2), ..Default::default() };
+
+    let acceptor = AcceptorBuilder::new(cfg.clone()).build();
+    let mut serial_acceptor = SerialAcceptor::new([&server_dev], BAUD_RATE).unwrap();
+    serial_acceptor.set_reopen_delay(Duration::from_millis(100));
+    acceptor.add(serial_acceptor);
+
+    let mut builder = ConnectorBuilder::new(cfg);
+    builder.set_reconnect_delay(Duration::from_millis(500));
+    let mut connector = builder.build();
+    let mut serial_connector = SerialConnector::new([&client_dev], BAUD_RATE).unwrap();
+    serial_connector.set_probe_interval(Duration::from_millis(100));
+    connector.add(serial_connector);
+    let outgoing = connector.channel().unwrap();
+
+    let server_task = async {
+        let (ch, _control) = acceptor.accept().await.unwrap();
+        let mut stream = ch.into_stream();
+
+        for _ in 0..2 {
+            let mut buf = vec![0; DATA.len()];
+            stream.read_exact(&mut buf).await.unwrap();
+            stream.write_all(&buf).await.unwrap();
+            stream.flush().await.unwrap();
+        }
+    };
+
+    let client_task = async {
+        let mut stream = outgoing.await.unwrap().into_stream();
+        let control = connector.control();
+
+        stream.write_all(DATA).await.unwrap();
+        stream.flush().await.unwrap();
+        let mut buf = vec![0; DATA.len()];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, DATA);
+
+        // Corrupt the serial line.
+        let link = control.links().pop().unwrap();
+        client_pty.write_all(&[0xff; 64]).await.unwrap();
+        client_pty.flush().await.unwrap();
+        let reason = timeout(Duration::from_secs(10), link.disconnected()).await.unwrap();
+        tracing::info!(%reason, "link failed due to corruption");
+
+        // Wait for link to be reestablished.
+        timeout(Duration::from_secs(30), async {
+            let mut control = control.clone();
+            while control.links().is_empty() {
+                control.links_changed().await;
+            }
+        })
+        .await
+        .unwrap();
+
+        stream.write_all(DATA).await.unwrap();
+        stream.flush().await.unwrap();
+        let mut buf = vec![0; DATA.len()];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, DATA);
+    };
+
+    join!(server_task, client_task);
+}
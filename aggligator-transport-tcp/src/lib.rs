@@ -11,27 +11,40 @@
 //! #### Simple aggregation of TCP links
 //! Use the [tcp_connect](simple::tcp_connect) and [tcp_server](simple::tcp_server) functions
 //! from the [simple module](simple).
+//!
+//! #### Proxies
+//! Outgoing links can be established through SOCKS5 and HTTP proxies,
+//! see [`TcpConnector::add_proxy`] and the [proxy module](proxy).
+//!
+//! #### Policy routing
+//! With multiple uplinks behind policy routing, binding a link to its local interface
+//! may not be sufficient to make its packets leave through the intended gateway.
+//! Thus a firewall mark (Linux only) and a source address can be configured for outgoing
+//! links, globally or per local interface, using [`TcpSocketOptions`].
+//! A routing table is selected by a routing rule matching the firewall mark,
+//! for example `ip rule add fwmark 1 table 100`.
 
 use aggligator::io::{IoBox, StreamBox};
 use async_trait::async_trait;
 use futures::{future, FutureExt};
 use network_interface::Addr;
-use socket2::SockRef;
+use socket2::{SockRef, TcpKeepalive};
 use std::{
     any::Any,
     cmp::Ordering,
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fmt,
     hash::{Hash, Hasher},
     io::{Error, ErrorKind, Result},
     net::{IpAddr, SocketAddr},
-    sync::Arc,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
     time::Duration,
 };
 use tokio::{
     net::{TcpListener, TcpSocket, TcpStream},
     sync::{mpsc, watch},
-    time::sleep,
+    time::{sleep, timeout},
 };
 
 use aggligator::{
@@ -39,8 +52,10 @@ use aggligator::{
     transport::{AcceptedStreamBox, AcceptingTransport, ConnectingTransport, LinkTag, LinkTagBox},
     Link,
 };
+use proxy::TcpProxy;
 use util::NetworkInterface;
 
+pub mod proxy;
 pub mod simple;
 pub mod util;
 
@@ -53,11 +68,38 @@ pub struct TcpSocketOptions {
     send_buffer_size: Option<usize>,
     recv_buffer_size: Option<usize>,
     tos_v4: Option<u8>,
+    fwmark: Option<u32>,
+    interface_fwmarks: HashMap<Vec<u8>, u32>,
+    source_addrs: Vec<IpAddr>,
+    interface_source_addrs: HashMap<Vec<u8>, Vec<IpAddr>>,
+    keepalive_time: Option<Duration>,
+    keepalive_interval: Option<Duration>,
+    keepalive_retries: Option<u32>,
+    user_timeout: Option<Duration>,
+    tclass_v6: Option<u32>,
+    congestion: Option<String>,
+    interface_congestions: HashMap<Vec<u8>, String>,
 }
 
 impl Default for TcpSocketOptions {
     fn default() -> Self {
-        Self { nodelay: Some(true), send_buffer_size: None, recv_buffer_size: None, tos_v4: None }
+        Self {
+            nodelay: Some(true),
+            send_buffer_size: None,
+            recv_buffer_size: None,
+            tos_v4: None,
+            fwmark: None,
+            interface_fwmarks: HashMap::new(),
+            source_addrs: Vec::new(),
+            interface_source_addrs: HashMap::new(),
+            keepalive_time: None,
+            keepalive_interval: None,
+            keepalive_retries: None,
+            user_timeout: None,
+            tclass_v6: None,
+            congestion: None,
+            interface_congestions: HashMap::new(),
+        }
     }
 }
 
@@ -72,6 +114,7 @@ impl TcpSocketOptions {
             send_buffer_size: Some(512 * 1024),
             recv_buffer_size: Some(512 * 1024),
             tos_v4: Some(0x10),
+            ..Default::default()
         }
     }
 
@@ -115,8 +158,162 @@ impl TcpSocketOptions {
         self.tos_v4 = tos;
     }
 
+    /// Returns the firewall mark applied to outgoing links without an interface-specific mark.
+    pub fn fwmark(&self) -> Option<u32> {
+        self.fwmark
+    }
+
+    /// Sets the firewall mark (`SO_MARK`) applied to outgoing links (`None` leaves it unset).
+    ///
+    /// This is only supported on Linux and requires the `CAP_NET_ADMIN` capability.
+    pub fn set_fwmark(&mut self, fwmark: Option<u32>) {
+        self.fwmark = fwmark;
+    }
+
+    /// Sets the firewall mark applied to outgoing links from the specified local interface.
+    ///
+    /// This takes precedence over the mark set by [`set_fwmark`](Self::set_fwmark).
+    pub fn set_interface_fwmark(&mut self, interface: impl AsRef<[u8]>, fwmark: Option<u32>) {
+        let interface = interface.as_ref().to_vec();
+        match fwmark {
+            Some(fwmark) => self.interface_fwmarks.insert(interface, fwmark),
+            None => self.interface_fwmarks.remove(&interface),
+        };
+    }
+
+    /// Returns the firewall mark for an outgoing link from the specified local interface.
+    pub fn fwmark_for(&self, interface: Option<&[u8]>) -> Option<u32> {
+        interface.and_then(|iface| self.interface_fwmarks.get(iface).copied()).or(self.fwmark)
+    }
+
+    /// Sets the source addresses used for outgoing links without interface-specific source addresses.
+    ///
+    /// For each link the first address of the same IP version as the remote address is used.
+    pub fn set_source_addrs(&mut self, addrs: impl IntoIterator<Item = IpAddr>) {
+        self.source_addrs = addrs.into_iter().collect();
+    }
+
+    /// Sets the source addresses used for outgoing links from the specified local interface.
+    ///
+    /// For each link the first address of the same IP version as the remote address is used.
+    /// This takes precedence over the addresses set by [`set_source_addrs`](Self::set_source_addrs).
+    pub fn set_interface_source_addrs(
+        &mut self, interface: impl AsRef<[u8]>, addrs: impl IntoIterator<Item = IpAddr>,
+    ) {
+        let interface = interface.as_ref().to_vec();
+        let addrs: Vec<_> = addrs.into_iter().collect();
+        if addrs.is_empty() {
+            self.interface_source_addrs.remove(&interface);
+        } else {
+            self.interface_source_addrs.insert(interface, addrs);
+        }
+    }
+
+    /// Returns the source address for an outgoing link from the specified local interface to `remote`.
+    pub fn source_addr_for(&self, interface: Option<&[u8]>, remote: IpAddr) -> Option<IpAddr> {
+        let addrs =
+            interface.and_then(|iface| self.interface_source_addrs.get(iface)).unwrap_or(&self.source_addrs);
+        addrs.iter().find(|addr| addr.is_ipv4() == remote.is_ipv4()).copied()
+    }
+
+    /// Returns the idle time before TCP keepalive probes are sent.
+    pub fn keepalive_time(&self) -> Option<Duration> {
+        self.keepalive_time
+    }
+
+    /// Enables TCP keepalive and sets the idle time before probes are sent (`None` keeps the OS default).
+    ///
+    /// TCP keepalive is enabled if any of the keepalive parameters is set.
+    pub fn set_keepalive_time(&mut self, time: Option<Duration>) {
+        self.keepalive_time = time;
+    }
+
+    /// Returns the interval between TCP keepalive probes.
+    pub fn keepalive_interval(&self) -> Option<Duration> {
+        self.keepalive_interval
+    }
+
+    /// Enables TCP keepalive and sets the interval between probes (`None` keeps the OS default).
+    pub fn set_keepalive_interval(&mut self, interval: Option<Duration>) {
+        self.keepalive_interval = interval;
+    }
+
+    /// Returns the number of unanswered TCP keepalive probes before the connection is dropped.
+    pub fn keepalive_retries(&self) -> Option<u32> {
+        self.keepalive_retries
+    }
+
+    /// Enables TCP keepalive and sets the number of unanswered probes before the connection
+    /// is dropped (`None` keeps the OS default).
+    pub fn set_keepalive_retries(&mut self, retries: Option<u32>) {
+        self.keepalive_retries = retries;
+    }
+
+    /// Returns the maximum time transmitted data may remain unacknowledged.
+    pub fn user_timeout(&self) -> Option<Duration> {
+        self.user_timeout
+    }
+
+    /// Sets the maximum time transmitted data may remain unacknowledged before
+    /// the connection is dropped (`TCP_USER_TIMEOUT`, Linux only).
+    ///
+    /// Setting this below the link ping timeout of the connection lets the kernel
+    /// detect dead links first.
+    pub fn set_user_timeout(&mut self, timeout: Option<Duration>) {
+        self.user_timeout = timeout;
+    }
+
+    /// Returns the IPv6 traffic class that will be applied to outgoing packets.
+    pub fn tclass_v6(&self) -> Option<u32> {
+        self.tclass_v6
+    }
+
+    /// Sets the IPv6 traffic class (`None` disables the override).
+    pub fn set_tclass_v6(&mut self, tclass: Option<u32>) {
+        self.tclass_v6 = tclass;
+    }
+
+    /// Returns the congestion control algorithm used for links without an interface-specific algorithm.
+    pub fn congestion(&self) -> Option<&str> {
+        self.congestion.as_deref()
+    }
+
+    /// Sets the TCP congestion control algorithm, for example `bbr` or `cubic`
+    /// (`TCP_CONGESTION`, Linux and FreeBSD only, `None` keeps the OS default).
+    ///
+    /// The algorithm must be available in the kernel.
+    pub fn set_congestion(&mut self, congestion: Option<String>) {
+        self.congestion = congestion;
+    }
+
+    /// Sets the TCP congestion control algorithm used for links on the specified local interface.
+    ///
+    /// This takes precedence over the algorithm set by [`set_congestion`](Self::set_congestion).
+    pub fn set_interface_congestion(&mut self, interface: impl AsRef<[u8]>, congestion: Option<String>) {
+        let interface = interface.as_ref().to_vec();
+        match congestion {
+            Some(congestion) => self.interface_congestions.insert(interface, congestion),
+            None => self.interface_congestions.remove(&interface),
+        };
+    }
+
+    /// Returns the congestion control algorithm for a link on the specified local interface.
+    pub fn congestion_for(&self, interface: Option<&[u8]>) -> Option<&str> {
+        interface
+            .and_then(|iface| self.interface_congestions.get(iface))
+            .or(self.congestion.as_ref())
+            .map(|congestion| congestion.as_str())
+    }
+
     /// Apply all configured options to a connected TCP stream.
     pub fn apply_to_stream(&self, stream: &TcpStream, remote: &SocketAddr) -> Result<()> {
+        self.apply_to_interface_stream(stream, remote, None)
+    }
+
+    /// Apply all configured options to a connected TCP stream of a link on the specified local interface.
+    pub fn apply_to_interface_stream(
+        &self, stream: &TcpStream, remote: &SocketAddr, interface: Option<&[u8]>,
+    ) -> Result<()> {
         if let Some(nodelay) = self.nodelay {
             stream.set_nodelay(nodelay)?;
         }
@@ -144,6 +341,65 @@ impl TcpSocketOptions {
             }
         }
 
+        #[cfg(any(
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "fuchsia",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "netbsd",
+            target_os = "openbsd",
+        ))]
+        if let Some(tclass) = self.tclass_v6 {
+            if remote.is_ipv6() {
+                sock.set_tclass_v6(tclass)?;
+            }
+        }
+
+        if self.keepalive_time.is_some() || self.keepalive_interval.is_some() || self.keepalive_retries.is_some()
+        {
+            let mut keepalive = TcpKeepalive::new();
+            if let Some(time) = self.keepalive_time {
+                keepalive = keepalive.with_time(time);
+            }
+            #[cfg(any(
+                target_os = "android",
+                target_os = "freebsd",
+                target_os = "fuchsia",
+                target_os = "linux",
+                target_os = "macos",
+                target_os = "netbsd",
+                target_os = "windows",
+            ))]
+            if let Some(interval) = self.keepalive_interval {
+                keepalive = keepalive.with_interval(interval);
+            }
+            #[cfg(any(
+                target_os = "android",
+                target_os = "freebsd",
+                target_os = "fuchsia",
+                target_os = "linux",
+                target_os = "macos",
+                target_os = "netbsd",
+            ))]
+            if let Some(retries) = self.keepalive_retries {
+                keepalive = keepalive.with_retries(retries);
+            }
+            sock.set_tcp_keepalive(&keepalive)?;
+        }
+
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        if self.user_timeout.is_some() {
+            sock.set_tcp_user_timeout(self.user_timeout)?;
+        }
+
+        #[cfg(any(target_os = "freebsd", target_os = "linux"))]
+        if let Some(congestion) = self.congestion_for(interface) {
+            sock.set_tcp_congestion(congestion.as_bytes())?;
+        }
+        #[cfg(not(any(target_os = "freebsd", target_os = "linux")))]
+        let _ = interface;
+
         Ok(())
     }
 }
@@ -190,9 +446,17 @@ pub struct TcpLinkTag {
     /// Local interface name.
     pub interface: Option<Vec<u8>>,
     /// Remote address.
+    ///
+    /// For a link established through a proxy this is the address of the proxy.
     pub remote: SocketAddr,
     /// Link direction.
     pub direction: Direction,
+    /// Target host and port requested from the proxy, if the link is established through a proxy.
+    pub target: Option<String>,
+    /// Local source address of an outgoing link.
+    pub source: Option<IpAddr>,
+    /// Firewall mark of an outgoing link.
+    pub fwmark: Option<u32>,
 }
 
 impl fmt::Display for TcpLinkTag {
@@ -201,19 +465,47 @@ impl fmt::Display for TcpLinkTag {
             Direction::Incoming => "<-",
             Direction::Outgoing => "->",
         };
-        write!(
-            f,
-            "{:16} {dir} {}",
-            String::from_utf8_lossy(self.interface.as_deref().unwrap_or_default()),
-            self.remote
-        )
+        write!(f, "{:16} {dir} ", String::from_utf8_lossy(self.interface.as_deref().unwrap_or_default()))?;
+        match &self.target {
+            Some(target) => write!(f, "{target}")?,
+            None => write!(f, "{}", self.remote)?,
+        }
+        if let Some(source) = &self.source {
+            write!(f, " from {source}")?;
+        }
+        if let Some(fwmark) = self.fwmark {
+            write!(f, " mark {fwmark:#x}")?;
+        }
+        if let Some(proxy) = self.proxy() {
+            write!(f, " via {proxy}")?;
+        }
+        Ok(())
     }
 }
 
 impl TcpLinkTag {
     /// Creates a new link tag for a TCP link.
     pub fn new(interface: Option<&[u8]>, remote: SocketAddr, direction: Direction) -> Self {
-        Self { interface: interface.map(|iface| iface.to_vec()), remote, direction }
+        Self {
+            interface: interface.map(|iface| iface.to_vec()),
+            remote,
+            direction,
+            target: None,
+            source: None,
+            fwmark: None,
+        }
+    }
+
+    /// Creates a new link tag for an outgoing TCP link to `target` established through the proxy at `proxy`.
+    ///
+    /// `target` consists of a host name or IP address and a port number.
+    pub fn with_proxy(interface: Option<&[u8]>, proxy: SocketAddr, target: &str) -> Self {
+        Self { target: Some(target.to_string()), ..Self::new(interface, proxy, Direction::Outgoing) }
+    }
+
+    /// Address of the proxy used for connecting.
+    pub fn proxy(&self) -> Option<SocketAddr> {
+        self.target.as_ref().map(|_| self.remote)
     }
 }
 
@@ -264,6 +556,34 @@ pub enum TcpLinkFilter {
     ///
     /// One link for each pair of local interface and remote IP address is established.
     InterfaceIp,
+    /// Filter based on local interface and remote interface, allowing parallel links.
+    ///
+    /// Up to the specified number of links for each pair of local interface and
+    /// remote interface are established.
+    /// This is useful when the server listens on multiple ports, see [`TcpConnector::new`],
+    /// since a single TCP flow often cannot fill a path with a high bandwidth-delay product.
+    Parallel(NonZeroUsize),
+}
+
+/// Host and local interface of a staggered connection attempt.
+type AttemptGroup = (String, Option<Vec<u8>>);
+
+/// Staggered connection attempt of a link tag.
+#[derive(Debug, Clone)]
+struct StaggeredAttempt {
+    /// Host and local interface the attempt belongs to.
+    group: AttemptGroup,
+    /// Delay of the first connection attempt, `None` once it has been attempted.
+    delay: Option<Duration>,
+}
+
+/// Staggered connection attempts of a TCP connector.
+#[derive(Debug, Default)]
+struct StaggeredAttempts {
+    /// Attempt of each link tag.
+    attempts: HashMap<TcpLinkTag, StaggeredAttempt>,
+    /// Hosts and local interfaces over which a connection has been established.
+    connected: HashSet<AttemptGroup>,
 }
 
 /// TCP transport for outgoing connections.
@@ -278,6 +598,10 @@ pub struct TcpConnector {
     multi_interface: bool,
     interface_filter: Arc<dyn Fn(&NetworkInterface) -> bool + Send + Sync>,
     socket_options: TcpSocketOptions,
+    proxies: Vec<TcpProxy>,
+    connect_timeout: Duration,
+    attempt_delay: Duration,
+    staggered: Arc<Mutex<StaggeredAttempts>>,
 }
 
 impl fmt::Debug for TcpConnector {
@@ -289,6 +613,9 @@ impl fmt::Debug for TcpConnector {
             .field("link_filter", &self.link_filter)
             .field("multi_interface", &self.multi_interface)
             .field("socket_options", &self.socket_options)
+            .field("proxies", &self.proxies)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("attempt_delay", &self.attempt_delay)
             .finish()
     }
 }
@@ -308,6 +635,8 @@ impl TcpConnector {
     ///
     /// `hosts` can contain IP addresses and hostnames, including port numbers.
     /// If an entry does not specify a port number, the `default_port` is used.
+    /// An entry may specify multiple ports and port ranges, for example `server:5900-5903`,
+    /// to connect to each of these ports; see [`util::expand_host_ports`].
     ///
     /// It is checked at creation that `hosts` resolves to at least one IP address.
     ///
@@ -327,24 +656,22 @@ impl TcpConnector {
 
     /// Create a new TCP transport for outgoing connections without checking that at least one host can be resolved.
     ///
-    /// `hosts` can contain IP addresses and hostnames, including port numbers.
+    /// `hosts` can contain IP addresses and hostnames, including port numbers or port ranges.
     /// If an entry does not specify a port number, the `default_port` is used.
     ///
     /// Host name resolution is retried periodically, thus DNS updates will be taken
     /// into account without the need to recreate this transport.
     pub async fn unresolved(hosts: impl IntoIterator<Item = String>, default_port: u16) -> Result<Self> {
-        let mut hosts: Vec<_> = hosts.into_iter().collect();
+        let mut expanded = Vec::new();
+        for host in hosts {
+            expanded.extend(util::expand_host_ports(&host, default_port)?);
+        }
+        let hosts = expanded;
 
         if hosts.is_empty() {
             return Err(Error::new(ErrorKind::InvalidInput, "at least one host is required"));
         }
 
-        for host in &mut hosts {
-            if !host.contains(':') {
-                host.push_str(&format!(":{default_port}"));
-            }
-        }
-
         Ok(Self {
             hosts,
             ip_version: IpVersion::Both,
@@ -353,6 +680,10 @@ impl TcpConnector {
             multi_interface: !cfg!(target_os = "android"),
             interface_filter: Arc::new(|_| true),
             socket_options: TcpSocketOptions::default(),
+            proxies: Vec::new(),
+            connect_timeout: Duration::from_secs(10),
+            attempt_delay: Duration::from_millis(250),
+            staggered: Arc::new(Mutex::new(StaggeredAttempts::default())),
         })
     }
 
@@ -408,6 +739,51 @@ impl TcpConnector {
         self.interface_filter = Arc::new(interface_filter);
     }
 
+    /// Adds an upstream proxy for connecting.
+    ///
+    /// For each pair of local interface and target host that a proxy is [used for](TcpProxy::is_used_for)
+    /// a link through the proxy is established instead of a direct link.
+    /// If multiple proxies are used for a pair, a separate link is established through each of them.
+    ///
+    /// Target host names are not resolved locally, but passed to the proxy, which resolves them.
+    pub fn add_proxy(&mut self, proxy: TcpProxy) {
+        self.proxies.push(proxy);
+    }
+
+    /// Returns the configured upstream proxies.
+    pub fn proxies(&self) -> &[TcpProxy] {
+        &self.proxies
+    }
+
+    /// Sets the timeout for establishing a link, including the proxy handshake.
+    ///
+    /// Without a timeout a connection attempt over a dead interface would only
+    /// fail after the operating system gives up retransmitting SYN packets, which
+    /// usually takes minutes.
+    ///
+    /// The default is 10 seconds.
+    pub fn set_connect_timeout(&mut self, connect_timeout: Duration) {
+        self.connect_timeout = connect_timeout;
+    }
+
+    /// Sets the delay between staggered connection attempts to the addresses of a host.
+    ///
+    /// When a host resolves to multiple addresses, connection attempts over each
+    /// local interface are started in the order recommended by RFC 8305 ("happy eyeballs"):
+    /// IPv6 first, then alternating between IPv4 and IPv6, with each attempt
+    /// delayed by this duration relative to the previous one.
+    /// Thus, the IPv6 link is preferred by the [link filter](Self::set_link_filter)
+    /// while a broken IPv6 network only delays the IPv4 link slightly.
+    /// Once a connection to the host over an interface has been established, delayed
+    /// attempts to its other addresses over that interface are skipped.
+    ///
+    /// Only the first connection attempt of each link is delayed, reconnects are started immediately.
+    ///
+    /// The default is 250 milliseconds. Set to zero to connect to all addresses simultaneously.
+    pub fn set_attempt_delay(&mut self, attempt_delay: Duration) {
+        self.attempt_delay = attempt_delay;
+    }
+
     /// Resolve target to socket addresses.
     async fn resolve(&self) -> Vec<SocketAddr> {
         util::resolve_hosts(&self.hosts, self.ip_version).await
@@ -432,22 +808,61 @@ impl ConnectingTransport for TcpConnector {
                 false => None,
             };
 
+            let proxies = proxy::resolve_proxies(&self.proxies).await;
+
             let mut tags: HashSet<LinkTagBox> = HashSet::new();
-            for addr in self.resolve().await {
-                match &interfaces {
-                    Some(interfaces) => {
-                        for iface in util::interface_names_for_target(interfaces, addr) {
-                            let tag = TcpLinkTag::new(Some(&iface), addr, Direction::Outgoing);
-                            tags.insert(Box::new(tag));
-                        }
-                    }
-                    None => {
-                        let tag = TcpLinkTag::new(None, addr, Direction::Outgoing);
+            let mut staggered = HashMap::new();
+            for host in &self.hosts {
+                // Links through proxies connect to the host name, which is resolved by the proxy.
+                for (iface, proxy) in proxy::proxy_paths(interfaces.as_deref(), host, &proxies) {
+                    let tag = TcpLinkTag {
+                        source: self.socket_options.source_addr_for(iface.as_deref(), proxy.ip()),
+                        fwmark: self.socket_options.fwmark_for(iface.as_deref()),
+                        ..TcpLinkTag::with_proxy(iface.as_deref(), proxy, host)
+                    };
+                    tags.insert(Box::new(tag));
+                }
+
+                if proxy::is_always_proxied(interfaces.as_deref(), host, &proxies) {
+                    continue;
+                }
+
+                let mut attempts: HashMap<_, u32> = HashMap::new();
+                for addr in util::interleave_families(util::resolve_hosts([host], self.ip_version).await) {
+                    for iface in proxy::direct_paths(interfaces.as_deref(), host, addr, &proxies) {
+                        let tag = TcpLinkTag {
+                            source: self.socket_options.source_addr_for(iface.as_deref(), addr.ip()),
+                            fwmark: self.socket_options.fwmark_for(iface.as_deref()),
+                            ..TcpLinkTag::new(iface.as_deref(), addr, Direction::Outgoing)
+                        };
+
+                        let attempt = attempts.entry(iface.clone()).or_default();
+                        staggered.insert(
+                            tag.clone(),
+                            StaggeredAttempt {
+                                group: (host.clone(), iface),
+                                delay: Some(self.attempt_delay * *attempt),
+                            },
+                        );
+                        *attempt += 1;
+
                         tags.insert(Box::new(tag));
                     }
                 }
             }
 
+            // Only the first connection attempt of a link is staggered, reconnects are not delayed.
+            {
+                let mut attempts = self.staggered.lock().unwrap();
+                for (tag, attempt) in &mut staggered {
+                    if let Some(StaggeredAttempt { delay: None, .. }) = attempts.attempts.get(tag) {
+                        attempt.delay = None;
+                    }
+                }
+                attempts.connected.retain(|group| staggered.values().any(|attempt| attempt.group == *group));
+                attempts.attempts = staggered;
+            }
+
             tx.send_if_modified(|v| {
                 if *v != tags {
                     *v = tags;
@@ -464,20 +879,45 @@ impl ConnectingTransport for TcpConnector {
     async fn connect(&self, tag: &dyn LinkTag) -> Result<StreamBox> {
         let tag: &TcpLinkTag = tag.as_any().downcast_ref().unwrap();
 
-        let socket = match tag.remote.ip() {
-            IpAddr::V4(_) => TcpSocket::new_v4(),
-            IpAddr::V6(_) => TcpSocket::new_v6(),
-        }?;
+        let (group, delay) = match self.staggered.lock().unwrap().attempts.get_mut(tag) {
+            Some(attempt) => (Some(attempt.group.clone()), attempt.delay.take().unwrap_or_default()),
+            None => (None, Duration::ZERO),
+        };
+        if !delay.is_zero() {
+            tracing::debug!(%tag, ?delay, "delaying staggered connection attempt");
+            sleep(delay).await;
 
-        if let Some(interface) = &tag.interface {
-            util::bind_socket_to_interface(&socket, interface, tag.remote.ip())?;
+            if group.as_ref().is_some_and(|group| self.staggered.lock().unwrap().connected.contains(group)) {
+                tracing::debug!(%tag, "skipping staggered connection attempt, since another address connected");
+                return Err(Error::new(ErrorKind::ConnectionAborted, "another address of host connected"));
+            }
         }
 
-        let stream = socket.connect(tag.remote).await?;
-        if let Err(err) = self.socket_options.apply_to_stream(&stream, &tag.remote) {
+        let socket = util::connect_socket(tag.remote.ip(), tag.interface.as_deref(), tag.fwmark, tag.source)?;
+
+        let connect = async {
+            match &tag.target {
+                Some(target) => {
+                    let proxy = proxy::find_proxy(&self.proxies, tag.remote).await?;
+                    proxy.connect(socket, tag.remote, target).await
+                }
+                None => socket.connect(tag.remote).await,
+            }
+        };
+        let stream = timeout(self.connect_timeout, connect)
+            .await
+            .map_err(|_| Error::new(ErrorKind::TimedOut, "connect timed out"))??;
+
+        if let Err(err) =
+            self.socket_options.apply_to_interface_stream(&stream, &tag.remote, tag.interface.as_deref())
+        {
             tracing::debug!(remote = %tag.remote, ?err, "failed to apply TCP socket options");
         }
 
+        if let Some(group) = group {
+            self.staggered.lock().unwrap().connected.insert(group);
+        }
+
         let (rh, wh) = stream.into_split();
         Ok(IoBox::new(rh, wh).into())
     }
@@ -492,23 +932,36 @@ impl ConnectingTransport for TcpConnector {
                 Direction::Incoming => "from",
                 Direction::Outgoing => "to",
             },
-            new_tag.remote,
+            new_tag.target.clone().unwrap_or_else(|| new_tag.remote.to_string()),
             String::from_utf8_lossy(new.remote_user_data()),
             String::from_utf8_lossy(new_tag.interface.as_deref().unwrap_or(b"any interface"))
         );
 
-        match existing.iter().find(|link| {
-            let Some(tag) = link.tag().as_any().downcast_ref::<TcpLinkTag>() else { return false };
-            match self.link_filter {
-                TcpLinkFilter::None => false,
-                TcpLinkFilter::InterfaceInterface => {
-                    tag.interface == new_tag.interface && link.remote_user_data() == new.remote_user_data()
-                }
-                TcpLinkFilter::InterfaceIp => {
-                    tag.interface == new_tag.interface && tag.remote.ip() == new_tag.remote.ip()
+        let max_links = match self.link_filter {
+            TcpLinkFilter::Parallel(n) => n.get(),
+            _ => 1,
+        };
+
+        match existing
+            .iter()
+            .filter(|link| {
+                let Some(tag) = link.tag().as_any().downcast_ref::<TcpLinkTag>() else { return false };
+                match self.link_filter {
+                    TcpLinkFilter::None => false,
+                    TcpLinkFilter::InterfaceInterface | TcpLinkFilter::Parallel(_) => {
+                        tag.interface == new_tag.interface
+                            && tag.proxy() == new_tag.proxy()
+                            && link.remote_user_data() == new.remote_user_data()
+                    }
+                    TcpLinkFilter::InterfaceIp => {
+                        tag.interface == new_tag.interface
+                            && tag.proxy() == new_tag.proxy()
+                            && tag.remote.ip() == new_tag.remote.ip()
+                    }
                 }
-            }
-        }) {
+            })
+            .nth(max_links - 1)
+        {
             Some(other) => {
                 let other_tag = other.tag().as_any().downcast_ref::<TcpLinkTag>().unwrap();
                 tracing::debug!("{intro} => link {} is redundant, rejecting.", other_tag.remote);
@@ -668,7 +1121,7 @@ impl AcceptingTransport for TcpAcceptor {
             let tag = TcpLinkTag::new(Some(&interface), remote, Direction::Incoming);
 
             // Configure socket.
-            if let Err(err) = self.socket_options.apply_to_stream(&socket, &remote) {
+            if let Err(err) = self.socket_options.apply_to_interface_stream(&socket, &remote, Some(&interface)) {
                 tracing::debug!(remote = %remote, ?err, "failed to apply TCP socket options");
             }
             let (rh, wh) = socket.into_split();
@@ -693,6 +1146,7 @@ impl TcpAcceptor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Instant;
     use tokio::net::TcpListener;
 
     #[tokio::test]
@@ -709,4 +1163,146 @@ mod tests {
         let client_stream = client.await.unwrap();
         options.apply_to_stream(&client_stream, &addr).unwrap();
     }
+
+    #[tokio::test]
+    async fn tcp_socket_options_keepalive_and_congestion() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = tokio::spawn(async move { TcpStream::connect(addr).await.unwrap() });
+        let (_server_stream, _) = listener.accept().await.unwrap();
+        let client_stream = client.await.unwrap();
+
+        let mut options = TcpSocketOptions::default();
+        options.set_keepalive_time(Some(Duration::from_secs(5)));
+        options.set_keepalive_interval(Some(Duration::from_secs(1)));
+        options.set_keepalive_retries(Some(3));
+        options.set_user_timeout(Some(Duration::from_secs(4)));
+        options.set_congestion(Some("cubic".to_string()));
+        options.set_interface_congestion("lo", Some("reno".to_string()));
+        assert_eq!(options.congestion_for(Some(b"eth0")), Some("cubic"));
+        assert_eq!(options.congestion_for(Some(b"lo")), Some("reno"));
+
+        options.apply_to_interface_stream(&client_stream, &addr, Some(b"lo")).unwrap();
+
+        let sock = SockRef::from(&client_stream);
+        assert!(sock.keepalive().unwrap());
+        #[cfg(target_os = "linux")]
+        {
+            assert_eq!(sock.tcp_keepalive_time().unwrap(), Duration::from_secs(5));
+            assert_eq!(sock.tcp_keepalive_interval().unwrap(), Duration::from_secs(1));
+            assert_eq!(sock.tcp_keepalive_retries().unwrap(), 3);
+            assert_eq!(sock.tcp_user_timeout().unwrap(), Some(Duration::from_secs(4)));
+            assert!(sock.tcp_congestion().unwrap().starts_with(b"reno\0"));
+        }
+    }
+
+    #[tokio::test]
+    async fn policy_routing() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut options = TcpSocketOptions::default();
+        options.set_source_addrs(["::1".parse().unwrap(), "127.0.0.1".parse().unwrap()]);
+        options.set_interface_source_addrs("lo", ["127.0.0.2".parse().unwrap()]);
+        options.set_fwmark(Some(1));
+        options.set_interface_fwmark("lo", Some(2));
+
+        assert_eq!(options.source_addr_for(None, addr.ip()), Some("127.0.0.1".parse().unwrap()));
+        assert_eq!(options.source_addr_for(Some(b"lo"), addr.ip()), Some("127.0.0.2".parse().unwrap()));
+        assert_eq!(options.source_addr_for(Some(b"lo"), "::1".parse().unwrap()), None);
+        assert_eq!(options.fwmark_for(Some(b"eth0")), Some(1));
+        assert_eq!(options.fwmark_for(Some(b"lo")), Some(2));
+
+        let tag = TcpLinkTag {
+            source: options.source_addr_for(None, addr.ip()),
+            fwmark: options.fwmark_for(None),
+            ..TcpLinkTag::new(None, addr, Direction::Outgoing)
+        };
+        assert!(tag.to_string().ends_with(&format!("{addr} from 127.0.0.1 mark 0x1")));
+
+        let socket = util::connect_socket(addr.ip(), None, None, tag.source).unwrap();
+        let stream = socket.connect(addr).await.unwrap();
+        let (_, remote) = listener.accept().await.unwrap();
+        assert_eq!(stream.local_addr().unwrap().ip(), tag.source.unwrap());
+        assert_eq!(remote.ip(), tag.source.unwrap());
+
+        // Setting the firewall mark requires privileges.
+        #[cfg(target_os = "linux")]
+        match util::connect_socket(addr.ip(), None, tag.fwmark, None) {
+            Ok(socket) => assert_eq!(SockRef::from(&socket).mark().unwrap(), 1),
+            Err(err) => assert_eq!(err.kind(), ErrorKind::PermissionDenied),
+        }
+    }
+
+    #[tokio::test]
+    async fn connect_timeout() {
+        // A listener that never accepts drops SYN packets once its backlog is full.
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let listener = socket.listen(0).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut backlog = Vec::new();
+        while let Ok(Ok(stream)) = timeout(Duration::from_millis(200), TcpStream::connect(addr)).await {
+            backlog.push(stream);
+        }
+
+        let mut connector = TcpConnector::unresolved([addr.to_string()], addr.port()).await.unwrap();
+        connector.set_multi_interface(false);
+        connector.set_connect_timeout(Duration::from_millis(500));
+
+        let tag = TcpLinkTag::new(None, addr, Direction::Outgoing);
+        let err = connector.connect(&tag).await.err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn attempt_delay_only_on_first_connect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut connector = TcpConnector::unresolved([addr.to_string()], addr.port()).await.unwrap();
+        connector.set_multi_interface(false);
+
+        let delay = Duration::from_millis(500);
+        let tag = TcpLinkTag::new(None, addr, Direction::Outgoing);
+        let attempt = StaggeredAttempt { group: (addr.to_string(), None), delay: Some(delay) };
+        connector.staggered.lock().unwrap().attempts.insert(tag.clone(), attempt);
+
+        let start = Instant::now();
+        connector.connect(&tag).await.unwrap();
+        assert!(start.elapsed() >= delay, "first connect was not delayed");
+
+        let start = Instant::now();
+        connector.connect(&tag).await.unwrap();
+        assert!(start.elapsed() < delay, "reconnect was delayed");
+    }
+
+    #[tokio::test]
+    async fn staggered_attempt_skipped_after_connect() {
+        let listener_a = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listener_b = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr_a = listener_a.local_addr().unwrap();
+        let addr_b = listener_b.local_addr().unwrap();
+
+        let mut connector = TcpConnector::unresolved([addr_a.to_string()], addr_a.port()).await.unwrap();
+        connector.set_multi_interface(false);
+
+        let group = ("host".to_string(), None);
+        let tag_a = TcpLinkTag::new(None, addr_a, Direction::Outgoing);
+        let tag_b = TcpLinkTag::new(None, addr_b, Direction::Outgoing);
+        {
+            let mut staggered = connector.staggered.lock().unwrap();
+            let attempt_a = StaggeredAttempt { group: group.clone(), delay: Some(Duration::ZERO) };
+            staggered.attempts.insert(tag_a.clone(), attempt_a);
+            let attempt_b = StaggeredAttempt { group, delay: Some(Duration::from_millis(200)) };
+            staggered.attempts.insert(tag_b.clone(), attempt_b);
+        }
+
+        let (res_a, res_b) = future::join(connector.connect(&tag_a), connector.connect(&tag_b)).await;
+        res_a.unwrap();
+        assert_eq!(res_b.err().unwrap().kind(), ErrorKind::ConnectionAborted);
+
+        // Reconnects are not skipped.
+        connector.connect(&tag_b).await.unwrap();
+    }
 }
@@ -3,7 +3,7 @@
 use network_interface::NetworkInterfaceConfig;
 use std::{
     collections::HashSet,
-    io::{Error, Result},
+    io::{Error, ErrorKind, Result},
     net::{IpAddr, SocketAddr},
 };
 use tokio::net::{lookup_host, TcpSocket};
@@ -41,6 +41,47 @@ pub fn local_interface_for_ip(ip: IpAddr) -> Result<Option<Vec<u8>>> {
     Ok(iface)
 }
 
+/// Parses a list of port numbers and port ranges.
+///
+/// Entries are separated by commas and a range is specified by its inclusive bounds
+/// separated by a dash, for example `5900,5910-5913`.
+pub fn parse_ports(spec: &str) -> Result<Vec<u16>> {
+    let invalid = || Error::new(ErrorKind::InvalidInput, format!("invalid port specification: {spec}"));
+
+    let mut ports = Vec::new();
+    for entry in spec.split(',') {
+        let entry = entry.trim();
+        match entry.split_once('-') {
+            Some((first, last)) => {
+                let first: u16 = first.trim().parse().map_err(|_| invalid())?;
+                let last: u16 = last.trim().parse().map_err(|_| invalid())?;
+                if first > last {
+                    return Err(invalid());
+                }
+                ports.extend(first..=last);
+            }
+            None => ports.push(entry.parse().map_err(|_| invalid())?),
+        }
+    }
+
+    ports.dedup();
+    Ok(ports)
+}
+
+/// Expands a host specification into one `host:port` entry per port.
+///
+/// The port part of `host` may be a list of port numbers and port ranges,
+/// as accepted by [`parse_ports`], for example `server:5900-5903`.
+/// If `host` does not specify a port, `default_port` is used.
+pub fn expand_host_ports(host: &str, default_port: u16) -> Result<Vec<String>> {
+    let (name, ports) = match host.rsplit_once(':') {
+        Some((name, ports)) if !name.contains(':') || name.ends_with(']') => (name, parse_ports(ports)?),
+        _ => (host, vec![default_port]),
+    };
+
+    Ok(ports.into_iter().map(|port| format!("{name}:{port}")).collect())
+}
+
 /// Resolves the specified hosts to IP addresses.
 pub async fn resolve_hosts(
     hosts: impl IntoIterator<Item = impl AsRef<str>>, ip_version: IpVersion,
@@ -59,6 +100,25 @@ pub async fn resolve_hosts(
     all_addrs
 }
 
+/// Orders addresses for staggered connection attempts as described in RFC 8305.
+///
+/// IPv6 addresses come first and the address families alternate thereafter,
+/// so that an unreachable IPv6 network delays IPv4 connectivity by at most one attempt.
+pub fn interleave_families(addrs: impl IntoIterator<Item = SocketAddr>) -> Vec<SocketAddr> {
+    let (mut v6, mut v4): (Vec<_>, Vec<_>) = addrs.into_iter().partition(|addr| addr.is_ipv6());
+    let mut v6 = v6.drain(..);
+    let mut v4 = v4.drain(..);
+
+    let mut ordered = Vec::new();
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => break,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+    ordered
+}
+
 /// Returns the interface usable for connecting to target.
 ///
 /// Filters interfaces out that either have no IP address or only support
@@ -92,26 +152,104 @@ pub fn bind_socket_to_interface(socket: &TcpSocket, interface: &[u8], remote: Ip
 
     #[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
     {
-        for ifn in local_interfaces()? {
-            if ifn.name.as_bytes() == interface {
-                for addr in ifn.addr {
-                    match (addr.ip(), remote) {
-                        (IpAddr::V4(_), IpAddr::V4(_)) => (),
-                        (IpAddr::V6(_), IpAddr::V6(_)) => (),
-                        _ => continue,
-                    }
-
-                    if addr.ip().is_loopback() != remote.is_loopback() {
-                        continue;
-                    }
-
-                    tracing::debug!("binding to {addr:?} on interface {}", &ifn.name);
-                    socket.bind(SocketAddr::new(addr.ip(), 0))?;
-                    return Ok(());
+        let ip = interface_ip_for_target(interface, remote)?;
+        socket.bind(SocketAddr::new(ip, 0))
+    }
+}
+
+/// Creates a socket for connecting to `remote` using the specified policy routing settings.
+///
+/// If `interface` is specified, the socket is bound to it.
+/// If `source` is specified, the socket is bound to this local IP address.
+/// If `fwmark` is specified, the firewall mark of the socket is set, which is only supported on Linux.
+pub fn connect_socket(
+    remote: IpAddr, interface: Option<&[u8]>, fwmark: Option<u32>, source: Option<IpAddr>,
+) -> Result<TcpSocket> {
+    let socket = match remote {
+        IpAddr::V4(_) => TcpSocket::new_v4(),
+        IpAddr::V6(_) => TcpSocket::new_v6(),
+    }?;
+
+    if let Some(interface) = interface {
+        // Binding to an interface is done by binding to its address on some platforms.
+        if source.is_none() || cfg!(any(target_os = "android", target_os = "fuchsia", target_os = "linux")) {
+            bind_socket_to_interface(&socket, interface, remote)?;
+        }
+    }
+
+    if let Some(source) = source {
+        socket.bind(SocketAddr::new(source, 0))?;
+    }
+
+    if let Some(fwmark) = fwmark {
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        socket2::SockRef::from(&socket).set_mark(fwmark)?;
+
+        #[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("firewall mark {fwmark} is not supported on this platform"),
+        ));
+    }
+
+    Ok(socket)
+}
+
+/// Returns the IP address of the specified network interface usable for connecting to `remote`.
+///
+/// The address has the same IP protocol version as `remote` and is a loopback
+/// address if and only if `remote` is.
+pub fn interface_ip_for_target(interface: &[u8], remote: IpAddr) -> Result<IpAddr> {
+    for ifn in local_interfaces()? {
+        if ifn.name.as_bytes() == interface {
+            for addr in ifn.addr {
+                match (addr.ip(), remote) {
+                    (IpAddr::V4(_), IpAddr::V4(_)) => (),
+                    (IpAddr::V6(_), IpAddr::V6(_)) => (),
+                    _ => continue,
+                }
+
+                if addr.ip().is_loopback() != remote.is_loopback() {
+                    continue;
                 }
+
+                tracing::debug!("using {addr:?} on interface {}", &ifn.name);
+                return Ok(addr.ip());
             }
         }
+    }
+
+    Err(Error::new(std::io::ErrorKind::NotFound, "no IP address for interface"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_specification() {
+        assert_eq!(parse_ports("5900").unwrap(), [5900]);
+        assert_eq!(parse_ports("5900-5902,5910").unwrap(), [5900, 5901, 5902, 5910]);
+        assert!(parse_ports("5902-5900").is_err());
+        assert!(parse_ports("abc").is_err());
+
+        assert_eq!(expand_host_ports("server", 5900).unwrap(), ["server:5900"]);
+        assert_eq!(expand_host_ports("server:5901-5902", 5900).unwrap(), ["server:5901", "server:5902"]);
+        assert_eq!(expand_host_ports("[::1]:5901,5903", 5900).unwrap(), ["[::1]:5901", "[::1]:5903"]);
+        assert!(expand_host_ports("server:x", 5900).is_err());
+    }
 
-        Err(Error::new(std::io::ErrorKind::NotFound, "no IP address for interface"))
+    #[test]
+    fn staggered_address_order() {
+        let addrs: Vec<SocketAddr> = ["10.0.0.1:1", "10.0.0.2:1", "10.0.0.3:1", "[fd00::1]:1"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+        let ordered = interleave_families(addrs);
+        let expected: Vec<SocketAddr> = ["[fd00::1]:1", "10.0.0.1:1", "10.0.0.2:1", "10.0.0.3:1"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+        assert_eq!(ordered, expected);
     }
 }
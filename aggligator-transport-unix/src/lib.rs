@@ -0,0 +1,244 @@
+#![warn(missing_docs)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/surban/aggligator/master/.misc/aggligator.png",
+    html_favicon_url = "https://raw.githubusercontent.com/surban/aggligator/master/.misc/aggligator.png",
+    issue_tracker_base_url = "https://github.com/surban/aggligator/issues/"
+)]
+#![cfg(unix)]
+
+//! [Aggligator](aggligator) transport: Unix domain sockets
+//!
+//! Each link is a connection to a Unix domain socket.
+//! This is useful when links are provided by local processes, for example
+//! forwarding daemons running in separate network namespaces.
+
+use async_trait::async_trait;
+use futures::{future, FutureExt};
+use std::{
+    any::Any,
+    cmp::Ordering,
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    io::{Error, ErrorKind, Result},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering as AtomicOrdering},
+};
+use tokio::{
+    net::{UnixListener, UnixStream},
+    sync::{mpsc, watch},
+};
+
+use aggligator::{
+    control::Direction,
+    io::{IoBox, StreamBox},
+    transport::{AcceptedStreamBox, AcceptingTransport, ConnectingTransport, LinkTag, LinkTagBox},
+};
+
+static NAME: &str = "unix";
+
+/// Link tag for Unix domain socket link.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnixLinkTag {
+    /// Path of the socket.
+    ///
+    /// For outgoing links this is the socket connected to and for
+    /// incoming links this is the socket listened on.
+    pub path: PathBuf,
+    /// Number of the incoming connection on the listening socket.
+    ///
+    /// Always zero for outgoing links.
+    pub id: u64,
+    /// Link direction.
+    pub direction: Direction,
+}
+
+impl fmt::Display for UnixLinkTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.direction {
+            Direction::Incoming => write!(f, "<- {} #{}", self.path.display(), self.id),
+            Direction::Outgoing => write!(f, "-> {}", self.path.display()),
+        }
+    }
+}
+
+impl UnixLinkTag {
+    /// Creates a new link tag for a Unix domain socket link.
+    pub fn new(path: impl Into<PathBuf>, id: u64, direction: Direction) -> Self {
+        Self { path: path.into(), id, direction }
+    }
+}
+
+impl LinkTag for UnixLinkTag {
+    fn transport_name(&self) -> &str {
+        NAME
+    }
+
+    fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    fn user_data(&self) -> Vec<u8> {
+        self.path.as_os_str().as_bytes().to_vec()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn box_clone(&self) -> LinkTagBox {
+        Box::new(self.clone())
+    }
+
+    fn dyn_cmp(&self, other: &dyn LinkTag) -> Ordering {
+        let other = other.as_any().downcast_ref::<Self>().unwrap();
+        Ord::cmp(self, other)
+    }
+
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        Hash::hash(self, &mut state)
+    }
+}
+
+/// Unix domain socket transport for outgoing connections.
+///
+/// One link is established to each socket path.
+///
+/// This transport is IO-stream based.
+#[derive(Debug, Clone)]
+pub struct UnixConnector {
+    paths: Vec<PathBuf>,
+}
+
+impl fmt::Display for UnixConnector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let paths: Vec<_> = self.paths.iter().map(|path| path.display().to_string()).collect();
+        if paths.len() > 1 {
+            write!(f, "[{}]", paths.join(", "))
+        } else {
+            write!(f, "{}", paths[0])
+        }
+    }
+}
+
+impl UnixConnector {
+    /// Create a new Unix domain socket transport for outgoing connections.
+    ///
+    /// A link is established to each socket specified in `paths`.
+    pub fn new(paths: impl IntoIterator<Item = impl Into<PathBuf>>) -> Result<Self> {
+        let paths: Vec<_> = paths.into_iter().map(|path| path.into()).collect();
+
+        if paths.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "at least one path is required"));
+        }
+
+        Ok(Self { paths })
+    }
+
+    /// The paths of the sockets links are established to.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+}
+
+#[async_trait]
+impl ConnectingTransport for UnixConnector {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    async fn link_tags(&self, tx: watch::Sender<HashSet<LinkTagBox>>) -> Result<()> {
+        let tags: HashSet<LinkTagBox> = self
+            .paths
+            .iter()
+            .map(|path| Box::new(UnixLinkTag::new(path, 0, Direction::Outgoing)) as LinkTagBox)
+            .collect();
+        tx.send_replace(tags);
+
+        future::pending().await
+    }
+
+    async fn connect(&self, tag: &dyn LinkTag) -> Result<StreamBox> {
+        let tag: &UnixLinkTag = tag.as_any().downcast_ref().unwrap();
+
+        let stream = UnixStream::connect(&tag.path).await?;
+        let (rh, wh) = stream.into_split();
+        Ok(IoBox::new(rh, wh).into())
+    }
+}
+
+/// Unix domain socket transport for incoming connections.
+///
+/// This transport is IO-stream based.
+#[derive(Debug)]
+pub struct UnixAcceptor {
+    listeners: Vec<(PathBuf, UnixListener)>,
+    next_id: AtomicU64,
+}
+
+impl fmt::Display for UnixAcceptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let paths: Vec<_> = self.listeners.iter().map(|(path, _)| path.display().to_string()).collect();
+        if paths.len() > 1 {
+            write!(f, "[{}]", paths.join(", "))
+        } else {
+            write!(f, "{}", paths[0])
+        }
+    }
+}
+
+impl UnixAcceptor {
+    /// Create a new Unix domain socket transport listening for incoming connections.
+    ///
+    /// It creates and listens on the sockets specified in `paths`.
+    /// Binding fails if a file already exists at a path.
+    pub async fn new(paths: impl IntoIterator<Item = impl AsRef<Path>>) -> Result<Self> {
+        let listeners = paths.into_iter().map(UnixListener::bind).collect::<Result<Vec<_>>>()?;
+        Self::from_listeners(listeners)
+    }
+
+    /// Create a new Unix domain socket transport for incoming connections using the specified listeners.
+    pub fn from_listeners(listeners: impl IntoIterator<Item = UnixListener>) -> Result<Self> {
+        let listeners = listeners
+            .into_iter()
+            .map(|listener| {
+                let path =
+                    listener.local_addr()?.as_pathname().map(|path| path.to_path_buf()).unwrap_or_default();
+                Ok((path, listener))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if listeners.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "at least one listener is required"));
+        }
+
+        Ok(Self { listeners, next_id: AtomicU64::new(1) })
+    }
+}
+
+#[async_trait]
+impl AcceptingTransport for UnixAcceptor {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    async fn listen(&self, tx: mpsc::Sender<AcceptedStreamBox>) -> Result<()> {
+        loop {
+            // Accept incoming connection.
+            let (res, idx, _) =
+                future::select_all(self.listeners.iter().map(|(_, listener)| listener.accept().boxed())).await;
+            let (socket, _) = res?;
+            let path = &self.listeners[idx].0;
+
+            // Build tag.
+            let id = self.next_id.fetch_add(1, AtomicOrdering::Relaxed);
+            tracing::debug!(path =% path.display(), %id, "Accepted Unix domain socket connection");
+            let tag = UnixLinkTag::new(path, id, Direction::Incoming);
+
+            let (rh, wh) = socket.into_split();
+            let _ = tx.send(AcceptedStreamBox::new(IoBox::new(rh, wh).into(), tag)).await;
+        }
+    }
+}
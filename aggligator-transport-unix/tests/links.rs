@@ -0,0 +1,71 @@
+//! Unix domain socket transport tests.
+
+use futures::join;
+use std::{path::PathBuf, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    time::sleep,
+};
+
+use aggligator::transport::{Acceptor, Connector};
+use aggligator_transport_unix::{UnixAcceptor, UnixConnector, UnixLinkTag};
+
+fn socket_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("aggligator-unix-{}-{name}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn two_links() {
+    const DATA: &[u8] = b"Hello over Unix domain sockets!";
+
+    let paths = [socket_path("a"), socket_path("b")];
+
+    let acceptor = Acceptor::new();
+    acceptor.add(UnixAcceptor::new(&paths).await.unwrap());
+
+    let mut connector = Connector::new();
+    connector.add(UnixConnector::new(&paths).unwrap());
+    let outgoing = connector.channel().unwrap();
+
+    let server_task = async {
+        let (ch, _control) = acceptor.accept().await.unwrap();
+        let mut stream = ch.into_stream();
+
+        let mut buf = vec![0; DATA.len()];
+        stream.read_exact(&mut buf).await.unwrap();
+        stream.write_all(&buf).await.unwrap();
+        stream.flush().await.unwrap();
+    };
+
+    let client_task = async {
+        let mut stream = outgoing.await.unwrap().into_stream();
+        stream.write_all(DATA).await.unwrap();
+        stream.flush().await.unwrap();
+
+        let mut buf = vec![0; DATA.len()];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, DATA);
+
+        let mut control = connector.control();
+        while control.links().len() < 2 {
+            control.links_changed().await;
+        }
+
+        let mut link_paths: Vec<_> = control
+            .links()
+            .iter()
+            .map(|link| link.tag().as_any().downcast_ref::<UnixLinkTag>().unwrap().path.clone())
+            .collect();
+        link_paths.sort();
+        assert_eq!(link_paths, paths);
+    };
+
+    join!(server_task, client_task);
+
+    sleep(Duration::from_millis(100)).await;
+    for path in &paths {
+        let _ = std::fs::remove_file(path);
+    }
+}
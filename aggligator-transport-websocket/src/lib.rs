@@ -31,7 +31,6 @@ use std::{
     time::Duration,
 };
 use tokio::{
-    net::TcpSocket,
     sync::{mpsc, watch, Mutex},
     time::sleep,
 };
@@ -45,8 +44,11 @@ use aggligator::{
     transport::{AcceptedStreamBox, AcceptingTransport, ConnectingTransport, LinkTag, LinkTagBox},
     Link,
 };
-use aggligator_transport_tcp::util::{self, NetworkInterface};
-pub use aggligator_transport_tcp::IpVersion;
+use aggligator_transport_tcp::{
+    proxy,
+    util::{self, NetworkInterface},
+};
+pub use aggligator_transport_tcp::{proxy::TcpProxy, IpVersion};
 
 static NAME: &str = "websocket";
 
@@ -56,22 +58,32 @@ pub struct OutgoingWebSocketLinkTag {
     /// Local interface name.
     pub interface: Option<Vec<u8>>,
     /// Remote socket address.
+    ///
+    /// For a link established through a proxy this is the address of the proxy.
     pub remote: SocketAddr,
     /// Remote URL.
     pub url: String,
     /// Whether to use TLS for connecting.
     pub tls: bool,
+    /// Whether the link is established through a proxy, which resolves the URL host.
+    pub proxied: bool,
+}
+
+impl OutgoingWebSocketLinkTag {
+    /// Address of the proxy used for connecting.
+    pub fn proxy(&self) -> Option<SocketAddr> {
+        self.proxied.then_some(self.remote)
+    }
 }
 
 impl fmt::Display for OutgoingWebSocketLinkTag {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{} -> {} ({})",
-            String::from_utf8_lossy(self.interface.as_deref().unwrap_or_default()),
-            &self.remote,
-            &self.url
-        )
+        let interface = String::from_utf8_lossy(self.interface.as_deref().unwrap_or_default());
+        match self.proxy() {
+            Some(proxy) => write!(f, "{interface} -> {} via {proxy}", &self.url)?,
+            None => write!(f, "{interface} -> {} ({})", &self.remote, &self.url)?,
+        }
+        Ok(())
     }
 }
 
@@ -118,6 +130,7 @@ pub struct WebSocketConnector {
     web_socket_config: Option<WebSocketConfig>,
     multi_interface: bool,
     interface_filter: Arc<dyn Fn(&NetworkInterface) -> bool + Send + Sync>,
+    proxies: Vec<TcpProxy>,
 }
 
 impl fmt::Debug for WebSocketConnector {
@@ -128,6 +141,7 @@ impl fmt::Debug for WebSocketConnector {
             .field("resolve_interval", &self.resolve_interval)
             .field("web_socket_config", &self.web_socket_config)
             .field("multi_interface", &self.multi_interface)
+            .field("proxies", &self.proxies)
             .finish()
     }
 }
@@ -197,6 +211,7 @@ impl WebSocketConnector {
             web_socket_config: None,
             multi_interface: !cfg!(target_os = "android"),
             interface_filter: Arc::new(|_| true),
+            proxies: Vec::new(),
         })
     }
 
@@ -249,6 +264,22 @@ impl WebSocketConnector {
         self.interface_filter = Arc::new(interface_filter);
     }
 
+    /// Adds an upstream proxy for connecting.
+    ///
+    /// For each pair of local interface and URL host that a proxy is [used for](TcpProxy::is_used_for)
+    /// a link through the proxy is established instead of a direct link.
+    /// If multiple proxies are used for a pair, a separate link is established through each of them.
+    ///
+    /// URL host names are not resolved locally, but passed to the proxy, which resolves them.
+    pub fn add_proxy(&mut self, proxy: TcpProxy) {
+        self.proxies.push(proxy);
+    }
+
+    /// Returns the configured upstream proxies.
+    pub fn proxies(&self) -> &[TcpProxy] {
+        &self.proxies
+    }
+
     /// Resolve URLs to socket addresses.
     async fn resolve(&self) -> HashMap<&Url, Vec<SocketAddr>> {
         let mut url_addrs = HashMap::new();
@@ -282,30 +313,40 @@ impl ConnectingTransport for WebSocketConnector {
                 false => None,
             };
 
+            let proxies = proxy::resolve_proxies(&self.proxies).await;
+
             let mut tags: HashSet<LinkTagBox> = HashSet::new();
-            for (url, addrs) in self.resolve().await {
-                for addr in addrs {
-                    match &interfaces {
-                        Some(interfaces) => {
-                            for interface in util::interface_names_for_target(interfaces, addr) {
-                                let tag = OutgoingWebSocketLinkTag {
-                                    interface: Some(interface),
-                                    remote: addr,
-                                    url: url.to_string(),
-                                    tls: url.scheme() == "wss",
-                                };
-                                tags.insert(Box::new(tag));
-                            }
-                        }
-                        None => {
-                            let tag = OutgoingWebSocketLinkTag {
-                                interface: None,
-                                remote: addr,
-                                url: url.to_string(),
-                                tls: url.scheme() == "wss",
-                            };
-                            tags.insert(Box::new(tag));
-                        }
+            for url in &self.urls {
+                let host = url.host_str().unwrap();
+                let port = url.port_or_known_default().unwrap();
+                let tls = url.scheme() == "wss";
+
+                // Links through proxies connect to the URL host, which is resolved by the proxy.
+                for (interface, proxy) in proxy::proxy_paths(interfaces.as_deref(), host, &proxies) {
+                    let tag = OutgoingWebSocketLinkTag {
+                        interface,
+                        remote: proxy,
+                        url: url.to_string(),
+                        tls,
+                        proxied: true,
+                    };
+                    tags.insert(Box::new(tag));
+                }
+
+                if proxy::is_always_proxied(interfaces.as_deref(), host, &proxies) {
+                    continue;
+                }
+
+                for addr in util::resolve_hosts(&[format!("{host}:{port}")], self.ip_version).await {
+                    for interface in proxy::direct_paths(interfaces.as_deref(), host, addr, &proxies) {
+                        let tag = OutgoingWebSocketLinkTag {
+                            interface,
+                            remote: addr,
+                            url: url.to_string(),
+                            tls,
+                            proxied: false,
+                        };
+                        tags.insert(Box::new(tag));
                     }
                 }
             }
@@ -327,16 +368,17 @@ impl ConnectingTransport for WebSocketConnector {
         let tag: &OutgoingWebSocketLinkTag = tag.as_any().downcast_ref().unwrap();
 
         // Establish TCP connection to server.
-        let socket = match tag.remote.ip() {
-            IpAddr::V4(_) => TcpSocket::new_v4(),
-            IpAddr::V6(_) => TcpSocket::new_v6(),
-        }?;
-
-        if let Some(interface) = &tag.interface {
-            util::bind_socket_to_interface(&socket, interface, tag.remote.ip())?;
-        }
-
-        let stream = socket.connect(tag.remote).await?;
+        let socket = util::connect_socket(tag.remote.ip(), tag.interface.as_deref(), None, None)?;
+
+        let stream = match tag.proxy() {
+            Some(proxy_addr) => {
+                let url = Url::parse(&tag.url).map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+                let target = format!("{}:{}", url.host_str().unwrap(), url.port_or_known_default().unwrap());
+                let proxy = proxy::find_proxy(&self.proxies, proxy_addr).await?;
+                proxy.connect(socket, proxy_addr, &target).await?
+            }
+            None => socket.connect(tag.remote).await?,
+        };
         let _ = stream.set_nodelay(true);
 
         // Convert into WebSocket.
@@ -390,7 +432,9 @@ impl ConnectingTransport for WebSocketConnector {
 
         match existing.iter().find(|link| {
             let Some(tag) = link.tag().as_any().downcast_ref::<OutgoingWebSocketLinkTag>() else { return false };
-            tag.interface == new_tag.interface && link.remote_user_data() == new.remote_user_data()
+            tag.interface == new_tag.interface
+                && tag.proxy() == new_tag.proxy()
+                && link.remote_user_data() == new.remote_user_data()
         }) {
             Some(other) => {
                 let other_tag = other.tag().as_any().downcast_ref::<OutgoingWebSocketLinkTag>().unwrap();
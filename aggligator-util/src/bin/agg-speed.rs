@@ -35,9 +35,11 @@ use aggligator_monitor::{
     monitor::{format_speed, interactive_monitor},
     speed::{speed_test, INTERVAL},
 };
-use aggligator_transport_tcp::{IpVersion, TcpAcceptor, TcpConnector, TcpLinkFilter};
+use aggligator_transport_tcp::{IpVersion, TcpAcceptor, TcpConnector, TcpLinkFilter, TcpSocketOptions};
 use aggligator_transport_websocket::{WebSocketAcceptor, WebSocketConnector};
-use aggligator_util::{init_log, load_cfg, parse_tcp_link_filter, print_default_cfg, wait_sigterm};
+use aggligator_util::{
+    init_log, load_cfg, parse_tcp_link_filter, print_default_cfg, wait_sigterm, TcpTuningArgs,
+};
 use aggligator_wrapper_tls::{TlsClient, TlsServer};
 
 #[cfg(feature = "bluer")]
@@ -266,8 +268,12 @@ pub struct ClientCli {
     /// interface-interface：为每对本地和远端网卡创建一条链路。
     ///
     /// interface-ip：为每个本地网卡与远端 IP 的组合创建一条链路。
+    ///
+    /// parallel:N：为每对本地和远端网卡最多创建 N 条并行链路，需配合多个服务器端口使用。
     #[arg(long, value_parser = parse_tcp_link_filter, default_value = "interface-interface")]
     tcp_link_filter: TcpLinkFilter,
+    #[command(flatten)]
+    tcp_tuning: TcpTuningArgs,
     /// WebSocket 主机或 URL。
     ///
     /// 默认端口为 8080，路径为 /agg-speed。
@@ -326,6 +332,9 @@ impl ClientCli {
                 TcpConnector::new(self.tcp.clone(), TCP_PORT).await.context("无法解析 TCP 目标")?;
             tcp_connector.set_ip_version(ip_version);
             tcp_connector.set_link_filter(self.tcp_link_filter);
+            let mut tcp_socket_options = TcpSocketOptions::default();
+            self.tcp_tuning.apply(&mut tcp_socket_options);
+            tcp_connector.set_socket_options(tcp_socket_options);
             targets.push(tcp_connector.to_string());
             connector.add(tcp_connector);
         }
@@ -539,6 +548,8 @@ pub struct ServerCli {
     /// 监听的 TCP 端口。
     #[arg(long, default_value_t = TCP_PORT)]
     tcp: u16,
+    #[command(flatten)]
+    tcp_tuning: TcpTuningArgs,
     /// 要监听的 RFCOMM 信道号。
     #[cfg(feature = "bluer")]
     #[arg(long, default_value_t = RFCOMM_CHANNEL)]
@@ -579,7 +590,10 @@ impl ServerCli {
             TcpAcceptor::new([SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), self.tcp)]).await
         };
         match tcp_acceptor_res {
-            Ok(tcp) => {
+            Ok(mut tcp) => {
+                let mut tcp_socket_options = TcpSocketOptions::default();
+                self.tcp_tuning.apply(&mut tcp_socket_options);
+                tcp.set_socket_options(tcp_socket_options);
                 ports.push(format!("TCP 端口 {tcp}"));
                 acceptor.add(tcp);
             }
@@ -8,14 +8,15 @@ use socket2::SockRef;
 use std::{
     collections::{HashMap, HashSet},
     io::stdout,
-    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     path::PathBuf,
     process::exit,
+    str::FromStr,
     sync::Arc,
     time::Duration,
 };
 use tokio::{
-    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    io::{split, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
     net::{TcpListener, TcpSocket, TcpStream},
     select,
     sync::{broadcast, mpsc, oneshot, watch},
@@ -24,17 +25,19 @@ use tokio::{
 };
 
 use aggligator::{
-    alc::{ReceiverStream, SenderSink},
     cfg::Cfg,
     dump::dump_to_json_line_file,
     exec,
     transport::{AcceptorBuilder, ConnectingTransport, ConnectorBuilder, LinkTagBox},
 };
 use aggligator_monitor::monitor::{interactive_monitor, watch_tags};
-use aggligator_transport_tcp::{IpVersion, TcpAcceptor, TcpConnector, TcpLinkFilter, TcpSocketOptions};
+use aggligator_transport_cmd::{CommandConnector, StdioAcceptor};
+use aggligator_transport_tcp::{
+    proxy::TcpProxy, util, IpVersion, TcpAcceptor, TcpConnector, TcpLinkFilter, TcpSocketOptions,
+};
 use aggligator_util::{
     ctcp::{self, CtcpWrapper},
-    init_log, load_cfg, parse_tcp_link_filter, print_default_cfg, wait_sigterm,
+    init_log, init_log_stderr, load_cfg, parse_tcp_link_filter, print_default_cfg, wait_sigterm, TcpTuningArgs,
 };
 
 #[cfg(feature = "bluer")]
@@ -46,6 +49,7 @@ use aggligator_transport_usb::{upc, usb_gadget};
 const TCP_PORT: u16 = 5800;
 const FLUSH_DELAY: Option<Duration> = Some(Duration::from_millis(10));
 const DUMP_BUFFER: usize = 8192;
+const MUX_RECONNECT_DELAY: Duration = Duration::from_secs(1);
 
 fn tcp_socket_options_from_cli(
     turbo: bool, send_buffer: Option<usize>, recv_buffer: Option<usize>, tos_v4: Option<u8>,
@@ -118,9 +122,15 @@ enum Commands {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    init_log();
-
     let cli = TunnelCli::parse();
+
+    // In stdio mode standard output carries the link.
+    if matches!(&cli.command, Commands::Server(server) if server.stdio) {
+        init_log_stderr();
+    } else {
+        init_log();
+    }
+
     let cfg = load_cfg(&cli.cfg)?;
     let dump = cli.dump.clone();
 
@@ -171,10 +181,17 @@ pub struct ClientCli {
     /// 处理完一条连接后立即退出。
     #[arg(long)]
     once: bool,
+    /// 通过同一个聚合连接复用所有转发的 TCP 连接。
+    ///
+    /// 避免为每条 TCP 连接重新建立所有链路，服务器端也必须启用此选项。
+    #[arg(long, short = 'm')]
+    mux: bool,
     /// 自定义 CTCP printable 加密密钥（支持十进制、0x 十六进制、0b 二进制或 0o 八进制，默认沿用 openppp2 的内置值）。
     #[arg(long, value_name = "KEY", value_parser = parse_ctcp_key, default_value_t = ctcp::DEFAULT_KEY)]
     ctcp_key: u32,
     /// TCP 服务器的名称或 IP 地址与端口号。
+    ///
+    /// 端口可以是多个端口或端口范围，例如 `server:5800-5803`，每个端口都会建立链路。
     #[arg(long)]
     tcp: Vec<String>,
     /// TCP 链路过滤方式。
@@ -184,8 +201,16 @@ pub struct ClientCli {
     /// interface-interface：为每对本地和远端网卡创建一条链路。
     ///
     /// interface-ip：为每个本地网卡与远端 IP 的组合创建一条链路。
+    ///
+    /// parallel:N：为每对本地和远端网卡最多创建 N 条并行链路，需配合多个服务器端口使用。
     #[arg(long, value_parser = parse_tcp_link_filter, default_value = "interface-interface")]
     tcp_link_filter: TcpLinkFilter,
+    /// 通过上游代理建立 TCP 链路，可重复指定。
+    ///
+    /// 格式为 `socks5://[user:pass@]host[:port]` 或 `http://[user:pass@]host[:port]`，
+    /// 每个代理都会产生单独的链路。目标主机名由代理解析。
+    #[arg(long, value_name = "URL")]
+    tcp_proxy: Vec<TcpProxy>,
     /// 启用 openppp2 Turbo 风格的 TCP 优化（禁用 Nagle 并放大缓冲区）。
     #[arg(long)]
     tcp_turbo: bool,
@@ -198,6 +223,26 @@ pub struct ClientCli {
     /// 设置 IPv4 数据包的 TOS/DSCP 值（默认 Turbo 模式下为 0x10）。
     #[arg(long, value_name = "TOS")]
     tcp_tos: Option<u8>,
+    #[command(flatten)]
+    tcp_tuning: TcpTuningArgs,
+    /// 为 TCP 链路设置防火墙标记（SO_MARK，仅限 Linux），用于策略路由，可重复指定。
+    ///
+    /// 格式为 `[网卡=]标记`，例如 `eth0=0x10`；未指定网卡时作用于所有网卡。
+    /// 可通过 `ip rule add fwmark <标记> table <路由表>` 为每个标记选择路由表。
+    #[arg(long, value_name = "[IFACE=]MARK", value_parser = parse_iface_val::<Fwmark>)]
+    tcp_fwmark: Vec<(Option<String>, Fwmark)>,
+    /// 为 TCP 链路绑定源 IP 地址，可重复指定。
+    ///
+    /// 格式为 `[网卡=]IP`，例如 `eth0=192.168.1.2`；未指定网卡时作用于所有网卡。
+    #[arg(long, value_name = "[IFACE=]IP", value_parser = parse_iface_val::<IpAddr>)]
+    tcp_source: Vec<(Option<String>, IpAddr)>,
+    /// 用于建立链路的命令，其标准输入和输出用作链路，可重复指定。
+    ///
+    /// 例如 `ssh host nc localhost 5800` 可连接到仅在 host 回环接口上监听的服务器。
+    ///
+    /// 命令由系统 shell 执行，链路断开时命令会被终止并在重连时重新执行。
+    #[arg(long, value_name = "COMMAND")]
+    cmd: Vec<String>,
     /// 蓝牙 RFCOMM 服务器地址。
     #[cfg(feature = "bluer")]
     #[arg(long)]
@@ -225,14 +270,34 @@ impl ClientCli {
         let mut watch_conn: Vec<Box<dyn ConnectingTransport>> = Vec::new();
         let mut targets = Vec::new();
         let ctcp_key = self.ctcp_key;
-        let tcp_socket_options =
+        let mut tcp_socket_options =
             tcp_socket_options_from_cli(self.tcp_turbo, self.tcp_send_buffer, self.tcp_recv_buffer, self.tcp_tos);
+        self.tcp_tuning.apply(&mut tcp_socket_options);
+        for (iface, Fwmark(mark)) in &self.tcp_fwmark {
+            match iface {
+                Some(iface) => tcp_socket_options.set_interface_fwmark(iface, Some(*mark)),
+                None => tcp_socket_options.set_fwmark(Some(*mark)),
+            }
+        }
+        let mut sources: HashMap<_, Vec<_>> = HashMap::new();
+        for (iface, ip) in &self.tcp_source {
+            sources.entry(iface.clone()).or_default().push(*ip);
+        }
+        for (iface, ips) in sources {
+            match iface {
+                Some(iface) => tcp_socket_options.set_interface_source_addrs(iface, ips),
+                None => tcp_socket_options.set_source_addrs(ips),
+            }
+        }
         let tcp_connector = if !self.tcp.is_empty() {
             match TcpConnector::new(self.tcp.clone(), TCP_PORT).await {
                 Ok(mut tcp) => {
                     tcp.set_ip_version(IpVersion::from_only(self.ipv4, self.ipv6)?);
                     tcp.set_link_filter(self.tcp_link_filter);
                     tcp.set_socket_options(tcp_socket_options.clone());
+                    for proxy in &self.tcp_proxy {
+                        tcp.add_proxy(proxy.clone());
+                    }
                     targets.push(tcp.to_string());
                     watch_conn.push(Box::new(tcp.clone()));
                     Some(tcp)
@@ -246,6 +311,15 @@ impl ClientCli {
             None
         };
 
+        let cmd_connector = if !self.cmd.is_empty() {
+            let cmd = CommandConnector::new(self.cmd.clone())?;
+            targets.push(cmd.to_string());
+            watch_conn.push(Box::new(cmd.clone()));
+            Some(cmd)
+        } else {
+            None
+        };
+
         #[cfg(feature = "bluer")]
         let rfcomm_connector = match self.rfcomm {
             Some(addr) => {
@@ -313,6 +387,102 @@ impl ClientCli {
         let (control_tx, control_rx) = broadcast::channel(8);
         let all_tags_rx = self.all_links.then(|| watch_tags(watch_conn));
 
+        let connect = {
+            let tag_err_tx = tag_err_tx.clone();
+            let disabled_tags_rx = disabled_tags_rx.clone();
+            let cfg = cfg.clone();
+            let dump = dump.clone();
+            move || {
+                let mut builder = ConnectorBuilder::new(cfg.clone());
+                builder.wrap(CtcpWrapper::with_key(ctcp_key));
+                if let Some(dump) = dump.clone() {
+                    let (tx, rx) = mpsc::channel(DUMP_BUFFER);
+                    builder.task().dump(tx);
+                    exec::spawn(dump_to_json_line_file(dump, rx));
+                }
+
+                let mut connector = builder.build();
+                if let Some(c) = tcp_connector.clone() {
+                    connector.add(c);
+                }
+                if let Some(c) = cmd_connector.clone() {
+                    connector.add(c);
+                }
+                #[cfg(feature = "bluer")]
+                if let Some(c) = rfcomm_connector.clone() {
+                    connector.add(c);
+                }
+                #[cfg(feature = "usb-host")]
+                if let Some(c) = usb_connector() {
+                    connector.add(c);
+                }
+                let control = connector.control();
+                let outgoing = connector.channel().unwrap();
+
+                exec::spawn({
+                    let control = control.clone();
+                    async move {
+                        wait_sigterm().await;
+                        control.terminate();
+                    }
+                });
+
+                let mut conn_tag_err_rx = connector.link_errors();
+                let tag_err_tx = tag_err_tx.clone();
+                exec::spawn(async move {
+                    while let Ok(err) = conn_tag_err_rx.recv().await {
+                        let _ = tag_err_tx.send(err);
+                    }
+                });
+                let mut disabled_tags_rx = disabled_tags_rx.clone();
+                exec::spawn(async move {
+                    loop {
+                        let disabled_tags: HashSet<LinkTagBox> = (*disabled_tags_rx.borrow_and_update()).clone();
+                        connector.set_disabled_tags(disabled_tags);
+                        if disabled_tags_rx.changed().await.is_err() {
+                            break;
+                        }
+                    }
+                });
+
+                (control, outgoing)
+            }
+        };
+
+        // In multiplex mode all forwarded TCP connections share one aggregated connection,
+        // which is re-established when it fails.
+        let mux_opener_rx = if self.mux {
+            let (opener_tx, opener_rx) = watch::channel(None);
+            let control_tx = control_tx.clone();
+            let connect = connect.clone();
+            exec::spawn(async move {
+                loop {
+                    let (control, outgoing) = connect();
+                    let _ = control_tx.send((control, format!("复用连接：{target}")));
+
+                    match outgoing.await {
+                        Ok(ch) => {
+                            let mut mux = ch.into_mux();
+                            if opener_tx.send(Some(mux.opener())).is_err() {
+                                break;
+                            }
+
+                            // Sub-streams opened by the server are not used and thus reset.
+                            while let Ok(Some(_)) = mux.accept().await {}
+                            let _ = opener_tx.send(None);
+                            eprintln!("复用连接已断开，正在重新连接");
+                        }
+                        Err(err) => eprintln!("无法建立复用连接：{err}"),
+                    }
+
+                    sleep(MUX_RECONNECT_DELAY).await;
+                }
+            });
+            Some(opener_rx)
+        } else {
+            None
+        };
+
         let mut port_tasks = Vec::new();
         for (server_port, client_port) in ports {
             let listeners = if self.global {
@@ -333,15 +503,8 @@ impl ClientCli {
             };
 
             let control_tx = control_tx.clone();
-            let tag_err_tx = tag_err_tx.clone();
-            let disabled_tags_rx = disabled_tags_rx.clone();
-            let port_cfg = cfg.clone();
-            let tcp_connector = tcp_connector.clone();
-            #[cfg(feature = "bluer")]
-            let rfcomm_connector = rfcomm_connector.clone();
-            #[cfg(feature = "usb-host")]
-            let usb_connector = usb_connector.clone();
-            let dump = dump.clone();
+            let connect = connect.clone();
+            let mux_opener_rx = mux_opener_rx.clone();
             port_tasks.push(async move {
                 loop {
                     let (socket, src) = tokio::select! {
@@ -349,70 +512,37 @@ impl ClientCli {
                         () = wait_sigterm() => break,
                     };
 
-                    let mut builder = ConnectorBuilder::new(port_cfg.clone());
-                    builder.wrap(CtcpWrapper::with_key(ctcp_key));
-                    if let Some(dump) = dump.clone() {
-                        let (tx, rx) = mpsc::channel(DUMP_BUFFER);
-                        builder.task().dump(tx);
-                        exec::spawn(dump_to_json_line_file(dump, rx));
+                    if no_monitor {
+                        eprintln!("来自 {src} 的连接请求端口 {client_port}");
                     }
 
-                    let mut connector = builder.build();
-                    if let Some(c) = tcp_connector.clone() {
-                        connector.add(c);
-                    }
-                    #[cfg(feature = "bluer")]
-                    if let Some(c) = rfcomm_connector.clone() {
-                        connector.add(c);
-                    }
-                    #[cfg(feature = "usb-host")]
-                    if let Some(c) = usb_connector() {
-                        connector.add(c);
-                    }
-                    let control = connector.control();
-                    let outgoing = connector.channel().unwrap();
-
-                    exec::spawn({
-                        let control = control.clone();
-                        async move {
-                            wait_sigterm().await;
-                            control.terminate();
-                        }
-                    });
+                    if let Some(mut opener_rx) = mux_opener_rx.clone() {
+                        exec::spawn(async move {
+                            let opener =
+                                opener_rx.wait_for(Option::is_some).await.context("复用连接失败")?.clone();
+                            let (server_read, server_write) = split(opener.unwrap().open()?);
+                            Self::handle_connection(socket, server_read, server_write, server_port).await?;
 
-                    let mut conn_tag_err_rx = connector.link_errors();
-                    let tag_err_tx = tag_err_tx.clone();
-                    exec::spawn(async move {
-                        while let Ok(err) = conn_tag_err_rx.recv().await {
-                            let _ = tag_err_tx.send(err);
-                        }
-                    });
-                    let mut disabled_tags_rx = disabled_tags_rx.clone();
-                    exec::spawn(async move {
-                        loop {
-                            let disabled_tags: HashSet<LinkTagBox> =
-                                (*disabled_tags_rx.borrow_and_update()).clone();
-                            connector.set_disabled_tags(disabled_tags);
-                            if disabled_tags_rx.changed().await.is_err() {
-                                break;
+                            if no_monitor {
+                                eprintln!("来自 {src} 的连接已结束");
                             }
-                        }
-                    });
 
-                    let _ = control_tx.send((control.clone(), format!("{src}: {server_port}->{client_port}")));
+                            if once {
+                                exit(0);
+                            }
 
-                    exec::spawn(async move {
-                        if no_monitor {
-                            eprintln!("来自 {src} 的连接请求端口 {client_port}");
-                        }
+                            anyhow::Ok(())
+                        });
+                        continue;
+                    }
 
-                        let ch = outgoing.await?;
-                        let (server_read, mut server_write) = ch.into_stream().into_split();
-                        server_write.write_u16(server_port).await?;
+                    let (control, outgoing) = connect();
+                    let _ = control_tx.send((control, format!("{src}: {server_port}->{client_port}")));
 
-                        let (client_read, client_write) = socket.into_split();
-                        exec::spawn(forward(client_read, server_write));
-                        forward(server_read, client_write).await?;
+                    exec::spawn(async move {
+                        let ch = outgoing.await?;
+                        let (server_read, server_write) = ch.into_stream().into_split();
+                        Self::handle_connection(socket, server_read, server_write, server_port).await?;
 
                         if no_monitor {
                             eprintln!("来自 {src} 的连接已结束");
@@ -457,6 +587,17 @@ impl ClientCli {
 
         Ok(())
     }
+
+    async fn handle_connection(
+        socket: TcpStream, server_read: impl AsyncRead + Unpin + Send + 'static,
+        mut server_write: impl AsyncWrite + Unpin + Send + 'static, server_port: u16,
+    ) -> Result<()> {
+        server_write.write_u16(server_port).await?;
+
+        let (client_read, client_write) = socket.into_split();
+        exec::spawn(forward(client_read, server_write));
+        forward(server_read, client_write).await
+    }
 }
 
 #[derive(Parser)]
@@ -471,9 +612,16 @@ pub struct ServerCli {
     /// 目标可以是主机名或 IP 地址；未指定时默认使用 localhost。
     #[arg(long, short = 'p', value_parser = parse_key_val::<String, u16>, required=true)]
     port: Vec<(String, u16)>,
+    /// 通过同一个聚合连接复用客户端转发的 TCP 连接。
+    ///
+    /// 客户端也必须启用此选项。
+    #[arg(long, short = 'm')]
+    mux: bool,
     /// 要监听的 TCP 端口。
-    #[arg(long)]
-    tcp: Option<u16>,
+    ///
+    /// 可以指定多个端口或端口范围，例如 `5800-5803`，以便客户端建立多条并行链路。
+    #[arg(long, value_name = "PORTS")]
+    tcp: Option<String>,
     /// 启用 openppp2 Turbo 风格的 TCP 优化（禁用 Nagle 并放大缓冲区）。
     #[arg(long)]
     tcp_turbo: bool,
@@ -486,9 +634,16 @@ pub struct ServerCli {
     /// 设置 IPv4 数据包的 TOS/DSCP 值（默认 Turbo 模式下为 0x10）。
     #[arg(long, value_name = "TOS")]
     tcp_tos: Option<u8>,
+    #[command(flatten)]
+    tcp_tuning: TcpTuningArgs,
     /// 自定义 CTCP printable 加密密钥（支持十进制、0x 十六进制、0b 二进制或 0o 八进制，默认沿用 openppp2 的内置值）。
     #[arg(long, value_name = "KEY", value_parser = parse_ctcp_key, default_value_t = ctcp::DEFAULT_KEY)]
     ctcp_key: u32,
+    /// 通过标准输入和输出提供单条链路，例如由 SSH 或 inetd 启动时。
+    ///
+    /// 此模式下不显示链路监视器，日志输出到标准错误，链路断开后程序退出。
+    #[arg(long)]
+    stdio: bool,
     /// 要监听的 RFCOMM 信道号。
     #[cfg(feature = "bluer")]
     #[arg(long)]
@@ -505,7 +660,9 @@ pub struct ServerCli {
 
 impl ServerCli {
     async fn run(self, cfg: Cfg, dump: Option<PathBuf>) -> Result<()> {
-        let no_monitor = self.no_monitor || !stdout().is_tty();
+        let no_monitor = self.no_monitor || self.stdio || !stdout().is_tty();
+        let mux = self.mux;
+        let stdio = self.stdio;
 
         let ports: Arc<HashMap<_, _>> = Arc::new(
             self.port
@@ -524,6 +681,10 @@ impl ServerCli {
 
         let mut builder = AcceptorBuilder::new(cfg);
         builder.wrap(CtcpWrapper::with_key(self.ctcp_key));
+        if stdio {
+            // Stop accepting once the link over standard input and output has terminated.
+            builder.set_no_transport_timeout(Duration::ZERO);
+        }
         if let Some(dump) = dump {
             builder.set_task_cfg(move |task| {
                 let (tx, rx) = mpsc::channel(DUMP_BUFFER);
@@ -534,20 +695,29 @@ impl ServerCli {
 
         let acceptor = builder.build();
         let mut server_ports = Vec::new();
-        let tcp_socket_options =
+        let mut tcp_socket_options =
             tcp_socket_options_from_cli(self.tcp_turbo, self.tcp_send_buffer, self.tcp_recv_buffer, self.tcp_tos);
+        self.tcp_tuning.apply(&mut tcp_socket_options);
 
-        if let Some(port) = self.tcp {
-            match TcpAcceptor::new([SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), port)]).await {
+        if let Some(ports) = &self.tcp {
+            let addrs = util::parse_ports(ports)?
+                .into_iter()
+                .map(|port| SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), port));
+            match TcpAcceptor::new(addrs).await {
                 Ok(mut tcp) => {
                     tcp.set_socket_options(tcp_socket_options.clone());
                     server_ports.push(format!("TCP 端口 {tcp}"));
                     acceptor.add(tcp);
                 }
-                Err(err) => eprintln!("无法监听 TCP 端口 {port}：{err}"),
+                Err(err) => eprintln!("无法监听 TCP 端口 {ports}：{err}"),
             }
         }
 
+        if stdio {
+            acceptor.add(StdioAcceptor::new());
+            server_ports.push("标准输入输出".to_string());
+        }
+
         #[cfg(feature = "bluer")]
         if let Some(ch) = self.rfcomm {
             match RfcommAcceptor::new(aggligator_transport_bluer::rfcomm::SocketAddr::new(
@@ -619,7 +789,11 @@ impl ServerCli {
             let term_tx = broadcast::Sender::<()>::new(1);
             loop {
                 let (ch, control) = tokio::select! {
-                    res = acceptor.accept() => res?,
+                    res = acceptor.accept() => match res {
+                        Ok(res) => res,
+                        Err(_) if stdio => break,
+                        Err(err) => return Err(err.into()),
+                    },
                     () = wait_sigterm() => break,
                 };
 
@@ -634,6 +808,30 @@ impl ServerCli {
 
                 let id = ch.id();
 
+                if mux {
+                    let _ = control_tx.send((control, format!("复用连接 {id}")));
+
+                    let ports = ports.clone();
+                    exec::spawn(async move {
+                        let mut mux = ch.into_mux();
+                        while let Some(stream) = mux.accept().await? {
+                            let ports = ports.clone();
+                            exec::spawn(async move {
+                                let (client_read, client_write) = split(stream);
+                                if let Err(err) =
+                                    Self::handle_client(ports, client_write, client_read, !no_monitor, None).await
+                                {
+                                    if no_monitor {
+                                        eprintln!("连接 {id} 的子流失败：{err}");
+                                    }
+                                }
+                            });
+                        }
+                        anyhow::Ok(())
+                    });
+                    continue;
+                }
+
                 let control_tx = control_tx.clone();
                 let (target_tx, target_rx) = oneshot::channel();
                 exec::spawn(async move {
@@ -649,7 +847,7 @@ impl ServerCli {
                 exec::spawn(async move {
                     let (client_read, client_write) = ch.into_stream().into_split();
                     if let Err(err) =
-                        Self::handle_client(ports, client_write, client_read, !no_monitor, target_tx).await
+                        Self::handle_client(ports, client_write, client_read, !no_monitor, Some(target_tx)).await
                     {
                         if no_monitor {
                             eprintln!("连接 {id} 失败：{err}");
@@ -681,13 +879,16 @@ impl ServerCli {
     }
 
     async fn handle_client(
-        ports: Arc<HashMap<u16, String>>, client_write: SenderSink, mut client_read: ReceiverStream, quiet: bool,
-        target_tx: oneshot::Sender<(u16, String)>,
+        ports: Arc<HashMap<u16, String>>, client_write: impl AsyncWrite + Unpin + Send + 'static,
+        mut client_read: impl AsyncRead + Unpin + Send + 'static, quiet: bool,
+        target_tx: Option<oneshot::Sender<(u16, String)>>,
     ) -> Result<()> {
         let port = client_read.read_u16().await?;
 
         if let Some(target) = ports.get(&port) {
-            let _ = target_tx.send((port, target.clone()));
+            if let Some(target_tx) = target_tx {
+                let _ = target_tx.send((port, target.clone()));
+            }
             if !quiet {
                 eprintln!("客户端请求的端口 {port} 连接到 {target}");
             }
@@ -738,7 +939,7 @@ async fn forward(mut read: impl AsyncRead + Unpin, mut write: impl AsyncWrite +
         write.write_all(&buf).await?;
     }
 
-    write.flush().await?;
+    write.shutdown().await?;
     Ok(())
 }
 
@@ -765,11 +966,36 @@ fn parse_ctcp_key(value: &str) -> std::result::Result<u32, String> {
     u32::from_str_radix(&digits.replace('_', ""), radix).map_err(|err| format!("无法解析 CTCP 密钥：{err}"))
 }
 
+/// Firewall mark given in decimal or hexadecimal notation.
+#[derive(Clone, Copy, Debug)]
+struct Fwmark(u32);
+
+impl FromStr for Fwmark {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Some(hex) => u32::from_str_radix(hex, 16).map(Self),
+            None => s.parse().map(Self),
+        }
+    }
+}
+
+fn parse_iface_val<T>(s: &str) -> std::result::Result<(Option<String>, T), T::Err>
+where
+    T: FromStr,
+{
+    match s.split_once('=') {
+        Some((iface, value)) => Ok((Some(iface.to_string()), value.parse()?)),
+        None => Ok((None, s.parse()?)),
+    }
+}
+
 fn parse_key_val<T, U>(s: &str) -> std::result::Result<(T, U), Box<dyn std::error::Error + Send + Sync + 'static>>
 where
-    T: std::str::FromStr + Default,
+    T: FromStr + Default,
     T::Err: std::error::Error + Send + Sync + 'static,
-    U: std::str::FromStr,
+    U: FromStr,
     U::Err: std::error::Error + Send + Sync + 'static,
 {
     match s.rfind(':') {
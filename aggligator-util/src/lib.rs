@@ -12,11 +12,11 @@
 pub mod ctcp;
 
 use anyhow::{bail, Context};
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
 use aggligator::cfg::Cfg;
-use aggligator_transport_tcp::TcpLinkFilter;
+use aggligator_transport_tcp::{TcpLinkFilter, TcpSocketOptions};
 
 /// 为命令行工具初始化日志系统。
 pub fn init_log() {
@@ -24,6 +24,17 @@ pub fn init_log() {
     tracing_log::LogTracer::init().unwrap();
 }
 
+/// 为命令行工具初始化日志系统，日志输出到标准错误。
+///
+/// 适用于标准输出被占用的情况，例如通过标准输入输出提供链路时。
+pub fn init_log_stderr() {
+    tracing_subscriber::registry()
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(EnvFilter::from_default_env())
+        .init();
+    tracing_log::LogTracer::init().unwrap();
+}
+
 /// 打印 Aggligator 的默认配置。
 pub fn print_default_cfg() {
     println!("{}", serde_json::to_string_pretty(&Cfg::default()).unwrap());
@@ -46,7 +57,64 @@ pub fn parse_tcp_link_filter(s: &str) -> anyhow::Result<TcpLinkFilter> {
         "none" => Ok(TcpLinkFilter::None),
         "interface-interface" => Ok(TcpLinkFilter::InterfaceInterface),
         "interface-ip" => Ok(TcpLinkFilter::InterfaceIp),
-        other => bail!("未知的 TCP 链路过滤器：{other}"),
+        other => match other.strip_prefix("parallel:").map(|n| n.parse()) {
+            Some(Ok(n)) => Ok(TcpLinkFilter::Parallel(n)),
+            _ => bail!("未知的 TCP 链路过滤器：{other}"),
+        },
+    }
+}
+
+/// TCP 保活、超时与拥塞控制相关的命令行选项。
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct TcpTuningArgs {
+    /// 启用 TCP 保活，并设置连接空闲多少秒后开始发送保活探测。
+    #[arg(long, value_name = "SECS")]
+    pub tcp_keepalive: Option<u64>,
+    /// TCP 保活探测的间隔（秒）。
+    #[arg(long, value_name = "SECS")]
+    pub tcp_keepalive_interval: Option<u64>,
+    /// 连续多少次保活探测无响应后断开连接。
+    #[arg(long, value_name = "COUNT")]
+    pub tcp_keepalive_retries: Option<u32>,
+    /// 已发送数据最长多少毫秒未被确认后由内核断开连接（TCP_USER_TIMEOUT，仅限 Linux）。
+    ///
+    /// 设置为小于链路 ping 超时的值，可让内核更快发现失效的链路。
+    #[arg(long, value_name = "MILLIS")]
+    pub tcp_user_timeout: Option<u64>,
+    /// 设置 IPv6 数据包的流量类别（traffic class）。
+    #[arg(long, value_name = "TCLASS")]
+    pub tcp_tclass: Option<u32>,
+    /// TCP 拥塞控制算法（TCP_CONGESTION，仅限 Linux 和 FreeBSD），可重复指定。
+    ///
+    /// 格式为 `[网卡=]算法`，例如 `wwan0=bbr`；未指定网卡时作用于所有网卡。
+    #[arg(long, value_name = "[IFACE=]ALGO")]
+    pub tcp_congestion: Vec<String>,
+}
+
+impl TcpTuningArgs {
+    /// 将选项应用到 TCP 套接字选项。
+    pub fn apply(&self, options: &mut TcpSocketOptions) {
+        if let Some(secs) = self.tcp_keepalive {
+            options.set_keepalive_time(Some(Duration::from_secs(secs)));
+        }
+        if let Some(secs) = self.tcp_keepalive_interval {
+            options.set_keepalive_interval(Some(Duration::from_secs(secs)));
+        }
+        if let Some(retries) = self.tcp_keepalive_retries {
+            options.set_keepalive_retries(Some(retries));
+        }
+        if let Some(millis) = self.tcp_user_timeout {
+            options.set_user_timeout(Some(Duration::from_millis(millis)));
+        }
+        if let Some(tclass) = self.tcp_tclass {
+            options.set_tclass_v6(Some(tclass));
+        }
+        for congestion in &self.tcp_congestion {
+            match congestion.split_once('=') {
+                Some((iface, algo)) => options.set_interface_congestion(iface, Some(algo.to_string())),
+                None => options.set_congestion(Some(congestion.clone())),
+            }
+        }
     }
 }
 
@@ -0,0 +1,172 @@
+//! Delay-based congestion control of links.
+
+use std::{collections::VecDeque, time::Duration};
+
+use crate::exec::time::Instant;
+
+/// Duration covered by one entry of the base delay history.
+const BASE_DELAY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of entries of the base delay history.
+const BASE_DELAY_HISTORY: usize = 10;
+
+/// Number of roundtrip samples used for filtering the current delay.
+const CURRENT_DELAY_SAMPLES: usize = 4;
+
+/// Number of delivery rate measurements the maximum is taken over.
+const DELIVERY_RATE_SAMPLES: usize = 10;
+
+/// Minimum duration of a delivery rate measurement.
+const DELIVERY_RATE_MIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Increase of the limit in packets per roundtrip when the delay is at zero.
+const GAIN: f64 = 1.0;
+
+/// Increase of the limit above the sent unacknowledged data in packets.
+const ALLOWED_INCREASE: usize = 2;
+
+/// Delay-based congestion controller of a link in the style of LEDBAT (RFC 6817).
+///
+/// It adjusts the limit of unacknowledged data of a link, so that the queuing delay,
+/// i.e. the measured roundtrip time minus the minimum roundtrip time, stays near the target.
+/// The limit never drops below the estimated bandwidth-delay product of the link.
+#[derive(Debug)]
+pub(crate) struct DelayController {
+    /// Target queuing delay.
+    target: Duration,
+    /// Packet size.
+    packet_size: usize,
+    /// Maximum limit of unacknowledged data.
+    max_limit: usize,
+    /// Limit is doubled every roundtrip until the target delay is approached.
+    slow_start: bool,
+    /// Minimum roundtrip per interval, starting with the oldest.
+    base_delays: VecDeque<(Instant, Duration)>,
+    /// Most recent roundtrip samples.
+    current_delays: VecDeque<Duration>,
+    /// Start of current delivery rate measurement and bytes acknowledged since then.
+    delivery: (Instant, usize),
+    /// Recent delivery rates in bytes per second.
+    delivery_rates: VecDeque<f64>,
+    /// When the limit was last decreased multiplicatively.
+    last_decrease: Option<Instant>,
+}
+
+impl DelayController {
+    /// Creates a new delay-based congestion controller.
+    pub fn new(target: Duration, packet_size: usize, max_limit: usize) -> Self {
+        Self {
+            target: target.max(Duration::from_millis(1)),
+            packet_size: packet_size.max(1),
+            max_limit,
+            slow_start: true,
+            base_delays: VecDeque::new(),
+            current_delays: VecDeque::new(),
+            delivery: (Instant::now(), 0),
+            delivery_rates: VecDeque::new(),
+            last_decrease: None,
+        }
+    }
+
+    /// Minimum measured roundtrip time.
+    pub fn base_delay(&self) -> Option<Duration> {
+        self.base_delays.iter().map(|&(_, delay)| delay).min()
+    }
+
+    /// Filtered current roundtrip time.
+    fn current_delay(&self) -> Option<Duration> {
+        self.current_delays.iter().copied().min()
+    }
+
+    /// Estimated bandwidth-delay product in bytes.
+    pub fn bdp(&self) -> usize {
+        let rate = self.delivery_rates.iter().copied().fold(0.0, f64::max);
+        let base = self.base_delay().unwrap_or_default();
+        (rate * base.as_secs_f64()) as usize
+    }
+
+    /// Records a roundtrip sample.
+    fn record_delay(&mut self, now: Instant, delay: Duration) {
+        match self.base_delays.back_mut() {
+            Some((since, base)) if now.duration_since(*since) < BASE_DELAY_INTERVAL => {
+                *base = (*base).min(delay);
+            }
+            _ => {
+                self.base_delays.push_back((now, delay));
+                if self.base_delays.len() > BASE_DELAY_HISTORY {
+                    self.base_delays.pop_front();
+                }
+            }
+        }
+
+        self.current_delays.push_back(delay);
+        if self.current_delays.len() > CURRENT_DELAY_SAMPLES {
+            self.current_delays.pop_front();
+        }
+    }
+
+    /// Records acknowledged data for measuring the delivery rate.
+    fn record_delivery(&mut self, now: Instant, acked: usize) {
+        let interval = self.base_delay().unwrap_or_default().max(DELIVERY_RATE_MIN_INTERVAL);
+        let (since, delivered) = &mut self.delivery;
+        *delivered += acked;
+
+        let elapsed = now.duration_since(*since);
+        if elapsed >= interval {
+            self.delivery_rates.push_back(*delivered as f64 / elapsed.as_secs_f64());
+            if self.delivery_rates.len() > DELIVERY_RATE_SAMPLES {
+                self.delivery_rates.pop_front();
+            }
+            self.delivery = (now, 0);
+        }
+    }
+
+    /// Adjusts the limit of unacknowledged data when data has been acknowledged.
+    ///
+    /// The roundtrip sample must only be provided if the data was not resent.
+    /// Increases of the limit are scaled by the specified factor.
+    pub fn on_ack(
+        &mut self, limit: &mut usize, acked: usize, roundtrip: Option<Duration>, unacked: usize, increase: f64,
+    ) {
+        let now = Instant::now();
+        self.record_delivery(now, acked);
+        if let Some(roundtrip) = roundtrip {
+            self.record_delay(now, roundtrip);
+        }
+
+        let (Some(base), Some(current)) = (self.base_delay(), self.current_delay()) else { return };
+        let queuing = current.saturating_sub(base);
+        let mut new_limit = *limit as f64;
+
+        if self.slow_start && queuing < self.target / 2 {
+            new_limit += increase * acked as f64;
+        } else if queuing > self.target * 2 {
+            // Drain a queue that has built up far beyond target at most once per roundtrip.
+            self.slow_start = false;
+            if self.last_decrease.map_or(true, |last| now.duration_since(last) >= current) {
+                new_limit /= 2.0;
+                self.last_decrease = Some(now);
+            }
+        } else {
+            self.slow_start = false;
+            let off_target = ((self.target.as_secs_f64() - queuing.as_secs_f64()) / self.target.as_secs_f64())
+                .clamp(-1.0, 1.0);
+            let change = GAIN * off_target * acked as f64 * self.packet_size as f64 / new_limit;
+            new_limit += if change > 0.0 { increase * change } else { change };
+        }
+
+        // Only grow if the limit is being used.
+        let allowed = (unacked + acked + ALLOWED_INCREASE * self.packet_size).max(*limit);
+        let min_limit = self.bdp().max(ALLOWED_INCREASE * self.packet_size);
+        *limit = (new_limit as usize).min(allowed).clamp(min_limit.min(self.max_limit), self.max_limit);
+    }
+
+    /// Restarts probing for bandwidth after the link has been unconfirmed.
+    pub fn reset(&mut self) {
+        self.slow_start = true;
+        self.current_delays.clear();
+        self.delivery = (Instant::now(), 0);
+        self.delivery_rates.clear();
+        self.last_decrease = None;
+    }
+}
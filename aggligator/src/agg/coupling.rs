@@ -0,0 +1,301 @@
+//! Coupled congestion control of links sharing a bottleneck.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use crate::{exec::time::Instant, id::LinkId};
+
+/// Duration of an interval over which roundtrip samples are averaged.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Number of intervals used for correlating roundtrip times.
+const SAMPLE_HISTORY: usize = 50;
+
+/// Minimum number of intervals with samples from both links for correlating them.
+const MIN_COMMON_SAMPLES: usize = 20;
+
+/// Correlation coefficient above which links are considered to share a bottleneck.
+const CORRELATION_THRESHOLD: f64 = 0.7;
+
+/// Minimum relative variation of the roundtrip time for it to be used for correlation.
+///
+/// A link whose queue is empty shows a constant roundtrip time, which carries no information.
+const MIN_VARIATION: f64 = 0.05;
+
+/// Interval between runs of shared bottleneck detection.
+const DETECTION_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Roundtrip history of a link.
+#[derive(Debug, Default)]
+struct History {
+    /// Sum and count of roundtrip samples per interval, indexed by interval number.
+    intervals: VecDeque<(u64, f64, u32)>,
+}
+
+impl History {
+    /// Records a roundtrip sample in the specified interval.
+    fn record(&mut self, interval: u64, roundtrip: Duration) {
+        match self.intervals.back_mut() {
+            Some((last, sum, count)) if *last == interval => {
+                *sum += roundtrip.as_secs_f64();
+                *count += 1;
+            }
+            _ => {
+                self.intervals.push_back((interval, roundtrip.as_secs_f64(), 1));
+                if self.intervals.len() > SAMPLE_HISTORY {
+                    self.intervals.pop_front();
+                }
+            }
+        }
+    }
+
+    /// Mean roundtrip time per interval.
+    fn means(&self) -> HashMap<u64, f64> {
+        self.intervals.iter().map(|&(interval, sum, count)| (interval, sum / count as f64)).collect()
+    }
+}
+
+/// Pearson correlation coefficient of the means of two roundtrip histories
+/// over their common intervals.
+fn correlation(a: &HashMap<u64, f64>, b: &HashMap<u64, f64>) -> Option<f64> {
+    let pairs: Vec<_> = a.iter().filter_map(|(interval, &x)| b.get(interval).map(|&y| (x, y))).collect();
+    if pairs.len() < MIN_COMMON_SAMPLES {
+        return None;
+    }
+
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in &pairs {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+
+    let (sd_x, sd_y) = ((var_x / n).sqrt(), (var_y / n).sqrt());
+    if sd_x < MIN_VARIATION * mean_x || sd_y < MIN_VARIATION * mean_y {
+        return None;
+    }
+
+    Some(cov / (var_x * var_y).sqrt())
+}
+
+/// Detects links sharing a bottleneck and couples their congestion control.
+///
+/// Links whose roundtrip times rise and fall together are assumed to share a queue
+/// at a common bottleneck. Losses on a link result in retransmissions by the
+/// underlying transport and thus appear as increased roundtrip time as well.
+///
+/// The increase of the unacknowledged data limits of links sharing a bottleneck
+/// is coupled following the linked increases algorithm (LIA, RFC 6356), so that
+/// together they take no more capacity than a single link would.
+#[derive(Debug)]
+pub(crate) struct Coupling {
+    /// Start of interval numbering.
+    epoch: Instant,
+    /// Roundtrip history of each link.
+    histories: HashMap<LinkId, History>,
+    /// When the coupling was last updated.
+    last_update: Option<Instant>,
+    /// When shared bottleneck detection was last run.
+    last_detection: Option<Instant>,
+    /// Group of links sharing a bottleneck, identified by the smallest link id of the group.
+    groups: HashMap<LinkId, LinkId>,
+}
+
+impl Default for Coupling {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+            histories: HashMap::new(),
+            last_update: None,
+            last_detection: None,
+            groups: HashMap::new(),
+        }
+    }
+}
+
+impl Coupling {
+    /// Records a roundtrip sample of a link.
+    pub fn record(&mut self, link_id: LinkId, roundtrip: Duration) {
+        let interval = (self.epoch.elapsed().as_nanos() / SAMPLE_INTERVAL.as_nanos()) as u64;
+        self.histories.entry(link_id).or_default().record(interval, roundtrip);
+    }
+
+    /// Groups of at least two links sharing a bottleneck.
+    pub fn groups(&self) -> Vec<Vec<LinkId>> {
+        let mut groups: HashMap<LinkId, Vec<LinkId>> = HashMap::new();
+        for (&link_id, &group) in &self.groups {
+            groups.entry(group).or_default().push(link_id);
+        }
+        groups.into_values().filter(|links| links.len() >= 2).collect()
+    }
+
+    /// Whether the coupling of links should be updated.
+    pub fn is_update_due(&mut self) -> bool {
+        if self.last_update.is_some_and(|last| last.elapsed() < SAMPLE_INTERVAL) {
+            return false;
+        }
+        self.last_update = Some(Instant::now());
+        true
+    }
+
+    /// Runs shared bottleneck detection on the specified links, if it is due.
+    pub fn detect(&mut self, link_ids: &[LinkId]) {
+        if self.last_detection.is_some_and(|last| last.elapsed() < DETECTION_INTERVAL) {
+            return;
+        }
+        self.last_detection = Some(Instant::now());
+
+        self.histories.retain(|link_id, _| link_ids.contains(link_id));
+        let means: Vec<_> =
+            link_ids.iter().map(|link_id| self.histories.get(link_id).map(History::means)).collect();
+
+        // Merge correlated links into groups.
+        let mut groups: HashMap<LinkId, LinkId> = link_ids.iter().map(|&id| (id, id)).collect();
+        for a in 0..link_ids.len() {
+            for b in a + 1..link_ids.len() {
+                let (Some(means_a), Some(means_b)) = (&means[a], &means[b]) else { continue };
+                if correlation(means_a, means_b).is_some_and(|c| c >= CORRELATION_THRESHOLD) {
+                    let (group_a, group_b) = (groups[&link_ids[a]], groups[&link_ids[b]]);
+                    let merged = group_a.min(group_b);
+                    for group in groups.values_mut() {
+                        if *group == group_a || *group == group_b {
+                            *group = merged;
+                        }
+                    }
+                }
+            }
+        }
+
+        for (link_id, group) in &groups {
+            if self.groups.get(link_id).unwrap_or(link_id) != group {
+                tracing::debug!(?link_id, ?group, "link assigned to shared bottleneck group");
+            }
+        }
+        self.groups = groups;
+    }
+
+    /// Calculates the factor by which the increase of the unacknowledged data limit
+    /// of each link must be scaled.
+    ///
+    /// Each link is specified by its unacknowledged data limit and roundtrip time.
+    /// The links must form one group sharing a bottleneck.
+    pub fn increase_factors(links: &[(usize, Duration)]) -> Vec<f64> {
+        if links.len() < 2 {
+            return vec![1.0; links.len()];
+        }
+
+        let rtt = |roundtrip: Duration| roundtrip.as_secs_f64().max(0.001);
+        let total: f64 = links.iter().map(|&(limit, _)| limit as f64).sum();
+        let best =
+            links.iter().map(|&(limit, roundtrip)| limit as f64 / rtt(roundtrip).powi(2)).fold(0.0, f64::max);
+        let sum: f64 = links.iter().map(|&(limit, roundtrip)| limit as f64 / rtt(roundtrip)).sum();
+        let alpha = total * best / sum.powi(2);
+
+        // Increase of link i is min(alpha / total, 1 / limit_i) times the uncoupled increase of 1 / limit_i.
+        links.iter().map(|&(limit, _)| (alpha * limit as f64 / total).min(1.0)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Coupling, SAMPLE_HISTORY};
+    use crate::id::LinkId;
+
+    /// Deterministic pseudo-random load between 0 and 1.
+    fn load(seed: u64, n: u64) -> f64 {
+        let mut x = (seed << 32 | n).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        x ^= x >> 29;
+        x = x.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x ^= x >> 32;
+        (x % 1000) as f64 / 1000.0
+    }
+
+    fn roundtrip(base_ms: f64, load: f64) -> Duration {
+        Duration::from_secs_f64((base_ms + 40.0 * load) / 1000.0)
+    }
+
+    /// Records roundtrip samples of two links and runs shared bottleneck detection.
+    fn detect(rtt_a: impl Fn(u64) -> Duration, rtt_b: impl Fn(u64) -> Duration) -> Vec<Vec<LinkId>> {
+        let (a, b) = (LinkId(1), LinkId(2));
+        let mut coupling = Coupling::default();
+        for interval in 0..SAMPLE_HISTORY as u64 {
+            for _ in 0..3 {
+                coupling.histories.entry(a).or_default().record(interval, rtt_a(interval));
+                coupling.histories.entry(b).or_default().record(interval, rtt_b(interval));
+            }
+        }
+        coupling.detect(&[a, b]);
+        coupling.groups()
+    }
+
+    #[test]
+    fn correlated_links_share_bottleneck() {
+        // Both links are delayed by the same queue, with a small independent contribution.
+        let groups = detect(
+            |n| roundtrip(20.0, load(1, n) + 0.1 * load(2, n)),
+            |n| roundtrip(60.0, load(1, n) + 0.1 * load(3, n)),
+        );
+        assert_eq!(groups.len(), 1);
+        let mut group = groups[0].clone();
+        group.sort();
+        assert_eq!(group, [LinkId(1), LinkId(2)]);
+    }
+
+    #[test]
+    fn uncorrelated_links_are_independent() {
+        let groups = detect(|n| roundtrip(20.0, load(1, n)), |n| roundtrip(60.0, load(2, n)));
+        assert!(groups.is_empty(), "independent links grouped: {groups:?}");
+    }
+
+    #[test]
+    fn constant_roundtrip_is_not_correlated() {
+        // Links with empty queues carry no information about a shared bottleneck.
+        let groups = detect(|n| roundtrip(20.0, 0.001 * load(1, n)), |n| roundtrip(60.0, 0.001 * load(1, n)));
+        assert!(groups.is_empty(), "idle links grouped: {groups:?}");
+    }
+
+    #[test]
+    fn single_link_increase_unchanged() {
+        assert_eq!(Coupling::increase_factors(&[(100_000, Duration::from_millis(50))]), [1.0]);
+    }
+
+    #[test]
+    fn coupled_increase_not_above_single_link() {
+        let cases: &[&[(usize, u64)]] = &[
+            &[(100_000, 50), (100_000, 50)],
+            &[(400_000, 50), (50_000, 50), (10_000, 50)],
+            &[(100_000, 20), (100_000, 200)],
+            &[(20_000, 20), (500_000, 200)],
+            &[(300_000, 10), (300_000, 30), (300_000, 90), (1_000, 500)],
+        ];
+
+        for links in cases {
+            let links: Vec<_> =
+                links.iter().map(|&(limit, roundtrip)| (limit, Duration::from_millis(roundtrip))).collect();
+            let factors = Coupling::increase_factors(&links);
+            assert!(factors.iter().all(|&factor| factor > 0.0 && factor <= 1.0), "{links:?}: {factors:?}");
+
+            // Without coupling each link increases its limit by the same amount per roundtrip.
+            // Coupled, the total increase of the sending rate must not exceed that of
+            // a single link on the path with the shortest roundtrip time.
+            let rate = |roundtrip: Duration| 1.0 / roundtrip.as_secs_f64();
+            let total: f64 =
+                links.iter().zip(&factors).map(|(&(_, roundtrip), factor)| factor * rate(roundtrip)).sum();
+            let single = links.iter().map(|&(_, roundtrip)| rate(roundtrip)).fold(0.0, f64::max);
+            assert!(total <= single * (1.0 + 1e-9), "{links:?}: {factors:?} increase {total} above {single}");
+
+            // With equal roundtrip times the factors sum to at most one.
+            if links.iter().all(|&(_, roundtrip)| roundtrip == links[0].1) {
+                assert!(factors.iter().sum::<f64>() <= 1.0 + 1e-9, "{links:?}: {factors:?}");
+            }
+        }
+    }
+}
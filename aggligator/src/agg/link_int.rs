@@ -6,7 +6,7 @@ use std::{
     collections::VecDeque,
     fmt, io, mem,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU8, Ordering},
         Arc,
     },
     task::{Context, Poll},
@@ -18,10 +18,12 @@ use tokio::{
 };
 
 use crate::{
-    cfg::{Cfg, ExchangedCfg},
-    control::{Direction, DisconnectReason, Link, LinkIntervalStats, LinkStats, NotWorkingReason},
+    agg::{congestion::DelayController, one_way_delay::OneWayDelays},
+    cfg::{Cfg, CongestionControl, ExchangedCfg, Extensions},
+    control::{Direction, DisconnectReason, Link, LinkIntervalStats, LinkStats, NotWorkingReason, OneWayDelay},
+    crypto::ConnSecret,
     exec::time::{sleep_until, Instant},
-    id::{ConnId, LinkId},
+    id::{ConnId, LinkId, ServerId},
     msg::LinkMsg,
     seq::Seq,
 };
@@ -50,6 +52,8 @@ pub(crate) enum LinkIntEvent {
     Disconnect,
     /// Link blocked status has changed.
     BlockedChanged,
+    /// Link priority has changed.
+    PriorityChanged,
 }
 
 /// Link test status.
@@ -86,6 +90,10 @@ pub(crate) struct LinkInt<TX, RX, TAG> {
     cfg: Arc<Cfg>,
     /// Configuration of remote endpoint.
     remote_cfg: Arc<ExchangedCfg>,
+    /// Protocol extensions negotiated during link establishment.
+    extensions: Extensions,
+    /// Server id of remote endpoint, if it is a server.
+    remote_server_id: Option<ServerId>,
     /// Whether the Accepeted message needs to be sent.
     pub(crate) needs_tx_accepted: bool,
     /// Transmit sink.
@@ -116,8 +124,22 @@ pub(crate) struct LinkInt<TX, RX, TAG> {
     pub(crate) txed_unacked_data_limit_increased: Option<Seq>,
     /// Times `txed_unacked_data_limit` was increased consecutively.
     pub(crate) txed_unacked_data_limit_increased_consecutively: usize,
+    /// Delay-based congestion controller adjusting `txed_unacked_data_limit`.
+    pub(crate) delay_controller: Option<DelayController>,
+    /// Factor for increases of `txed_unacked_data_limit` due to coupling with links sharing a bottleneck.
+    pub(crate) coupled_increase: f64,
+    /// Whether the link was detected to share a bottleneck with other links.
+    pub(crate) shared_bottleneck: bool,
     /// Acks queued for sending.
     pub(crate) tx_ack_queue: VecDeque<Seq>,
+    /// Sequence numbers of data messages sent over other links queued for
+    /// sending a redundant copy over this link.
+    pub(crate) tx_redundant_queue: VecDeque<Seq>,
+    /// Redundant copies sent over this link that have not been acknowledged, with their size.
+    pub(crate) txed_redundant: VecDeque<(Seq, usize)>,
+    /// Parities sent over this link that count as unacknowledged data, with the
+    /// sequence number following the protected group and their size.
+    pub(crate) txed_parities: VecDeque<(Seq, usize)>,
     /// Number of acks sent since last flush.
     txed_acks_unflushed: usize,
     /// Receive sink.
@@ -144,6 +166,12 @@ pub(crate) struct LinkInt<TX, RX, TAG> {
     blocked_changed_out_rx: watch::Receiver<()>,
     /// Link blocked by remote endpoint.
     pub(crate) remotely_blocked: Arc<AtomicBool>,
+    /// Link priority.
+    pub(crate) priority: Arc<AtomicU8>,
+    /// Link priority changed.
+    pub(crate) priority_changed_tx: mpsc::Sender<()>,
+    /// Link priority changed receiver.
+    priority_changed_rx: mpsc::Receiver<()>,
     /// Since when the link is unconfirmed, i.e. it has not been tested or message
     /// acknowledgement timed out.
     pub(crate) unconfirmed: Option<(Instant, NotWorkingReason)>,
@@ -163,12 +191,24 @@ pub(crate) struct LinkInt<TX, RX, TAG> {
     pub(crate) send_ping: bool,
     /// Send ping reply when link becomes ready for sending.
     pub(crate) send_pong: bool,
+    /// Send timestamp and local receive timestamp of ping to reply to with a timestamped pong.
+    pub(crate) pong_timestamps: Option<(u64, u64)>,
+    /// One-way delay samples from timestamped pings.
+    pub(crate) one_way_delays: OneWayDelays,
+    /// One-way delay to the remote endpoint relative to the other links of the connection.
+    pub(crate) forward_delay: Option<OneWayDelay>,
+    /// One-way delay from the remote endpoint relative to the other links of the connection.
+    pub(crate) backward_delay: Option<OneWayDelay>,
+    /// Estimated absolute one-way delay to the remote endpoint.
+    pub(crate) estimated_forward_delay: Option<Duration>,
     /// Initiator of disconnection.
     pub(crate) disconnecting: Option<DisconnectInitiator>,
     /// Goodbye message has been sent.
     pub(crate) goodbye_sent: bool,
     /// User data provided by remote endpoint.
     remote_user_data: Arc<Vec<u8>>,
+    /// Diffie-Hellman shared secret of the link, if it established the connection.
+    pub(crate) conn_secret: Option<ConnSecret>,
     /// Link statistics calculator.
     stats: LinkStatistican,
 }
@@ -198,6 +238,16 @@ impl<TX, RX, TAG> LinkInt<TX, RX, TAG> {
     pub(crate) fn remote_cfg(&self) -> Arc<ExchangedCfg> {
         self.remote_cfg.clone()
     }
+
+    /// Protocol extensions negotiated during link establishment.
+    pub(crate) fn extensions(&self) -> Extensions {
+        self.extensions
+    }
+
+    /// Server id of remote endpoint, if it is a server.
+    pub(crate) fn remote_server_id(&self) -> Option<ServerId> {
+        self.remote_server_id
+    }
 }
 
 impl<TX, RX, TAG> LinkInt<TX, RX, TAG>
@@ -208,12 +258,14 @@ where
     /// Creates new internal link data.
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
-        tag: TAG, conn_id: ConnId, tx: TX, rx: RX, cfg: Arc<Cfg>, remote_cfg: ExchangedCfg, direction: Direction,
-        roundtrip: Duration, remote_user_data: Vec<u8>,
+        tag: TAG, conn_id: ConnId, tx: TX, rx: RX, cfg: Arc<Cfg>, remote_cfg: ExchangedCfg,
+        extensions: Extensions, remote_server_id: Option<ServerId>, direction: Direction, roundtrip: Duration,
+        remote_user_data: Vec<u8>,
     ) -> Self {
         let (disconnected_tx, _) = watch::channel(DisconnectReason::TaskTerminated);
         let (disconnect_tx, disconnect_rx) = mpsc::channel(1);
         let (blocked_changed_tx, blocked_changed_rx) = mpsc::channel(2);
+        let (priority_changed_tx, priority_changed_rx) = mpsc::channel(1);
         let stats = LinkStatistican::new(&cfg.stats_intervals, roundtrip);
         let (unconfirmed_tx, unconfirmed_rx) = watch::channel(None);
         let (blocked_changed_out_tx, blocked_changed_out_rx) = watch::channel(());
@@ -228,6 +280,8 @@ where
             tx_error: None,
             rx,
             remote_cfg: Arc::new(remote_cfg),
+            extensions,
+            remote_server_id,
             needs_tx_accepted: direction == Direction::Incoming,
             disconnected_tx,
             disconnect_tx,
@@ -242,6 +296,9 @@ where
             blocked_changed_out_tx,
             blocked_changed_out_rx,
             remotely_blocked: Arc::new(AtomicBool::new(false)),
+            priority: Arc::new(AtomicU8::new(0)),
+            priority_changed_tx,
+            priority_changed_rx,
             unconfirmed: None,
             unconfirmed_tx,
             unconfirmed_rx,
@@ -255,18 +312,35 @@ where
             current_ping_sent: None,
             send_ping: false,
             send_pong: false,
+            pong_timestamps: None,
+            one_way_delays: OneWayDelays::default(),
+            forward_delay: None,
+            backward_delay: None,
+            estimated_forward_delay: None,
             roundtrip,
             disconnecting: None,
             txed_unacked_data: 0,
             txed_unacked_data_limit: cfg.link_unacked_init.get(),
             txed_unacked_data_limit_increased: None,
             txed_unacked_data_limit_increased_consecutively: 45,
+            delay_controller: match cfg.link_congestion_control {
+                CongestionControl::Heuristic => None,
+                CongestionControl::Ledbat(target) => {
+                    Some(DelayController::new(target, cfg.io_write_size.get(), cfg.link_unacked_limit.get()))
+                }
+            },
+            coupled_increase: 1.0,
+            shared_bottleneck: false,
             txed_acks_unflushed: 0,
             tx_ack_queue: VecDeque::new(),
+            tx_redundant_queue: VecDeque::new(),
+            txed_redundant: VecDeque::new(),
+            txed_parities: VecDeque::new(),
             tx_idle_since: None,
             tx_pending: false,
             cfg,
             remote_user_data: Arc::new(remote_user_data),
+            conn_secret: None,
         }
     }
 
@@ -361,7 +435,11 @@ where
                                             _ => (),
                                         }
 
-                                        if let LinkMsg::Data { .. } = &msg {
+                                        if let LinkMsg::Data { .. }
+                                        | LinkMsg::CompressedData { .. }
+                                        | LinkMsg::Parity { .. }
+                                        | LinkMsg::Datagram { .. } = &msg
+                                        {
                                             self.rxed_data_msg = Some(msg);
                                         } else {
                                             break LinkIntEvent::Rx { msg, data: None };
@@ -397,6 +475,7 @@ where
             () = flush_req_task => LinkIntEvent::FlushDelayPassed,
             Some(()) = self.disconnect_rx.recv() => LinkIntEvent::Disconnect,
             Some(()) = self.blocked_changed_rx.recv() => LinkIntEvent::BlockedChanged,
+            Some(()) = self.priority_changed_rx.recv() => LinkIntEvent::PriorityChanged,
         }
     }
 
@@ -436,16 +515,22 @@ where
 
         match &msg {
             LinkMsg::Ack { .. } | LinkMsg::Consumed { .. } => self.txed_acks_unflushed += 1,
-            LinkMsg::Data { seq } => match self.txed_unacked {
+            LinkMsg::Data { seq } | LinkMsg::CompressedData { seq } => match self.txed_unacked {
                 Some(txed_unacked) if txed_unacked > *seq => (),
                 _ => self.txed_unacked = Some(*seq),
             },
             LinkMsg::Accepted
+            | LinkMsg::Established
             | LinkMsg::Ping
             | LinkMsg::Pong
+            | LinkMsg::TimestampedPing { .. }
+            | LinkMsg::TimestampedPong { .. }
             | LinkMsg::SendFinish { .. }
             | LinkMsg::ReceiveClose { .. }
             | LinkMsg::ReceiveFinish { .. }
+            | LinkMsg::Resume { .. }
+            | LinkMsg::Parity { .. }
+            | LinkMsg::Datagram { .. }
             | LinkMsg::Goodbye => self.start_flush(),
             _ => (),
         }
@@ -569,6 +654,11 @@ where
         self.txed_unacked_data < self.txed_unacked_data_limit
     }
 
+    /// Total data sent over the link.
+    pub(crate) fn total_sent(&self) -> u64 {
+        self.stats.current.total_sent
+    }
+
     /// Since when transmitter is being polled for readyness.
     pub(crate) fn tx_polling(&self) -> Option<Instant> {
         self.tx_polling
@@ -583,6 +673,9 @@ where
         self.txed_unacked_data_limit = self.txed_unacked_data_limit.clamp(128, self.cfg.link_unacked_init.get());
         self.txed_unacked_data_limit_increased = None;
         self.txed_unacked_data_limit_increased_consecutively = 0;
+        if let Some(delay_controller) = &mut self.delay_controller {
+            delay_controller.reset();
+        }
     }
 
     /// Whether link is blocked locally or remotely.
@@ -590,11 +683,19 @@ where
         self.blocked.load(Ordering::SeqCst) || self.remotely_blocked.load(Ordering::SeqCst)
     }
 
+    /// Link priority.
+    pub(crate) fn priority(&self) -> u8 {
+        self.priority.load(Ordering::SeqCst)
+    }
+
     /// Publishes link statistics.
     pub(crate) fn publish_stats(&mut self) {
         self.stats.current.sent_unacked = self.txed_unacked_data as _;
         self.stats.current.unacked_limit = self.txed_unacked_data_limit as _;
         self.stats.current.roundtrip = self.roundtrip;
+        self.stats.current.forward_delay = self.forward_delay;
+        self.stats.current.backward_delay = self.backward_delay;
+        self.stats.current.shared_bottleneck = self.shared_bottleneck;
 
         self.stats.publish();
     }
@@ -617,6 +718,8 @@ impl<TX, RX, TAG> From<&LinkInt<TX, RX, TAG>> for Link<TAG> {
             blocked_changed_rx: link_int.blocked_changed_out_rx.clone(),
             not_working_rx: link_int.unconfirmed_rx.clone(),
             remotely_blocked: link_int.remotely_blocked.clone(),
+            priority: link_int.priority.clone(),
+            priority_changed_tx: link_int.priority_changed_tx.clone(),
         }
     }
 }
@@ -645,6 +748,9 @@ impl LinkStatistican {
             roundtrip,
             hangs: 0,
             time_stats: running_stats.clone(),
+            forward_delay: None,
+            backward_delay: None,
+            shared_bottleneck: false,
         };
 
         Self { tx: watch::channel(current.clone()).0, current, running_stats }
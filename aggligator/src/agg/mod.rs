@@ -4,14 +4,14 @@ use bytes::Bytes;
 use futures::{Sink, Stream};
 use std::{
     io,
-    sync::{atomic::AtomicBool, Arc},
+    sync::{atomic::AtomicBool, Arc, RwLock},
 };
-use tokio::sync::{mpsc, oneshot, watch, Mutex};
+use tokio::sync::{broadcast, mpsc, oneshot, watch, Mutex};
 
 use crate::{
     agg::{link_int::LinkInt, task::Task},
-    alc::{Channel, RecvError, SendError},
-    cfg::{Cfg, ExchangedCfg},
+    alc::{Channel, RecvError, SendError, SendPriority},
+    cfg::{Cfg, ExchangedCfg, Extensions},
     control::{Control, Direction, Link},
     id::{OwnedConnId, ServerId},
     TaskError,
@@ -21,7 +21,11 @@ use crate::{
 #[cfg_attr(docsrs, doc(cfg(feature = "dump")))]
 pub mod dump;
 
+pub(crate) mod congestion;
+pub(crate) mod coupling;
 pub(crate) mod link_int;
+pub(crate) mod one_way_delay;
+pub mod scheduler;
 pub(crate) mod task;
 
 /// Link aggregator parts.
@@ -39,15 +43,15 @@ where
     TAG: Send + Sync + 'static,
 {
     /// Creates a new aggregated connection and returns its parts.
-    #[allow(clippy::type_complexity)]
+    #[allow(clippy::type_complexity, clippy::too_many_arguments)]
     pub(crate) fn new(
         cfg: Arc<Cfg>, conn_id: OwnedConnId, direction: Direction, server_id: Option<ServerId>,
-        remote_server_id: Option<ServerId>, links: Vec<LinkInt<TX, RX, TAG>>,
+        remote_server_id: Option<ServerId>, extensions: Option<Extensions>, links: Vec<LinkInt<TX, RX, TAG>>,
         link_tx_rx: Option<(mpsc::Sender<LinkInt<TX, RX, TAG>>, mpsc::Receiver<LinkInt<TX, RX, TAG>>)>,
     ) -> Self {
         let (terminate_tx, terminate_rx) = mpsc::channel(1);
         let (read_tx, read_rx) = mpsc::channel(cfg.recv_queue.get());
-        let (write_tx, write_rx) = mpsc::channel(cfg.send_queue.get());
+        let (write_tx, write_rx) = SendPriority::ALL.iter().map(|_| mpsc::channel(cfg.send_queue.get())).unzip();
         let (read_error_tx, read_error_rx) = watch::channel(Some(RecvError::TaskTerminated));
         let (write_error_tx, write_error_rx) = watch::channel(SendError::TaskTerminated);
         let (read_closed_tx, read_closed_rx) = mpsc::channel(1);
@@ -57,6 +61,9 @@ where
         let (stats_tx, stats_rx) = watch::channel(Default::default());
         let (server_changed_tx, server_changed_rx) = mpsc::channel(1);
         let (result_tx, result_rx) = watch::channel(Err(TaskError::Terminated));
+        let (datagram_tx, datagram_rx) = mpsc::channel(cfg.datagram_queue.get());
+        let (datagram_recv_tx, datagram_recv_rx) = broadcast::channel(cfg.datagram_queue.get());
+        let (suspend_tx, suspend_rx) = mpsc::channel(1);
         let remote_cfg = links.first().as_ref().map(|link| link.remote_cfg());
         let connected = Arc::new(AtomicBool::new(!links.is_empty()));
 
@@ -64,6 +71,7 @@ where
             task: Task::new(
                 cfg.clone(),
                 remote_cfg.clone(),
+                extensions,
                 conn_id.clone(),
                 direction,
                 terminate_rx,
@@ -78,6 +86,9 @@ where
                 stats_tx,
                 server_changed_rx,
                 result_tx,
+                datagram_rx,
+                datagram_recv_tx,
+                suspend_rx,
                 links,
             ),
             channel: Channel::new(
@@ -100,9 +111,13 @@ where
                 link_tx,
                 links_rx,
                 connected,
+                extensions: Arc::new(RwLock::new(extensions)),
                 stats_rx,
                 server_changed_tx,
                 result_rx,
+                datagram_tx,
+                datagram_rx: Arc::new(datagram_recv_rx),
+                suspend_tx,
             },
             connected_rx,
         }
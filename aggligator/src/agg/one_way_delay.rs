@@ -0,0 +1,146 @@
+//! One-way delay measurement of links using timestamped pings.
+
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{control::OneWayDelay, exec::time::Instant};
+
+/// Duration covered by one entry of the base delay history.
+const BASE_DELAY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of entries of the base delay history.
+const BASE_DELAY_HISTORY: usize = 10;
+
+/// Number of samples used for filtering the current delay.
+const CURRENT_DELAY_SAMPLES: usize = 4;
+
+/// Number of samples used for estimating the trend of the delay.
+const TREND_SAMPLES: usize = 16;
+
+/// Minimum number of samples for estimating the trend of the delay.
+const MIN_TREND_SAMPLES: usize = 4;
+
+/// Timestamp in microseconds since the specified epoch.
+pub(crate) fn timestamp(epoch: Instant, at: Instant) -> u64 {
+    at.duration_since(epoch).as_micros() as u64
+}
+
+/// Delay samples of a link in one direction.
+///
+/// A sample is the difference between the receive timestamp and the send timestamp,
+/// which are taken from the clocks of different endpoints.
+/// It thus contains an unknown offset between both clocks, which is the same for all
+/// links of a connection, and is only meaningful compared to other samples.
+#[derive(Debug, Default)]
+pub(crate) struct DelaySamples {
+    /// Minimum delay per interval, starting with the oldest.
+    base: VecDeque<(Instant, i64)>,
+    /// Most recent samples with their time of measurement.
+    recent: VecDeque<(Instant, i64)>,
+}
+
+impl DelaySamples {
+    /// Records a delay sample in microseconds.
+    fn record(&mut self, now: Instant, delay: i64) {
+        match self.base.back_mut() {
+            Some((since, base)) if now.duration_since(*since) < BASE_DELAY_INTERVAL => {
+                *base = (*base).min(delay);
+            }
+            _ => {
+                self.base.push_back((now, delay));
+                if self.base.len() > BASE_DELAY_HISTORY {
+                    self.base.pop_front();
+                }
+            }
+        }
+
+        self.recent.push_back((now, delay));
+        if self.recent.len() > TREND_SAMPLES {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Minimum recently measured delay.
+    pub fn base(&self) -> Option<i64> {
+        self.base.iter().map(|&(_, delay)| delay).min()
+    }
+
+    /// Filtered current delay.
+    pub fn current(&self) -> Option<i64> {
+        self.recent.iter().rev().take(CURRENT_DELAY_SAMPLES).map(|&(_, delay)| delay).min()
+    }
+
+    /// Change of the delay in microseconds per second, estimated by linear regression.
+    pub fn trend(&self) -> Option<i64> {
+        if self.recent.len() < MIN_TREND_SAMPLES {
+            return None;
+        }
+
+        let (start, _) = self.recent[0];
+        let n = self.recent.len() as f64;
+        let points: Vec<_> = self
+            .recent
+            .iter()
+            .map(|&(at, delay)| (at.duration_since(start).as_secs_f64(), delay as f64))
+            .collect();
+        let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+        let mean_d = points.iter().map(|(_, d)| d).sum::<f64>() / n;
+
+        let (mut cov, mut var) = (0.0, 0.0);
+        for (t, d) in &points {
+            cov += (t - mean_t) * (d - mean_d);
+            var += (t - mean_t).powi(2);
+        }
+        if var == 0.0 {
+            return None;
+        }
+
+        Some((cov / var) as i64)
+    }
+
+    /// Current delay relative to the specified base delay of the connection.
+    pub fn relative_to(&self, base: i64) -> Option<OneWayDelay> {
+        let current = self.current()?;
+        Some(OneWayDelay {
+            relative: Duration::from_micros(current.saturating_sub(base).max(0) as u64),
+            trend: self.trend().unwrap_or_default(),
+        })
+    }
+}
+
+/// One-way delay samples of a link in both directions.
+#[derive(Debug, Default)]
+pub(crate) struct OneWayDelays {
+    /// Delay from the local to the remote endpoint.
+    pub forward: DelaySamples,
+    /// Delay from the remote to the local endpoint.
+    pub backward: DelaySamples,
+}
+
+impl OneWayDelays {
+    /// Records the timestamps of a completed timestamped ping.
+    ///
+    /// `ping_sent` and `pong_received` are local timestamps, while
+    /// `ping_received` and `pong_sent` are timestamps of the remote endpoint.
+    pub fn record(&mut self, ping_sent: u64, ping_received: u64, pong_sent: u64, pong_received: u64) {
+        let now = Instant::now();
+        self.forward.record(now, ping_received as i64 - ping_sent as i64);
+        self.backward.record(now, pong_received as i64 - pong_sent as i64);
+    }
+
+    /// Filtered roundtrip time in microseconds, excluding processing time of the remote endpoint.
+    pub fn roundtrip(&self) -> Option<i64> {
+        Some(self.forward.current()? + self.backward.current()?)
+    }
+
+    /// Clock offset of the remote endpoint in microseconds, assuming that
+    /// the link has the same delay in both directions.
+    pub fn symmetric_clock_offset(&self) -> Option<i64> {
+        Some((self.forward.current()? - self.backward.current()?) / 2)
+    }
+
+    /// Estimated delay from the local to the remote endpoint using the specified clock offset.
+    pub fn estimated_forward(&self, clock_offset: i64) -> Option<Duration> {
+        let forward = self.forward.current()?.saturating_sub(clock_offset);
+        Some(Duration::from_micros(forward.clamp(0, self.roundtrip()?.max(0)) as u64))
+    }
+}
@@ -0,0 +1,183 @@
+//! Link scheduling.
+//!
+//! A [link scheduler](LinkScheduler) decides over which link of a connection
+//! the next data packet is sent.
+//! It is consulted by the [connection task](crate::Task) whenever data is ready to
+//! be sent and at least one link is able to accept it.
+//!
+//! By default the [`DefaultLinkScheduler`] is used, which sends data over the link
+//! that most recently became ready.
+//! Since links that are faster become ready more often, this naturally distributes
+//! data in proportion to the capacity of each link.
+//! The [`LowestDelayLinkScheduler`] instead prefers the ready link over which data
+//! arrives soonest, which is useful if links have very different latencies.
+//!
+//! A custom scheduler can be set using [`Task::set_link_scheduler`](crate::Task::set_link_scheduler)
+//! or [`ConnectorBuilder::set_link_scheduler`](crate::transport::ConnectorBuilder::set_link_scheduler).
+//! Independently of the scheduler, the connection task limits the amount of
+//! unacknowledged data on each link to prevent slow links from stalling the connection.
+//!
+
+use std::{fmt, time::Duration};
+
+use crate::id::LinkId;
+
+/// State of a link presented to a [link scheduler](LinkScheduler).
+pub struct LinkState<'a, TAG> {
+    pub(crate) id: LinkId,
+    pub(crate) tag: &'a TAG,
+    pub(crate) ready: bool,
+    pub(crate) working: bool,
+    pub(crate) priority: u8,
+    pub(crate) roundtrip: Duration,
+    pub(crate) forward_delay: Duration,
+    pub(crate) unacked: usize,
+    pub(crate) unacked_limit: usize,
+    pub(crate) total_sent: u64,
+}
+
+impl<TAG> fmt::Debug for LinkState<'_, TAG> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LinkState")
+            .field("id", &self.id)
+            .field("ready", &self.ready)
+            .field("working", &self.working)
+            .field("priority", &self.priority)
+            .field("roundtrip", &self.roundtrip)
+            .field("forward_delay", &self.forward_delay)
+            .field("unacked", &self.unacked)
+            .field("unacked_limit", &self.unacked_limit)
+            .field("total_sent", &self.total_sent)
+            .finish()
+    }
+}
+
+impl<TAG> LinkState<'_, TAG> {
+    /// The link id.
+    pub fn id(&self) -> LinkId {
+        self.id
+    }
+
+    /// The user-defined tag of the link.
+    pub fn tag(&self) -> &TAG {
+        self.tag
+    }
+
+    /// Whether the link is idle and can accept data for sending right now.
+    ///
+    /// Links that have a lower [priority](Self::priority) than another working link
+    /// are never ready.
+    /// Only ready links may be selected by a link scheduler.
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    /// Whether the link is confirmed and not blocked.
+    ///
+    /// A working link that is not [ready](Self::is_ready) is busy sending
+    /// or has reached its limit of unacknowledged data.
+    pub fn is_working(&self) -> bool {
+        self.working
+    }
+
+    /// The [priority](crate::Link::set_priority) of the link.
+    pub fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    /// Round trip duration, i.e. ping.
+    pub fn roundtrip(&self) -> Duration {
+        self.roundtrip
+    }
+
+    /// Estimated one-way delay from the local to the remote endpoint.
+    ///
+    /// If the [timestamps extension](crate::cfg::Extensions::TIMESTAMPS) is supported
+    /// by both endpoints, this is measured using timestamped pings.
+    /// The unknown clock offset between both endpoints is estimated from the link
+    /// with the shortest roundtrip time, assuming that its delay is the same in both directions.
+    ///
+    /// Otherwise, or until the link has been pinged, half of the [roundtrip time](Self::roundtrip)
+    /// is used.
+    pub fn forward_delay(&self) -> Duration {
+        self.forward_delay
+    }
+
+    /// Data sent over the link but not yet acknowledged by the remote endpoint in bytes.
+    pub fn unacked(&self) -> usize {
+        self.unacked
+    }
+
+    /// Current limit of [unacknowledged data](Self::unacked) in bytes.
+    pub fn unacked_limit(&self) -> usize {
+        self.unacked_limit
+    }
+
+    /// Total data sent over the link in bytes.
+    pub fn total_sent(&self) -> u64 {
+        self.total_sent
+    }
+}
+
+/// Selects the link over which the next data packet is sent.
+///
+/// The scheduler is called with the state of all links of the connection.
+/// Links that are [ready](LinkState::is_ready) are listed last and are ordered
+/// by the time they became ready, i.e. the most recently ready link comes last.
+///
+/// It must return the index of a ready link within the provided slice or
+/// `None` to hold back the data until the state of a link changes.
+/// Returning the index of a link that is not ready is treated as `None`.
+///
+/// Holding back data while a working link is busy is useful to keep traffic off
+/// links that should only be used when all others are saturated.
+/// Note that holding back data while no other link is working stalls the connection.
+///
+/// The scheduler is called from the connection task and thus must execute quickly.
+///
+/// A closure of the form `FnMut(&[LinkState<TAG>]) -> Option<usize>` can be used as a scheduler.
+pub trait LinkScheduler<TAG>: Send {
+    /// Selects the link for sending the next data packet.
+    fn select(&mut self, links: &[LinkState<'_, TAG>]) -> Option<usize>;
+}
+
+impl<TAG, F> LinkScheduler<TAG> for F
+where
+    F: FnMut(&[LinkState<'_, TAG>]) -> Option<usize> + Send,
+{
+    fn select(&mut self, links: &[LinkState<'_, TAG>]) -> Option<usize> {
+        self(links)
+    }
+}
+
+/// The default link scheduler.
+///
+/// It sends data over the link that most recently became ready.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultLinkScheduler;
+
+impl<TAG> LinkScheduler<TAG> for DefaultLinkScheduler {
+    fn select(&mut self, links: &[LinkState<'_, TAG>]) -> Option<usize> {
+        links.iter().rposition(|link| link.ready)
+    }
+}
+
+/// A link scheduler that sends data over the ready link with the
+/// lowest [forward delay](LinkState::forward_delay).
+///
+/// Ties are broken in favor of the link that most recently became ready.
+/// Use [`Task::set_link_scheduler`](crate::Task::set_link_scheduler) to enable it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LowestDelayLinkScheduler;
+
+impl<TAG> LinkScheduler<TAG> for LowestDelayLinkScheduler {
+    fn select(&mut self, links: &[LinkState<'_, TAG>]) -> Option<usize> {
+        links
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, link)| link.ready)
+            .min_by_key(|(_, link)| link.forward_delay)
+            .map(|(idx, _)| idx)
+    }
+}
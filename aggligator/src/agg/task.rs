@@ -12,7 +12,7 @@ use std::{
     error::Error,
     fmt,
     future::IntoFuture,
-    io,
+    io, mem,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -21,18 +21,25 @@ use std::{
 };
 use tokio::{
     select,
-    sync::{mpsc, oneshot, watch},
+    sync::{broadcast, mpsc, oneshot, watch},
 };
 
 use crate::{
-    agg::link_int::{DisconnectInitiator, LinkInt, LinkIntEvent, LinkTest},
+    agg::{
+        coupling::Coupling,
+        link_int::{DisconnectInitiator, LinkInt, LinkIntEvent, LinkTest},
+        one_way_delay::timestamp,
+        scheduler::{DefaultLinkScheduler, LinkScheduler, LinkState},
+    },
     alc::{RecvError, SendError},
-    cfg::{Cfg, ExchangedCfg, LinkPing},
-    control::{Direction, DisconnectReason, Link, NotWorkingReason, Stats},
+    cfg::{Cfg, ExchangedCfg, Extensions, LinkPing},
+    control::{Direction, DisconnectReason, Link, NotWorkingReason, ResumptionTicket, Stats, SuspendError},
+    crypto::Cipher,
     exec::time::{interval_stream, sleep_until, timeout, Instant},
-    id::{ConnId, LinkId, OwnedConnId},
+    fec::{FecDecoder, FecEncoder, Parity},
+    id::{ConnId, LinkId, OwnedConnId, ServerId},
     msg::{LinkMsg, RefusedReason, ReliableMsg},
-    peekable_mpsc::{PeekableReceiver, RecvIfError},
+    peekable_mpsc::{PriorityReceiver, RecvIfError},
     protocol_err,
     seq::Seq,
 };
@@ -57,6 +64,9 @@ pub enum TaskError {
     ServerIdMismatch,
     /// The connection was forcefully terminated.
     Terminated,
+    /// The connection was [suspended](crate::control::Control::suspend) for
+    /// resuming it on another server.
+    Suspended,
 }
 
 impl fmt::Display for TaskError {
@@ -67,6 +77,7 @@ impl fmt::Display for TaskError {
             Self::ProtocolError { link_id, error } => write!(f, "protocol error on link {link_id}: {error}"),
             Self::ServerIdMismatch => write!(f, "a new link connected to another server"),
             Self::Terminated => write!(f, "connection forcefully terminated"),
+            Self::Suspended => write!(f, "connection suspended"),
         }
     }
 }
@@ -88,6 +99,22 @@ pub(crate) enum SendReq {
     Flush(oneshot::Sender<()>),
 }
 
+/// An unreliable datagram queued for sending.
+#[derive(Debug)]
+pub(crate) struct Datagram {
+    /// Data.
+    pub data: Bytes,
+    /// Time after which the datagram is discarded instead of sent.
+    pub expires: Option<Instant>,
+}
+
+impl Datagram {
+    /// Whether the datagram has expired.
+    fn is_expired(&self) -> bool {
+        self.expires.is_some_and(|expires| expires <= Instant::now())
+    }
+}
+
 /// Send overrun handling.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum SendOverrun {
@@ -135,6 +162,8 @@ enum SentReliableStatus {
     Received {
         /// Size of data.
         size: usize,
+        /// Sent message, kept if the session is resumable.
+        msg: Option<ReliableMsg>,
     },
     /// Message has been queued for resending.
     ResendQueued {
@@ -150,6 +179,8 @@ struct ReceivedReliableMsg {
     seq: Seq,
     /// Message.
     msg: ReliableMsg,
+    /// Size of data as transmitted.
+    size: usize,
 }
 
 /// Link aggregator task event.
@@ -166,6 +197,8 @@ enum TaskEvent<TX, RX, TAG> {
     WriteRx { id: usize, data: Bytes },
     /// No more data to send will be received.
     WriteEnd,
+    /// Datagram to send over an idle link has been received.
+    DatagramTx { id: usize, datagram: Datagram },
     /// Flush.
     Flush(oneshot::Sender<()>),
     /// Confirmation of sent packet over specified link timed out.
@@ -198,6 +231,8 @@ enum TaskEvent<TX, RX, TAG> {
     RefusedLinkTask,
     /// The server id changed.
     ServerChanged,
+    /// Suspend the connection for resumption on another server.
+    Suspend(oneshot::Sender<Result<ResumptionTicket, SuspendError>>),
 }
 
 /// Forceful connection termination.
@@ -228,6 +263,22 @@ pub struct Task<TX, RX, TAG> {
     /// Configuration of remote endpoint.
     /// `None` if not connected yet.
     remote_cfg: Option<Arc<ExchangedCfg>>,
+    /// Protocol extensions used by the connection.
+    /// `None` if not connected yet.
+    extensions: Option<Extensions>,
+    /// Server id of remote endpoint.
+    /// `None` if not connected yet or remote endpoint is not a server.
+    remote_server_id: Option<ServerId>,
+    /// Server ids of remote servers the session has been resumed from.
+    previous_server_ids: HashSet<ServerId>,
+    /// Cipher for end-to-end encryption of data.
+    /// `None` if encryption is not used or the connection is not established yet.
+    cipher: Option<Cipher>,
+    /// Forward error correction of sent messages.
+    /// `None` if not used or the connection is not established yet.
+    fec_encoder: Option<FecEncoder>,
+    /// Forward error correction of received messages.
+    fec_decoder: FecDecoder,
     /// Connection identifier.
     conn_id: OwnedConnId,
     /// Connection direction.
@@ -253,7 +304,7 @@ pub struct Task<TX, RX, TAG> {
     /// ReceiveFinish message has been sent.
     receive_finish_sent: bool,
     /// Channel for receiving messages to send from user.
-    write_rx: Option<PeekableReceiver<SendReq>>,
+    write_rx: Option<PriorityReceiver<SendReq>>,
     /// Whether remote endpoint closed its receiver.
     write_closed: Arc<AtomicBool>,
     /// SendFinish message has been sent.
@@ -278,6 +329,18 @@ pub struct Task<TX, RX, TAG> {
     txed_unconsumable: usize,
     /// Sequence number of last packet consumed by the remote endpoint.
     txed_last_consumed: Seq,
+    /// Data messages received but not yet consumed by the remote endpoint,
+    /// kept if the session is resumable.
+    txed_unconsumed_msgs: VecDeque<ReliableMsg>,
+    /// Size of data consumed by the remote endpoint that has not yet been
+    /// removed from `txed_unconsumed_msgs`.
+    txed_consumed_unprocessed: usize,
+    /// Number of data messages consumed by the remote endpoint and
+    /// removed from `txed_unconsumed_msgs`.
+    txed_consumed_count: u64,
+    /// Data messages to send once the remote endpoint has reported how many it
+    /// has received while resuming the session, starting with message `txed_consumed_count`.
+    resume_msgs: Option<VecDeque<ReliableMsg>>,
     /// Queue of packets that have been declared lost and must be send again.
     resend_queue: VecDeque<Arc<SentReliable>>,
     /// Ids of links that are ready to send data.
@@ -290,14 +353,16 @@ pub struct Task<TX, RX, TAG> {
     rxed_reliable_consumable: VecDeque<ReceivedReliableMsg>,
     /// Sum of size of all buffers in `rxed_reliable` and `rxed_reliable_consumable`.
     rxed_reliable_size: usize,
+    /// Number of received data messages that have become ready for consumption.
+    rxed_data_count: u64,
     /// Size of that that has been consumed since last acknowledgement.
     rxed_reliable_consumed_since_last_ack: usize,
     /// Forces acking consumed data.
     rxed_reliable_consumed_force_ack: bool,
     /// Ids of links that are currently being flushed by user request.
     unflushed_links: HashSet<usize>,
-    /// Channel for sending notification when flushing completed.
-    flushed_tx: Option<oneshot::Sender<()>>,
+    /// Channels for sending notification when flushing completed.
+    flushed_tx: Vec<oneshot::Sender<()>>,
     /// Time when task was started.
     start_time: Instant,
     /// Time when both read_tx and write_rx became None.
@@ -310,14 +375,34 @@ pub struct Task<TX, RX, TAG> {
     stats_last_sent: Instant,
     /// Filter function for new links.
     link_filter: LinkFilterFn<TAG>,
+    /// Scheduler selecting the link for sending data.
+    link_scheduler: Box<dyn LinkScheduler<TAG>>,
+    /// Whether a link is ready for data by link id, used by the link scheduler.
+    scheduler_ready: Vec<bool>,
+    /// Ids of the links presented to the link scheduler.
+    scheduler_ids: Vec<usize>,
+    /// Allocation of the link states presented to the link scheduler.
+    scheduler_states: Vec<LinkState<'static, ()>>,
+    /// Coupled congestion control of links sharing a bottleneck.
+    coupling: Option<Coupling>,
     /// Links provided at creation of this task.
     init_links: VecDeque<LinkInt<TX, RX, TAG>>,
+    /// Links waiting for the link that established the connection to set up encryption.
+    links_awaiting_cipher: Vec<LinkInt<TX, RX, TAG>>,
     /// Tasks handling refused links.
     refused_links_tasks: FuturesUnordered<BoxFuture<'static, ()>>,
     /// Server changed notification.
     server_changed_rx: mpsc::Receiver<()>,
+    /// Channel for receiving suspension requests.
+    suspend_rx: mpsc::Receiver<oneshot::Sender<Result<ResumptionTicket, SuspendError>>>,
     /// Result of task sender.
     result_tx: watch::Sender<Result<(), TaskError>>,
+    /// Channel for receiving datagrams to send from user.
+    datagram_rx: mpsc::Receiver<Datagram>,
+    /// Channel for sending received datagrams to user.
+    datagram_tx: broadcast::Sender<Bytes>,
+    /// Number of next datagram for sending.
+    datagram_number: u64,
     /// Channel for sending analysis data.
     #[cfg(feature = "dump")]
     dump_tx: Option<mpsc::Sender<super::dump::ConnDump>>,
@@ -337,17 +422,28 @@ where
 {
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
-        cfg: Arc<Cfg>, remote_cfg: Option<Arc<ExchangedCfg>>, conn_id: OwnedConnId, direction: Direction,
-        terminate_rx: mpsc::Receiver<()>, links_tx: watch::Sender<Vec<Link<TAG>>>,
-        link_rx: mpsc::Receiver<LinkInt<TX, RX, TAG>>, connected_tx: oneshot::Sender<Arc<ExchangedCfg>>,
-        read_tx: mpsc::Sender<Bytes>, read_closed_rx: mpsc::Receiver<()>, write_rx: mpsc::Receiver<SendReq>,
+        cfg: Arc<Cfg>, remote_cfg: Option<Arc<ExchangedCfg>>, extensions: Option<Extensions>,
+        conn_id: OwnedConnId, direction: Direction, terminate_rx: mpsc::Receiver<()>,
+        links_tx: watch::Sender<Vec<Link<TAG>>>, link_rx: mpsc::Receiver<LinkInt<TX, RX, TAG>>,
+        connected_tx: oneshot::Sender<Arc<ExchangedCfg>>, read_tx: mpsc::Sender<Bytes>,
+        read_closed_rx: mpsc::Receiver<()>, write_rx: Vec<mpsc::Receiver<SendReq>>,
         read_error_tx: watch::Sender<Option<RecvError>>, write_error_tx: watch::Sender<SendError>,
         stats_tx: watch::Sender<Stats>, server_changed_rx: mpsc::Receiver<()>,
-        result_tx: watch::Sender<Result<(), TaskError>>, links: Vec<LinkInt<TX, RX, TAG>>,
+        result_tx: watch::Sender<Result<(), TaskError>>, datagram_rx: mpsc::Receiver<Datagram>,
+        datagram_tx: broadcast::Sender<Bytes>,
+        suspend_rx: mpsc::Receiver<oneshot::Sender<Result<ResumptionTicket, SuspendError>>>,
+        links: Vec<LinkInt<TX, RX, TAG>>,
     ) -> Self {
+        let coupling = cfg.link_coupled_congestion_control.then(Coupling::default);
         Self {
             cfg,
             remote_cfg,
+            extensions,
+            remote_server_id: None,
+            previous_server_ids: HashSet::new(),
+            cipher: None,
+            fec_encoder: None,
+            fec_decoder: FecDecoder::default(),
             conn_id,
             direction,
             terminate_rx,
@@ -360,7 +456,7 @@ where
             read_closed_rx: Some(read_closed_rx),
             receive_close_sent: false,
             receive_finish_sent: false,
-            write_rx: Some(write_rx.into()),
+            write_rx: Some(PriorityReceiver::new(write_rx)),
             write_closed: Arc::new(AtomicBool::new(false)),
             send_finish_sent: false,
             read_error_tx,
@@ -379,20 +475,35 @@ where
             txed_unconsumed: 0,
             txed_unconsumable: 0,
             txed_last_consumed: Seq::MINUS_ONE,
+            txed_unconsumed_msgs: VecDeque::new(),
+            txed_consumed_unprocessed: 0,
+            txed_consumed_count: 0,
+            resume_msgs: None,
             rxed_reliable_size: 0,
+            rxed_data_count: 0,
             rxed_reliable_consumed_force_ack: false,
             unflushed_links: HashSet::new(),
-            flushed_tx: None,
+            flushed_tx: Vec::new(),
             start_time: Instant::now(),
             read_write_closed: None,
             established: None,
             stats_tx,
             stats_last_sent: Instant::now(),
             link_filter: Box::new(|_, _| async { true }.boxed()),
+            link_scheduler: Box::new(DefaultLinkScheduler),
+            scheduler_ready: Vec::new(),
+            scheduler_ids: Vec::new(),
+            scheduler_states: Vec::new(),
+            coupling,
             init_links: links.into(),
+            links_awaiting_cipher: Vec::new(),
             refused_links_tasks: FuturesUnordered::new(),
             server_changed_rx,
+            suspend_rx,
             result_tx,
+            datagram_rx,
+            datagram_tx,
+            datagram_number: 0,
             #[cfg(feature = "dump")]
             dump_tx: None,
         }
@@ -427,6 +538,7 @@ where
             let resending = !self.resend_queue.is_empty();
             let links_idling = !self.idle_links.is_empty();
             let links_available = self.links.iter().any(Option::is_some);
+            let resuming = self.resume_msgs.is_some();
 
             // Send statistics and dump.
             self.send_stats();
@@ -443,7 +555,8 @@ where
                     && self.rxed_reliable_size == 0
                     && self.rxed_reliable_consumed_since_last_ack == 0
                     && self.send_finish_sent
-                    && self.receive_finish_sent)
+                    && self.receive_finish_sent
+                    && !resuming)
                     || !links_available
                     || since.elapsed() >= self.cfg.termination_timeout
                 {
@@ -478,15 +591,19 @@ where
 
             // Notify that flushing has completed.
             if self.unflushed_links.is_empty() {
-                if let Some(tx) = self.flushed_tx.take() {
+                for tx in self.flushed_tx.drain(..) {
                     tracing::trace!("flush request completed");
                     let _ = tx.send(());
                 }
             }
 
             // Adjust link transmit buffer limits.
+            self.update_coupling();
             self.adjust_link_tx_limits();
 
+            // Select idle link for sending data.
+            let sendable_idle_link_id = self.schedule_link(None);
+
             // Timeout for no working links.
             let no_link_since = self.links_not_working_since();
             let no_link_timeout = self.cfg.no_link_timeout;
@@ -567,14 +684,12 @@ where
             };
 
             // Task for receiving requests from sender.
-            let sendable_idle_link_id =
-                self.idle_links.iter().rev().cloned().find(|id| self.links[*id].as_ref().unwrap().is_sendable());
             let write_rx_task = async {
                 if links_idling && is_consume_ack_required {
                     TaskEvent::SendConsumed
                 } else {
                     match &mut self.write_rx {
-                        Some(write_rx) if tx_seq_avail && !resending => {
+                        Some(write_rx) if tx_seq_avail && !resending && !resuming => {
                             match write_rx
                                 .recv_if(|msg| match msg {
                                     SendReq::Send(data) => {
@@ -597,6 +712,17 @@ where
                 }
             };
 
+            // Task for receiving datagrams to send from user.
+            let datagram_task = async {
+                match sendable_idle_link_id {
+                    Some(id) => match self.datagram_rx.recv().await {
+                        Some(datagram) => TaskEvent::DatagramTx { id, datagram },
+                        None => future::pending().await,
+                    },
+                    None => future::pending().await,
+                }
+            };
+
             // Task for receiving link events.
             let link_task = async {
                 if self.links.is_empty() {
@@ -662,6 +788,7 @@ where
                 new_link_event = new_link_task => new_link_event,
                 ((id, event), _, _) = link_task => TaskEvent::LinkEvent { id, event },
                 write_event = write_rx_task => write_event,
+                datagram_event = datagram_task => datagram_event,
                 link_id = recv_confirm_timeout => TaskEvent::ConfirmTimedOut(link_id),
                 link_id = next_ping_timeout => TaskEvent::PingLink(link_id),
                 link_id = next_pong_timeout => TaskEvent::LinkPingTimeout(link_id),
@@ -676,6 +803,7 @@ where
                 Some(()) = self.refused_links_tasks.next(), if !self.refused_links_tasks.is_empty()
                     => TaskEvent::RefusedLinkTask,
                 Some(()) = self.server_changed_rx.recv() => TaskEvent::ServerChanged,
+                Some(ticket_tx) = self.suspend_rx.recv() => TaskEvent::Suspend(ticket_tx),
             };
 
             // Handle event.
@@ -691,14 +819,52 @@ where
                 }
                 TaskEvent::NewLink(mut link) => {
                     let link_id = link.link_id();
+                    let server_changed = match (self.remote_server_id, link.remote_server_id()) {
+                        (Some(current), Some(server_id)) => server_id != current,
+                        _ => false,
+                    };
+                    if server_changed
+                        && (self.direction != Direction::Outgoing
+                            || !self.is_resumable()
+                            || link.remote_server_id().is_some_and(|id| self.previous_server_ids.contains(&id)))
+                    {
+                        tracing::debug!(?link_id, "link connects to another server");
+                        link.notify_disconnected(DisconnectReason::ServerIdMismatch);
+                        continue;
+                    }
                     if self.remote_cfg.is_none() {
                         let remote_cfg = link.remote_cfg();
                         tracing::debug!(?remote_cfg, "obtained remote configuration");
                         self.remote_cfg = Some(remote_cfg);
                     }
+                    if self.extensions.is_none() {
+                        self.extensions = Some(link.extensions());
+                    }
+                    if self.remote_server_id.is_none() {
+                        self.remote_server_id = link.remote_server_id();
+                    }
+                    if self.cipher.is_none()
+                        && self.extensions.is_some_and(|ext| ext.contains(Extensions::ENCRYPTION))
+                    {
+                        let Some(conn_secret) = &link.conn_secret else {
+                            // Keys are derived from the link that established the connection.
+                            tracing::debug!(?link_id, "deferring link until encryption is set up");
+                            self.links_awaiting_cipher.push(*link);
+                            continue;
+                        };
+                        tracing::debug!("using end-to-end encryption");
+                        self.cipher = Some(Cipher::new(conn_secret, self.cfg.psk.as_ref(), self.direction));
+                        self.init_links.extend(self.links_awaiting_cipher.drain(..));
+                    }
+                    if self.fec_encoder.is_none() {
+                        self.fec_encoder = self.new_fec_encoder();
+                    }
                     let others =
                         self.links.iter().filter_map(|link_opt| link_opt.as_ref().map(Link::from)).collect();
                     if (self.link_filter)(Link::from(&*link), others).await {
+                        if server_changed {
+                            self.resume(&link);
+                        }
                         self.add_link(*link);
                         tracing::info!(?link_id, "added new link");
                     } else {
@@ -732,17 +898,40 @@ where
                     match event {
                         LinkIntEvent::TxReady => {
                             // Link is ready to send more data.
+                            let fec_pending = self
+                                .fec_encoder
+                                .as_ref()
+                                .is_some_and(|fec_encoder| fec_encoder.has_parity() || fec_encoder.has_group());
+                            let scheduled = (resending
+                                || self.write_rx.is_some()
+                                || !self.datagram_rx.is_empty()
+                                || fec_pending)
+                                && self.schedule_link(Some(id)) == Some(id);
                             let link = self.links[id].as_mut().unwrap();
                             let link_blocked = link.blocked.load(Ordering::SeqCst);
                             if link.needs_tx_accepted {
                                 tracing::debug!(?link_id, "sending Accepted over link");
                                 self.idle_links.retain(|&idle_id| idle_id != id);
-                                link.start_send_msg(LinkMsg::Accepted, None);
+                                let msg = match &link.conn_secret {
+                                    Some(_) if link.extensions().contains(Extensions::ENCRYPTION) => {
+                                        LinkMsg::Established
+                                    }
+                                    _ => LinkMsg::Accepted,
+                                };
+                                link.start_send_msg(msg, None);
                                 link.needs_tx_accepted = false;
                             } else if link.send_pong {
                                 tracing::trace!(?link_id, "sending Pong over link");
                                 self.idle_links.retain(|&idle_id| idle_id != id);
-                                link.start_send_msg(LinkMsg::Pong, None);
+                                let msg = match link.pong_timestamps.take() {
+                                    Some((ping_sent, ping_received)) => LinkMsg::TimestampedPong {
+                                        ping_sent,
+                                        ping_received,
+                                        sent: timestamp(self.start_time, Instant::now()),
+                                    },
+                                    None => LinkMsg::Pong,
+                                };
+                                link.start_send_msg(msg, None);
                                 link.send_pong = false;
                             } else if let Some(initiator) = link.disconnecting {
                                 if !link.goodbye_sent {
@@ -759,8 +948,14 @@ where
                             } else if link.send_ping {
                                 tracing::trace!(?link_id, "sending Ping over link");
                                 self.idle_links.retain(|&idle_id| idle_id != id);
-                                link.start_send_msg(LinkMsg::Ping, None);
-                                link.current_ping_sent = Some(Instant::now());
+                                let now = Instant::now();
+                                let msg = if link.extensions().contains(Extensions::TIMESTAMPS) {
+                                    LinkMsg::TimestampedPing { sent: timestamp(self.start_time, now) }
+                                } else {
+                                    LinkMsg::Ping
+                                };
+                                link.start_send_msg(msg, None);
+                                link.current_ping_sent = Some(now);
                                 link.send_ping = false;
                             } else if link_blocked != link.blocked_sent {
                                 tracing::debug!(?link_id, %link_blocked, "local block status of link changed");
@@ -784,7 +979,7 @@ where
                                     self.send_reliable_over_link(id, ReliableMsg::Consumed(consumed));
                                     self.rxed_reliable_consumed_since_last_ack = 0;
                                     self.rxed_reliable_consumed_force_ack = false;
-                                } else if resending && link.is_sendable() {
+                                } else if resending && scheduled {
                                     let packet = self.resend_queue.pop_front().unwrap();
                                     tracing::trace!(
                                         ?link_id,
@@ -793,6 +988,9 @@ where
                                     );
                                     self.idle_links.retain(|idle_id| *idle_id != id);
                                     self.resend_reliable_over_link(id, packet);
+                                } else if !link.tx_redundant_queue.is_empty() && link.is_sendable() {
+                                    self.idle_links.retain(|&idle_id| idle_id != id);
+                                    self.send_redundant_over_link(id);
                                 } else if self.read_closed_rx.is_none() && !self.receive_close_sent {
                                     tracing::trace!(?link_id, "sending ReceiveClose over non-idle link");
                                     self.idle_links.retain(|&idle_id| idle_id != id);
@@ -803,15 +1001,26 @@ where
                                     self.idle_links.retain(|&idle_id| idle_id != id);
                                     self.send_reliable_over_link(id, ReliableMsg::ReceiveFinish);
                                     self.receive_finish_sent = true;
-                                } else if self.write_rx.is_none() && !self.send_finish_sent {
+                                } else if self.write_rx.is_none() && !self.send_finish_sent && !resuming {
                                     tracing::trace!(?link_id, "sending SendFinish over non-idle link");
                                     self.idle_links.retain(|&idle_id| idle_id != id);
                                     self.send_reliable_over_link(id, ReliableMsg::SendFinish);
                                     self.send_finish_sent = true;
+                                } else if let Some(datagram) = scheduled
+                                    .then(|| Self::pop_datagram(self.extensions, &mut self.datagram_rx))
+                                    .flatten()
+                                {
+                                    self.idle_links.retain(|&idle_id| idle_id != id);
+                                    self.send_datagram_over_link(id, datagram);
+                                } else if scheduled
+                                    && self.fec_encoder.as_ref().is_some_and(FecEncoder::has_parity)
+                                {
+                                    self.idle_links.retain(|&idle_id| idle_id != id);
+                                    self.send_parity_over_link(id);
                                 } else if let Some(SendReq::Send(data)) = self
                                     .write_rx
                                     .as_mut()
-                                    .filter(|_| tx_seq_avail && link.is_sendable())
+                                    .filter(|_| tx_seq_avail && scheduled && !resuming)
                                     .and_then(|rx| {
                                         rx.try_recv_if(
                                             |msg| matches!(msg, SendReq::Send(data) if data.len() <= tx_space),
@@ -825,7 +1034,16 @@ where
                                         data.len()
                                     );
                                     self.idle_links.retain(|idle_id| *idle_id != id);
-                                    self.send_reliable_over_link(id, ReliableMsg::Data(data));
+                                    let msg = self.data_msg(data);
+                                    self.send_reliable_over_link(id, msg);
+                                } else if scheduled
+                                    && self.fec_encoder.as_ref().is_some_and(FecEncoder::has_group)
+                                    && self.write_rx.as_mut().map_or(true, |rx| rx.try_peek().is_err())
+                                {
+                                    // Protect incomplete group, since no more data is available for now.
+                                    self.idle_links.retain(|&idle_id| idle_id != id);
+                                    self.fec_encoder.as_mut().unwrap().finish();
+                                    self.send_parity_over_link(id);
                                 } else if link.need_ack_flush() {
                                     tracing::trace!(
                                         ?link_id,
@@ -903,6 +1121,11 @@ where
                             link.report_ready();
                             link.blocked_changed_out_tx.send_replace(());
                         }
+                        LinkIntEvent::PriorityChanged => {
+                            // Link priority has changed, which is considered by the next scheduling.
+                            let priority = self.links[id].as_ref().unwrap().priority();
+                            tracing::debug!(?link_id, %priority, "link priority changed");
+                        }
                         LinkIntEvent::Disconnect => {
                             // Local request to disconnect link.
                             let link = self.links[id].as_mut().unwrap();
@@ -919,7 +1142,18 @@ where
                     let link_id = self.links[id].as_ref().unwrap().link_id();
                     tracing::trace!(?link_id, "sending data of size {} bytes over idle link", data.len());
                     self.idle_links.retain(|&idle_id| idle_id != id);
-                    self.send_reliable_over_link(id, ReliableMsg::Data(data));
+                    let msg = self.data_msg(data);
+                    self.send_reliable_over_link(id, msg);
+                }
+                TaskEvent::DatagramTx { id, datagram } => {
+                    if !self.extensions.is_some_and(|ext| ext.contains(Extensions::DATAGRAM)) {
+                        tracing::debug!("discarding datagram since remote endpoint does not support datagrams");
+                    } else if datagram.is_expired() {
+                        tracing::trace!("discarding expired datagram");
+                    } else {
+                        self.idle_links.retain(|&idle_id| idle_id != id);
+                        self.send_datagram_over_link(id, datagram);
+                    }
                 }
                 TaskEvent::SendConsumed => {
                     let id = self.idle_links.pop().unwrap();
@@ -960,7 +1194,7 @@ where
                         })
                         .collect();
                     self.idle_links.retain(|idle_id| !self.unflushed_links.contains(idle_id));
-                    self.flushed_tx = Some(tx);
+                    self.flushed_tx.push(tx);
                 }
                 TaskEvent::ConfirmTimedOut(id) => {
                     tracing::debug!("acknowledgement timeout on link {id}");
@@ -998,8 +1232,8 @@ where
                     tracing::trace!("consuming received data message {:?}", &received.msg);
                     match received.msg {
                         ReliableMsg::Data(data) => {
-                            self.rxed_reliable_size -= data.len();
-                            self.rxed_reliable_consumed_since_last_ack += data.len();
+                            self.rxed_reliable_size -= received.size;
+                            self.rxed_reliable_consumed_since_last_ack += received.size;
                             if let Some(permit) = permit {
                                 permit.send(data);
                             }
@@ -1011,7 +1245,11 @@ where
                             self.rxed_reliable_consumed_force_ack = true;
                         }
                         // Handled in handle_received_reliable_msg.
-                        ReliableMsg::ReceiveClose | ReliableMsg::ReceiveFinish | ReliableMsg::Consumed(_) => {
+                        ReliableMsg::CompressedData(_)
+                        | ReliableMsg::ReceiveClose
+                        | ReliableMsg::ReceiveFinish
+                        | ReliableMsg::Consumed(_)
+                        | ReliableMsg::Resume(_) => {
                             unreachable!()
                         }
                     }
@@ -1062,6 +1300,26 @@ where
                     link_term = DisconnectReason::ServerIdMismatch;
                     break;
                 }
+                TaskEvent::Suspend(ticket_tx) => {
+                    if self.direction != Direction::Incoming || !self.is_resumable() || resuming {
+                        let _ = ticket_tx.send(Err(SuspendError::NotResumable));
+                        continue;
+                    }
+                    let ticket = match self.resumption_ticket() {
+                        Ok(ticket) => ticket,
+                        Err(err) => {
+                            let _ = ticket_tx.send(Err(err));
+                            continue;
+                        }
+                    };
+                    tracing::info!("suspending connection for resumption on another server");
+                    let _ = ticket_tx.send(Ok(ticket));
+                    result = Err(TaskError::Suspended);
+                    read_term = Some(RecvError::TaskTerminated);
+                    write_term = SendError::TaskTerminated;
+                    link_term = DisconnectReason::ConnectionClosed;
+                    break;
+                }
             }
 
             // Check for link ping exceeding configured limit.
@@ -1175,6 +1433,203 @@ where
         self.publish_links();
     }
 
+    /// Creates the forward error correction encoder, if enabled and supported by the remote endpoint.
+    fn new_fec_encoder(&self) -> Option<FecEncoder> {
+        match (self.cfg.fec_group_size, self.extensions) {
+            (Some(group_size), Some(extensions)) if extensions.contains(Extensions::FEC) => {
+                Some(FecEncoder::new(group_size))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns whether the session can be resumed on another server.
+    ///
+    /// Sent data is then kept until it has been consumed by the remote endpoint.
+    /// Resumption is not possible with encryption, since the encryption keys
+    /// are bound to the connection.
+    fn is_resumable(&self) -> bool {
+        self.cfg.session_resumption && self.cipher.is_none()
+    }
+
+    /// Resumes the session on a new server, which the specified link is connected to.
+    ///
+    /// All links to the previous server are removed and the new server is told how many
+    /// data messages have been received.
+    /// Sent data that has not been consumed by the previous server is kept until the new
+    /// server reports how many data messages it has received, see [`Self::handle_resume`].
+    /// Sequence numbers start from zero, since the new server sees a new connection.
+    fn resume(&mut self, link: &LinkInt<TX, RX, TAG>) {
+        tracing::info!(
+            previous_server_id =? self.remote_server_id,
+            server_id =? link.remote_server_id(),
+            "resuming session on new server"
+        );
+
+        // Remove links to previous server.
+        while let Some(id) = self.links.iter().position(Option::is_some) {
+            self.remove_link(id, DisconnectReason::ServerIdMismatch);
+        }
+
+        // Collect data that has not been consumed by previous server in order.
+        let mut msgs: VecDeque<_> = self.txed_unconsumed_msgs.drain(..).collect();
+        for packet in self.txed_packets.drain(..) {
+            let msg = match &*packet.status.borrow() {
+                SentReliableStatus::Sent { msg, .. } | SentReliableStatus::ResendQueued { msg } => {
+                    Some(msg.clone())
+                }
+                SentReliableStatus::Received { msg, .. } => msg.clone(),
+            };
+            if let Some(msg @ (ReliableMsg::Data(_) | ReliableMsg::CompressedData(_))) = msg {
+                msgs.push_back(msg);
+            }
+        }
+
+        // Reset sender and receiver state.
+        // Received data that is ready for consumption is kept.
+        self.resend_queue.clear();
+        self.tx_seq = Seq::ZERO;
+        self.txed_unacked = 0;
+        self.txed_unconsumed = 0;
+        self.txed_unconsumable = 0;
+        self.txed_last_consumed = Seq::MINUS_ONE;
+        self.txed_consumed_unprocessed = 0;
+        self.rx_seq = Seq::ZERO;
+        self.rxed_reliable.clear();
+        for received in &mut self.rxed_reliable_consumable {
+            received.size = 0;
+        }
+        self.rxed_reliable_size = 0;
+        self.rxed_reliable_consumed_since_last_ack = 0;
+        self.rxed_reliable_consumed_force_ack = false;
+
+        // Switch to new server.
+        self.previous_server_ids.extend(self.remote_server_id);
+        self.remote_server_id = link.remote_server_id();
+        self.remote_cfg = Some(link.remote_cfg());
+        self.extensions = Some(link.extensions());
+        self.fec_encoder = self.new_fec_encoder();
+        self.fec_decoder = FecDecoder::default();
+
+        // Report received data, followed by closing of receiving direction.
+        self.resume_msgs = Some(msgs);
+        self.queue_reliable_msg(ReliableMsg::Resume(self.rxed_data_count));
+        if self.receive_close_sent {
+            self.queue_reliable_msg(ReliableMsg::ReceiveClose);
+        }
+        if self.receive_finish_sent {
+            self.queue_reliable_msg(ReliableMsg::ReceiveFinish);
+        }
+    }
+
+    /// Creates a ticket for resuming the session on another server.
+    fn resumption_ticket(&self) -> Result<ResumptionTicket, SuspendError> {
+        let mut unconsumed = Vec::new();
+        let packet_msgs = self.txed_packets.iter().filter_map(|packet| match &*packet.status.borrow() {
+            SentReliableStatus::Sent { msg, .. } | SentReliableStatus::ResendQueued { msg } => Some(msg.clone()),
+            SentReliableStatus::Received { msg, .. } => msg.clone(),
+        });
+        for msg in self.txed_unconsumed_msgs.iter().cloned().chain(packet_msgs) {
+            match msg {
+                ReliableMsg::Data(data) => unconsumed.push(data),
+                ReliableMsg::CompressedData(data) => match lz4_flex::decompress_size_prepended(&data) {
+                    Ok(data) => unconsumed.push(data.into()),
+                    Err(err) => {
+                        tracing::warn!(%err, "cannot decompress sent data for resumption ticket");
+                        return Err(SuspendError::NotResumable);
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        // Data that is ready for consumption but has not been passed to the receiver is lost.
+        let unpassed =
+            self.rxed_reliable_consumable.iter().filter(|received| matches!(received.msg, ReliableMsg::Data(_)));
+
+        Ok(ResumptionTicket {
+            conn_id: self.conn_id.get(),
+            received: self.rxed_data_count - unpassed.count() as u64,
+            sent_consumed: self.txed_consumed_count,
+            unconsumed,
+            send_finished: self.send_finish_sent,
+        })
+    }
+
+    /// Continues a suspended session using the specified resumption ticket.
+    pub(crate) fn resume_from_ticket(&mut self, ticket: ResumptionTicket) {
+        tracing::info!(?ticket, "resuming suspended session");
+
+        self.rxed_data_count = ticket.received;
+        self.txed_consumed_count = ticket.sent_consumed;
+        self.resume_msgs = Some(ticket.unconsumed.into_iter().map(ReliableMsg::Data).collect());
+        if ticket.send_finished {
+            self.write_rx = None;
+            self.send_finish_sent = true;
+        }
+
+        self.queue_reliable_msg(ReliableMsg::Resume(ticket.received));
+    }
+
+    /// Handles the report of the remote endpoint how many data messages it has received
+    /// when resuming a session.
+    ///
+    /// Only kept data messages that have not been received by the remote endpoint are sent.
+    /// Resumption fails if data that the remote endpoint has not received is no longer available.
+    /// A server that has not been given a resumption ticket refuses the resuming link instead.
+    fn handle_resume(&mut self, received: u64) -> Result<(), io::Error> {
+        let Some(mut msgs) = self.resume_msgs.take() else {
+            return Err(protocol_err!("remote endpoint resumes unknown session"));
+        };
+
+        let available = self.txed_consumed_count..=self.txed_consumed_count + msgs.len() as u64;
+        if !available.contains(&received) {
+            return Err(protocol_err!(
+                "cannot resume session after {received} received messages, since only messages {} to {} \
+                 are available",
+                available.start(),
+                available.end()
+            ));
+        }
+        msgs.drain(..(received - self.txed_consumed_count) as usize);
+        self.txed_consumed_count = received;
+        tracing::debug!("resending {} messages not received by remote endpoint", msgs.len());
+
+        let compression = self.extensions.is_some_and(|ext| ext.contains(Extensions::COMPRESSION));
+        for msg in msgs {
+            let msg = match msg {
+                ReliableMsg::CompressedData(data) if !compression => {
+                    let data = lz4_flex::decompress_size_prepended(&data)
+                        .map_err(|_| protocol_err!("invalid compressed data"))?;
+                    ReliableMsg::Data(data.into())
+                }
+                msg => msg,
+            };
+            self.queue_reliable_msg(msg);
+        }
+
+        // Finish sending direction after data.
+        if self.send_finish_sent {
+            self.queue_reliable_msg(ReliableMsg::SendFinish);
+        }
+
+        Ok(())
+    }
+
+    /// Queues a reliable message for sending over the next available link.
+    fn queue_reliable_msg(&mut self, msg: ReliableMsg) {
+        let size = msg.size();
+        self.txed_unacked += size;
+        self.txed_unconsumed += size;
+
+        let packet = Arc::new(SentReliable {
+            seq: self.next_tx_seq(),
+            status: AtomicRefCell::new(SentReliableStatus::ResendQueued { msg }),
+        });
+        self.txed_packets.push_back(packet.clone());
+        self.resend_queue.push_back(packet);
+    }
+
     /// Publishes the currently connected links.
     fn publish_links(&self) {
         let links = self.links.iter().filter_map(|link_opt| link_opt.as_ref().map(Link::from)).collect();
@@ -1206,7 +1661,8 @@ where
     fn tx_space(&self) -> usize {
         let tx_local_space = (self.cfg.send_buffer.get() as usize).saturating_sub(self.txed_unacked);
         let tx_remote_space = self.remote_recv_buffer().unwrap_or_default().saturating_sub(self.txed_unconsumed);
-        tx_local_space.min(tx_remote_space)
+        let overhead = if self.cipher.is_some() { Cipher::TAG_SIZE } else { 0 };
+        tx_local_space.min(tx_remote_space).saturating_sub(overhead)
     }
 
     /// Returns whether a sequence number is available for sending.
@@ -1214,13 +1670,137 @@ where
         self.txed_packets.front().map(|p| self.tx_seq - p.seq <= Seq::USABLE_INTERVAL).unwrap_or(true)
     }
 
+    /// Selects the link for sending the next data packet using the link scheduler.
+    ///
+    /// Idle links that are sendable and have the highest priority of all working links
+    /// are ready for data.
+    /// If `ready_id` is specified, that link is treated as having become ready just now.
+    fn schedule_link(&mut self, ready_id: Option<usize>) -> Option<usize> {
+        let is_working = |link: &LinkInt<TX, RX, TAG>| link.unconfirmed.is_none() && !link.is_blocked();
+        let priority =
+            self.links.iter().flatten().filter(|link| is_working(link)).map(|link| link.priority()).min();
+
+        // Mark links that are ready for data.
+        self.scheduler_ready.clear();
+        self.scheduler_ready.resize(self.links.len(), false);
+        let mut any_ready = false;
+        for id in self.idle_links.iter().cloned().chain(ready_id) {
+            let link = self.links[id].as_ref().unwrap();
+            if link.is_sendable() && is_working(link) && Some(link.priority()) == priority {
+                self.scheduler_ready[id] = true;
+                any_ready = true;
+            }
+        }
+        if !any_ready {
+            return None;
+        }
+
+        // Busy links come first, followed by ready links in the order they became ready.
+        let ready = &self.scheduler_ready;
+        self.scheduler_ids.clear();
+        self.scheduler_ids.extend((0..self.links.len()).filter(|&id| self.links[id].is_some() && !ready[id]));
+        self.scheduler_ids.extend(
+            self.idle_links
+                .iter()
+                .cloned()
+                .filter(|&id| Some(id) != ready_id)
+                .chain(ready_id)
+                .filter(|&id| ready[id]),
+        );
+
+        // The link states borrow the links, thus only their allocation is kept between calls.
+        let mut states: Vec<LinkState<TAG>> =
+            mem::take(&mut self.scheduler_states).into_iter().map(|_| unreachable!()).collect();
+        states.extend(self.scheduler_ids.iter().map(|&id| {
+            let link = self.links[id].as_ref().unwrap();
+            LinkState {
+                id: link.link_id(),
+                tag: link.tag(),
+                ready: ready[id],
+                working: is_working(link),
+                priority: link.priority(),
+                roundtrip: link.roundtrip,
+                forward_delay: link.estimated_forward_delay.unwrap_or(link.roundtrip / 2),
+                unacked: link.txed_unacked_data,
+                unacked_limit: link.txed_unacked_data_limit,
+                total_sent: link.total_sent(),
+            }
+        }));
+
+        let selected = self
+            .link_scheduler
+            .select(&states)
+            .filter(|&idx| states.get(idx).is_some_and(|state| state.ready))
+            .map(|idx| self.scheduler_ids[idx]);
+
+        states.clear();
+        self.scheduler_states = states.into_iter().map(|_| unreachable!()).collect();
+
+        selected
+    }
+
+    /// Updates the one-way delays of all links from their timestamped ping samples.
+    ///
+    /// Since the clock offset between both endpoints is the same for all links,
+    /// delays of different links are comparable.
+    fn update_one_way_delays(&mut self) {
+        let links = || self.links.iter().flatten();
+        let forward_base = links().filter_map(|link| link.one_way_delays.forward.base()).min();
+        let backward_base = links().filter_map(|link| link.one_way_delays.backward.base()).min();
+
+        // Estimate clock offset from the link with the shortest roundtrip time, assuming
+        // it has the same delay in both directions, since this bounds the estimation error.
+        let clock_offset = links()
+            .filter_map(|link| {
+                let delays = &link.one_way_delays;
+                Some((delays.roundtrip()?, delays.symmetric_clock_offset()?))
+            })
+            .min_by_key(|&(roundtrip, _)| roundtrip)
+            .map(|(_, clock_offset)| clock_offset);
+
+        for link in self.links.iter_mut().flatten() {
+            let delays = &link.one_way_delays;
+            link.forward_delay = forward_base.and_then(|base| delays.forward.relative_to(base));
+            link.backward_delay = backward_base.and_then(|base| delays.backward.relative_to(base));
+            link.estimated_forward_delay = clock_offset.and_then(|offset| delays.estimated_forward(offset));
+        }
+    }
+
+    /// Couples the increase of the unacknowledged data limits of links sharing a bottleneck.
+    fn update_coupling(&mut self) {
+        let Some(coupling) = &mut self.coupling else { return };
+        if !coupling.is_update_due() {
+            return;
+        }
+
+        let link_ids: Vec<_> = self.links.iter().flatten().map(|link| link.link_id()).collect();
+        coupling.detect(&link_ids);
+
+        for link in self.links.iter_mut().flatten() {
+            link.coupled_increase = 1.0;
+            link.shared_bottleneck = false;
+        }
+
+        for group in coupling.groups() {
+            let mut links: Vec<_> =
+                self.links.iter_mut().flatten().filter(|link| group.contains(&link.link_id())).collect();
+            let limits: Vec<_> =
+                links.iter().map(|link| (link.txed_unacked_data_limit, link.roundtrip)).collect();
+            for (link, factor) in links.iter_mut().zip(Coupling::increase_factors(&limits)) {
+                link.coupled_increase = factor;
+                link.shared_bottleneck = true;
+            }
+        }
+    }
+
     /// Adjusts the link transmission buffer limits to ensure that no link stalls the channel.
+    ///
+    /// Links using [delay-based congestion control](crate::cfg::CongestionControl::Ledbat) are only
+    /// subject to send overrun handling, since their limits are adjusted when acknowledgements
+    /// are received.
     fn adjust_link_tx_limits(&mut self) {
         let Some(remote_recv_buffer) = self.remote_recv_buffer() else { return };
-        let coming_seq = match self.resend_queue.front() {
-            Some(packet) => packet.seq,
-            None => self.tx_seq,
-        };
+        let coming_seq = self.resend_queue.iter().map(|packet| packet.seq).min().unwrap_or(self.tx_seq);
 
         // Check for unconsumable data approaching its limits.
         let unconsumable_limit = (self.cfg.send_buffer.get() as usize).min(remote_recv_buffer);
@@ -1295,6 +1875,7 @@ where
                         match link_opt {
                             Some(link)
                                 if link.unconfirmed.is_none()
+                                    && link.delay_controller.is_none()
                                     && link.txed_unacked_data_limit_increased.is_none()
                                     && link.roundtrip > max_ping * 3 / 4 =>
                             {
@@ -1343,6 +1924,7 @@ where
                         if !link.tx_pending
                             && link.unconfirmed.is_none()
                             && !link.is_blocked()
+                            && link.delay_controller.is_none()
                             && link.txed_unacked_data >= link.txed_unacked_data_limit
                             && link.txed_unacked_data_limit_increased.is_none()
                             && link.txed_unacked_data_limit < self.cfg.link_unacked_limit.get()
@@ -1353,19 +1935,22 @@ where
                                 .unwrap_or(true) =>
                     {
                         // Increase limit, faster if done many times consecutively.
+                        let increased = if link.txed_unacked_data_limit_increased_consecutively >= 100 {
+                            link.txed_unacked_data_limit * 120 / 100
+                        } else if link.txed_unacked_data_limit_increased_consecutively >= 50 {
+                            link.txed_unacked_data_limit * 110 / 100
+                        } else if link.txed_unacked_data_limit_increased_consecutively >= 25 {
+                            link.txed_unacked_data_limit * 105 / 100
+                        } else if link.txed_unacked_data_limit_increased_consecutively >= 10 {
+                            link.txed_unacked_data_limit * 102 / 100
+                        } else {
+                            link.txed_unacked_data_limit * 101 / 100
+                        };
+
+                        // Scale increase when coupled with links sharing a bottleneck.
+                        let increase = (increased - link.txed_unacked_data_limit) as f64 * link.coupled_increase;
                         link.txed_unacked_data_limit =
-                            if link.txed_unacked_data_limit_increased_consecutively >= 100 {
-                                link.txed_unacked_data_limit * 120 / 100
-                            } else if link.txed_unacked_data_limit_increased_consecutively >= 50 {
-                                link.txed_unacked_data_limit * 110 / 100
-                            } else if link.txed_unacked_data_limit_increased_consecutively >= 25 {
-                                link.txed_unacked_data_limit * 105 / 100
-                            } else if link.txed_unacked_data_limit_increased_consecutively >= 10 {
-                                link.txed_unacked_data_limit * 102 / 100
-                            } else {
-                                link.txed_unacked_data_limit * 101 / 100
-                            }
-                            .max(100);
+                            (link.txed_unacked_data_limit + increase as usize).max(100);
 
                         tracing::trace!(link_id =? link.link_id(),
                             "increasing unacked limit of link to {} bytes (done {} times without overrun)",
@@ -1460,6 +2045,68 @@ where
             .min_by_key(|(_id, next_ping)| *next_ping)
     }
 
+    /// Creates a data message, compressing the data if enabled and beneficial
+    /// and encrypting it if enabled.
+    ///
+    /// Data messages must be sent in the order they are created.
+    fn data_msg(&mut self, mut data: Bytes) -> ReliableMsg {
+        /// Minimum size of data for compression to be attempted.
+        const MIN_COMPRESS_SIZE: usize = 64;
+
+        let mut compressed = false;
+        if self.cfg.compression
+            && data.len() >= MIN_COMPRESS_SIZE
+            && self.extensions.is_some_and(|ext| ext.contains(Extensions::COMPRESSION))
+        {
+            let compressed_data = lz4_flex::compress_prepend_size(&data);
+            if compressed_data.len() < data.len() {
+                data = compressed_data.into();
+                compressed = true;
+            }
+        }
+
+        if let Some(cipher) = &mut self.cipher {
+            data = cipher.encrypt(&data);
+        }
+
+        if compressed {
+            ReliableMsg::CompressedData(data)
+        } else {
+            ReliableMsg::Data(data)
+        }
+    }
+
+    /// Decrypts and decompresses the data of a received data message.
+    ///
+    /// Data messages must be processed in the order they were sent.
+    fn decode_data(&mut self, mut data: Bytes, compressed: bool) -> Result<Bytes, io::Error> {
+        if let Some(cipher) = &mut self.cipher {
+            data = cipher.decrypt(&data)?;
+        }
+
+        if compressed {
+            data = self.decompress(&data)?;
+        }
+
+        Ok(data)
+    }
+
+    /// Decompresses received compressed data.
+    fn decompress(&self, data: &[u8]) -> Result<Bytes, io::Error> {
+        let (size, compressed) = lz4_flex::block::uncompressed_size(data)
+            .map_err(|err| protocol_err!("invalid compressed data: {err}"))?;
+        if size > self.cfg.recv_buffer.get() as usize {
+            return Err(protocol_err!("compressed data exceeds receive buffer"));
+        }
+
+        let mut buf = vec![0; size];
+        match lz4_flex::block::decompress_into(compressed, &mut buf) {
+            Ok(n) if n == size => Ok(buf.into()),
+            Ok(_) => Err(protocol_err!("compressed data size mismatch")),
+            Err(err) => Err(protocol_err!("invalid compressed data: {err}")),
+        }
+    }
+
     /// Sends a sequenced reliable message over the specified link.
     fn send_reliable_over_link(&mut self, id: usize, reliable_msg: ReliableMsg) -> Seq {
         let seq = self.next_tx_seq();
@@ -1471,10 +2118,17 @@ where
         link.start_send_msg(msg, data);
 
         // Update statistics.
-        if let ReliableMsg::Data(data) = &reliable_msg {
-            self.txed_unacked += data.len();
-            self.txed_unconsumed += data.len();
-            link.txed_unacked_data += data.len();
+        let size = reliable_msg.size();
+        self.txed_unacked += size;
+        self.txed_unconsumed += size;
+        link.txed_unacked_data += size;
+
+        // Calculate parity for forward error correction and queue redundant copies of data.
+        if matches!(reliable_msg, ReliableMsg::Data(_) | ReliableMsg::CompressedData(_)) {
+            if let Some(fec_encoder) = &mut self.fec_encoder {
+                fec_encoder.add(seq, &reliable_msg);
+            }
+            self.queue_redundant(id, seq);
         }
 
         // Store sent message until confirmation to be able to resend it should the link fail.
@@ -1492,6 +2146,142 @@ where
         seq
     }
 
+    /// Queues redundant copies of a data message sent over the specified link
+    /// for sending over the fastest other working links.
+    fn queue_redundant(&mut self, id: usize, seq: Seq) {
+        let copies = self.cfg.redundancy.get() - 1;
+        if copies == 0 {
+            return;
+        }
+
+        let is_working = |link: &LinkInt<TX, RX, TAG>| link.unconfirmed.is_none() && !link.is_blocked();
+        let priority =
+            self.links.iter().flatten().filter(|link| is_working(link)).map(|link| link.priority()).min();
+        let mut others: Vec<_> = self
+            .links
+            .iter()
+            .enumerate()
+            .filter_map(|(other_id, link_opt)| {
+                let link = link_opt.as_ref()?;
+                (other_id != id && is_working(link) && Some(link.priority()) == priority)
+                    .then_some((other_id, link.roundtrip))
+            })
+            .collect();
+        others.sort_by_key(|&(_, roundtrip)| roundtrip);
+
+        for (other_id, _) in others.into_iter().take(copies) {
+            let link = self.links[other_id].as_mut().unwrap();
+            link.tx_redundant_queue.push_back(seq);
+
+            if self.idle_links.contains(&other_id) {
+                self.idle_links.retain(|&idle_id| idle_id != other_id);
+                link.report_ready();
+            }
+        }
+    }
+
+    /// Sends the next queued redundant copy of a data message over the specified link.
+    ///
+    /// Copies of messages that have been received by the remote endpoint or that
+    /// are not currently sent over another link are skipped.
+    fn send_redundant_over_link(&mut self, id: usize) {
+        let link = self.links[id].as_mut().unwrap();
+
+        while let Some(seq) = link.tx_redundant_queue.pop_front() {
+            let back_idx = (self.tx_seq - seq) as usize;
+            if back_idx == 0 || back_idx > self.txed_packets.len() {
+                continue;
+            }
+
+            let packet = &self.txed_packets[self.txed_packets.len() - back_idx];
+            assert_eq!(packet.seq, seq);
+
+            if let SentReliableStatus::Sent { link_id, msg, .. } = &*packet.status.borrow() {
+                if *link_id != id {
+                    tracing::trace!(link_id =? link.link_id(), "sending redundant copy of reliable message {seq} over link");
+                    let size = msg.size();
+                    let (msg, data) = msg.to_link_msg(seq);
+                    link.start_send_msg(msg, data);
+
+                    // The copy counts as unacknowledged data of this link until it is acked over it.
+                    link.txed_unacked_data += size;
+                    link.txed_redundant.push_back((seq, size));
+                    return;
+                }
+            }
+        }
+
+        // Nothing was sent, thus link is still ready.
+        link.report_ready();
+    }
+
+    /// Sends the next queued parity for forward error correction over the specified link.
+    ///
+    /// The parity counts as unacknowledged data of the link until all messages
+    /// of the protected group have been received by the remote endpoint.
+    fn send_parity_over_link(&mut self, id: usize) {
+        let Some(parity) = self.fec_encoder.as_mut().and_then(FecEncoder::pop) else { return };
+
+        let link = self.links[id].as_mut().unwrap();
+        tracing::trace!(link_id =? link.link_id(), "sending parity of {} messages starting at {}", parity.count, parity.first);
+
+        let size = parity.data.len();
+        link.txed_unacked_data += size;
+        link.txed_parities.push_back((parity.first + u32::from(parity.count), size));
+
+        link.start_send_msg(LinkMsg::Parity { first: parity.first, count: parity.count }, Some(parity.data));
+    }
+
+    /// Releases parities whose protected groups have been completely received by the
+    /// remote endpoint from the unacknowledged data of the links that sent them.
+    fn release_parities(&mut self) {
+        let received_until = self.txed_packets.front().map_or(self.tx_seq, |packet| packet.seq);
+        for link in self.links.iter_mut().flatten() {
+            while let Some(&(end, size)) = link.txed_parities.front() {
+                if end > received_until {
+                    break;
+                }
+                link.txed_unacked_data -= size;
+                link.txed_parities.pop_front();
+            }
+        }
+    }
+
+    /// Takes the next datagram for sending, discarding expired datagrams.
+    ///
+    /// Returns `None` if no datagram is queued or the remote endpoint does not support datagrams.
+    fn pop_datagram(
+        extensions: Option<Extensions>, datagram_rx: &mut mpsc::Receiver<Datagram>,
+    ) -> Option<Datagram> {
+        if !extensions.is_some_and(|ext| ext.contains(Extensions::DATAGRAM)) {
+            return None;
+        }
+
+        while let Ok(datagram) = datagram_rx.try_recv() {
+            if !datagram.is_expired() {
+                return Some(datagram);
+            }
+            tracing::trace!("discarding expired datagram");
+        }
+
+        None
+    }
+
+    /// Sends a datagram over the specified link.
+    fn send_datagram_over_link(&mut self, id: usize, datagram: Datagram) {
+        let number = self.datagram_number;
+        self.datagram_number += 1;
+
+        let mut data = datagram.data;
+        if let Some(cipher) = &self.cipher {
+            data = cipher.encrypt_datagram(number, &data);
+        }
+
+        let link = self.links[id].as_mut().unwrap();
+        tracing::trace!(link_id =? link.link_id(), "sending datagram {number} of size {} over link", data.len());
+        link.start_send_msg(LinkMsg::Datagram { number }, Some(data));
+    }
+
     /// Resends a packet over the specified link.
     fn resend_reliable_over_link(&mut self, id: usize, packet: Arc<SentReliable>) {
         let link = self.links[id].as_mut().unwrap();
@@ -1508,9 +2298,7 @@ where
         link.start_send_msg(msg, data);
 
         // Update link statistics.
-        if let ReliableMsg::Data(data) = reliable_msg {
-            link.txed_unacked_data += data.len();
-        }
+        link.txed_unacked_data += reliable_msg.size();
 
         // Adjust last buffer increase sequence number if necessary.
         match &mut link.txed_unacked_data_limit_increased {
@@ -1543,16 +2331,25 @@ where
         // Reset limits.
         link.reset();
 
+        // Redundant copies are not sent over a link that is not working.
+        link.tx_redundant_queue.clear();
+        for (_, size) in link.txed_redundant.drain(..) {
+            link.txed_unacked_data -= size;
+        }
+
+        // Parities sent over the link are lost.
+        for (_, size) in link.txed_parities.drain(..) {
+            link.txed_unacked_data -= size;
+        }
+
         // Mark packets as being resent and put them into resend queue.
         for p in &mut self.txed_packets {
             let mut status = p.status.borrow_mut();
             match &*status {
                 SentReliableStatus::Sent { link_id, msg, .. } if *link_id == id => {
                     // Update link statistics.
-                    if let ReliableMsg::Data(data) = &msg {
-                        let old_link = self.links[*link_id].as_mut().unwrap();
-                        old_link.txed_unacked_data -= data.len();
-                    }
+                    let old_link = self.links[*link_id].as_mut().unwrap();
+                    old_link.txed_unacked_data -= msg.size();
 
                     *status = SentReliableStatus::ResendQueued { msg: msg.clone() };
                     self.resend_queue.push_back(p.clone());
@@ -1561,7 +2358,9 @@ where
             };
         }
 
-        // Sort resend queue, so that oldest packets are resend first.
+        // Sort resend queue, so that oldest packets are resent first.
+        // Since the remote endpoint delivers data in order, resending a packet of higher priority
+        // before older packets would not let it be received any earlier.
         self.resend_queue.make_contiguous().sort_by_key(|packet| packet.seq);
 
         // Re-test other links that have failed testing.
@@ -1609,12 +2408,14 @@ where
                         && link.current_ping_sent.is_none()
                         && !link.has_outstanding_ack()
                     {
-                        let test_data_limit = if self.cfg.link_max_ping.is_some() {
-                            self.cfg.link_unacked_init.get()
-                        } else {
-                            self.cfg.link_unacked_limit.get().min(self.cfg.send_buffer.get() as usize)
-                        }
-                        .min(self.cfg.link_test_data_limit);
+                        // Avoid filling the bottleneck buffer when latency matters.
+                        let test_data_limit =
+                            if self.cfg.link_max_ping.is_some() || link.delay_controller.is_some() {
+                                self.cfg.link_unacked_init.get()
+                            } else {
+                                self.cfg.link_unacked_limit.get().min(self.cfg.send_buffer.get() as usize)
+                            }
+                            .min(self.cfg.link_test_data_limit);
                         let test_data = link.send_test_data(self.cfg.io_write_size.get(), test_data_limit);
                         link.send_ping = true;
                         link.test = LinkTest::InProgress;
@@ -1701,6 +2502,13 @@ where
                 link.send_pong = true;
                 self.flush_link(id);
             }
+            LinkMsg::TimestampedPing { sent } => {
+                // Respond with timestamped pong on same link.
+                tracing::trace!(?link_id, "timestamped ping received, requesting sending resposne");
+                link.pong_timestamps = Some((sent, timestamp(self.start_time, Instant::now())));
+                link.send_pong = true;
+                self.flush_link(id);
+            }
             LinkMsg::Pong => {
                 if let Some(current_ping_sent) = link.current_ping_sent.take() {
                     let elapsed = current_ping_sent.elapsed();
@@ -1710,18 +2518,75 @@ where
                     self.link_testing_step(id);
                 }
             }
+            LinkMsg::TimestampedPong { ping_sent, ping_received, sent } => {
+                if let Some(current_ping_sent) = link.current_ping_sent.take() {
+                    let now = Instant::now();
+                    let elapsed = now.duration_since(current_ping_sent);
+                    tracing::trace!(?link_id, "ping round-trip time is {} ms", elapsed.as_millis());
+                    link.roundtrip = elapsed;
+                    link.last_ping = Some(now);
+                    if ping_sent == timestamp(self.start_time, current_ping_sent) {
+                        link.one_way_delays.record(
+                            ping_sent,
+                            ping_received,
+                            sent,
+                            timestamp(self.start_time, now),
+                        );
+                        self.update_one_way_delays();
+                    }
+                    self.link_testing_step(id);
+                }
+            }
             msg @ (LinkMsg::Data { .. }
+            | LinkMsg::CompressedData { .. }
             | LinkMsg::Consumed { .. }
             | LinkMsg::SendFinish { .. }
             | LinkMsg::ReceiveClose { .. }
-            | LinkMsg::ReceiveFinish { .. }) => {
+            | LinkMsg::ReceiveFinish { .. }
+            | LinkMsg::Resume { .. }) => {
                 let (reliable_msg, seq) = ReliableMsg::from_link_msg(msg, data);
                 tracing::trace!(?link_id, "received reliable message {seq}: {reliable_msg:?}");
-                self.handle_received_reliable_msg(id, seq, reliable_msg)?;
+                self.handle_received_reliable_msg(Some(id), seq, reliable_msg)?;
+                self.recover_reliable_msgs()?;
+            }
+            LinkMsg::Parity { first, count } => {
+                tracing::trace!(?link_id, "received parity of {count} messages starting at {first}");
+                self.fec_decoder.add_parity(Parity { first, count, data: data.unwrap() })?;
+                self.recover_reliable_msgs()?;
+            }
+            LinkMsg::Datagram { number } => {
+                tracing::trace!(?link_id, "received datagram {number}");
+                let data = data.unwrap();
+                let data = match &mut self.cipher {
+                    Some(cipher) => cipher.decrypt_datagram(number, &data),
+                    None => Ok(data),
+                };
+
+                // Datagrams are unreliable, thus a bad datagram is dropped instead of failing the connection.
+                match data {
+                    Ok(data) if data.len() > self.cfg.datagram_max_size.get() => {
+                        tracing::debug!(
+                            ?link_id,
+                            "dropping datagram {number} of {} bytes exceeding maximum size",
+                            data.len()
+                        );
+                    }
+                    Ok(data) => {
+                        let _ = self.datagram_tx.send(data);
+                    }
+                    Err(err) => tracing::debug!(?link_id, %err, "dropping datagram {number}"),
+                }
             }
             LinkMsg::Ack { received } => {
                 tracing::trace!(?link_id, "link acked reception up to {received}");
                 self.handle_ack(id, received);
+
+                // Resume sending queued redundant copies once the link is sendable again.
+                let link = self.links[id].as_mut().unwrap();
+                if !link.tx_redundant_queue.is_empty() && link.is_sendable() && self.idle_links.contains(&id) {
+                    self.idle_links.retain(|&idle_id| idle_id != id);
+                    link.report_ready();
+                }
             }
             LinkMsg::TestData { size } => {
                 tracing::trace!(?link_id, "link received {size} bytes of test data");
@@ -1757,23 +2622,55 @@ where
                 tracing::trace!(?link_id, "link recevied forceful connection termination request");
                 return Ok(true);
             }
-            LinkMsg::Welcome { .. } | LinkMsg::Connect { .. } | LinkMsg::Accepted | LinkMsg::Refused { .. } => {
-                return Err(protocol_err!("received unexpected message"))
-            }
+            LinkMsg::Welcome { .. }
+            | LinkMsg::Connect { .. }
+            | LinkMsg::Authenticate { .. }
+            | LinkMsg::Accepted
+            | LinkMsg::Established
+            | LinkMsg::Refused { .. } => return Err(protocol_err!("received unexpected message")),
         }
 
         Ok(false)
     }
 
+    /// Rebuilds missing received reliable messages using forward error correction.
+    fn recover_reliable_msgs(&mut self) -> Result<(), io::Error> {
+        while !self.fec_decoder.is_empty() {
+            let (rx_seq, rxed_reliable) = (self.rx_seq, &self.rxed_reliable);
+            let received = |seq: Seq| match rxed_reliable.get((seq - rx_seq) as usize) {
+                Some(Some(received)) => Some(&received.msg),
+                _ => None,
+            };
+            let Some((seq, msg)) = self.fec_decoder.recover(rx_seq, received)? else { break };
+
+            tracing::debug!("recovered reliable message {seq} using parity: {msg:?}");
+            self.handle_received_reliable_msg(None, seq, msg)?;
+        }
+
+        Ok(())
+    }
+
     /// Handle received data.
-    fn handle_received_reliable_msg(&mut self, id: usize, seq: Seq, msg: ReliableMsg) -> Result<(), io::Error> {
-        let link = self.links[id].as_mut().unwrap();
-        let link_id = link.link_id();
+    ///
+    /// `id` is the index of the link the message was received over
+    /// or `None` if it was recovered using forward error correction.
+    /// Only messages received over a link are acknowledged.
+    fn handle_received_reliable_msg(
+        &mut self, id: Option<usize>, seq: Seq, msg: ReliableMsg,
+    ) -> Result<(), io::Error> {
+        let link_id = match id {
+            Some(id) => {
+                let link = self.links[id].as_mut().unwrap();
 
-        // Update link and queue sending of ack.
-        link.tx_ack_queue.push_back(seq);
-        self.idle_links.retain(|&idle_id| idle_id != id);
-        link.report_ready();
+                // Update link and queue sending of ack.
+                link.tx_ack_queue.push_back(seq);
+                self.idle_links.retain(|&idle_id| idle_id != id);
+                link.report_ready();
+
+                Some(link.link_id())
+            }
+            None => None,
+        };
 
         if seq < self.rx_seq {
             // The sequence number belongs to a packet that has already been
@@ -1789,9 +2686,10 @@ where
             if self.rxed_reliable[offset].is_none() {
                 tracing::trace!(?link_id, "received reliable message {}", seq);
 
+                let size = msg.size();
                 match &msg {
-                    ReliableMsg::Data(data) => {
-                        self.rxed_reliable_size += data.len();
+                    ReliableMsg::Data(_) | ReliableMsg::CompressedData(_) => {
+                        self.rxed_reliable_size += size;
                         if self.rxed_reliable_size > self.cfg.recv_buffer.get() as usize {
                             return Err(protocol_err!("receive buffer overflow"));
                         }
@@ -1799,12 +2697,23 @@ where
                     ReliableMsg::SendFinish => {
                         // Handled during consumption.
                     }
+                    ReliableMsg::Resume(received) => {
+                        tracing::debug!(
+                            ?link_id,
+                            "remote endpoint resumes session after receiving {received} messages"
+                        );
+                        self.handle_resume(*received)?;
+                    }
                     ReliableMsg::Consumed(consumed) => {
                         tracing::trace!(?link_id, "remote consumed {consumed} bytes");
                         match self.txed_unconsumed.checked_sub(*consumed as usize) {
                             Some(txed_unconsumed) => self.txed_unconsumed = txed_unconsumed,
                             None => return Err(protocol_err!("txed_unconsumed underflow")),
                         }
+                        if self.is_resumable() {
+                            self.txed_consumed_unprocessed += *consumed as usize;
+                            self.remove_consumed_msgs();
+                        }
                     }
                     ReliableMsg::ReceiveClose => {
                         self.write_error_tx.send_replace(SendError::Closed);
@@ -1819,7 +2728,7 @@ where
                     }
                 }
 
-                self.rxed_reliable[offset] = Some(ReceivedReliableMsg { seq, msg });
+                self.rxed_reliable[offset] = Some(ReceivedReliableMsg { seq, msg, size });
             } else {
                 // The sequence number belongs to a packet that has alredy been
                 // received. Thus the acknowledgement has been lost and must be resend.
@@ -1829,11 +2738,21 @@ where
 
         // Forward received messages that are ready for consumption.
         while let Some(Some(_)) = self.rxed_reliable.front().as_ref() {
-            let msg = self.rxed_reliable.pop_front().unwrap().unwrap();
+            let mut msg = self.rxed_reliable.pop_front().unwrap().unwrap();
 
             assert_eq!(msg.seq, self.rx_seq);
             self.rx_seq += 1;
+            self.fec_decoder.forwarded(msg.seq, &msg.msg);
 
+            msg.msg = match msg.msg {
+                ReliableMsg::Data(data) => ReliableMsg::Data(self.decode_data(data, false)?),
+                ReliableMsg::CompressedData(data) => ReliableMsg::Data(self.decode_data(data, true)?),
+                msg => msg,
+            };
+
+            if matches!(&msg.msg, ReliableMsg::Data(_)) {
+                self.rxed_data_count += 1;
+            }
             if matches!(&msg.msg, ReliableMsg::Data(_) | ReliableMsg::SendFinish) {
                 self.rxed_reliable_consumable.push_back(msg);
             }
@@ -1851,11 +2770,18 @@ where
 
     /// Handles a received acknowledgement.
     fn handle_ack(&mut self, id: usize, rxed_seq: Seq) {
+        let resumable = self.is_resumable();
         let link = self.links[id].as_mut().unwrap();
         let link_id = link.link_id();
 
         tracing::trace!(?link_id, "processing received ack for {rxed_seq} on link");
 
+        // Release redundant copy sent over this link.
+        if let Some(pos) = link.txed_redundant.iter().position(|&(seq, _)| seq == rxed_seq) {
+            let (_, size) = link.txed_redundant.remove(pos).unwrap();
+            link.txed_unacked_data -= size;
+        }
+
         // Possibly unblock send buffer increase.
         match link.txed_unacked_data_limit_increased {
             Some(last_increased) if last_increased <= rxed_seq => {
@@ -1874,8 +2800,8 @@ where
 
             let mut status = packet.status.borrow_mut();
             match &*status {
-                SentReliableStatus::Sent { sent, link_id, msg, .. } if *link_id == id => {
-                    let size = if let ReliableMsg::Data(data) = &msg { data.len() } else { 0 };
+                SentReliableStatus::Sent { sent, link_id, msg, resent } if *link_id == id => {
+                    let size = msg.size();
 
                     link.txed_unacked_data -= size;
                     self.txed_unacked -= size;
@@ -1883,16 +2809,43 @@ where
 
                     link.roundtrip = (99 * link.roundtrip + sent.elapsed()) / 100;
 
-                    *status = SentReliableStatus::Received { size };
+                    let roundtrip = (!*resent).then(|| sent.elapsed());
+                    if let Some(delay_controller) = &mut link.delay_controller {
+                        delay_controller.on_ack(
+                            &mut link.txed_unacked_data_limit,
+                            size,
+                            roundtrip,
+                            link.txed_unacked_data,
+                            link.coupled_increase,
+                        );
+                    }
+                    if let (Some(coupling), Some(roundtrip)) = (&mut self.coupling, roundtrip) {
+                        coupling.record(link.link_id(), roundtrip);
+                    }
+
+                    let msg = resumable.then(|| msg.clone());
+                    *status = SentReliableStatus::Received { size, msg };
+                }
+                SentReliableStatus::Sent { link_id: sent_id, msg, .. } if self.cfg.redundancy.get() > 1 => {
+                    // A redundant copy sent over this link was received first.
+                    let size = msg.size();
+
+                    self.links[*sent_id].as_mut().unwrap().txed_unacked_data -= size;
+                    self.txed_unacked -= size;
+                    self.txed_unconsumable += size;
+
+                    let msg = resumable.then(|| msg.clone());
+                    *status = SentReliableStatus::Received { size, msg };
                 }
                 SentReliableStatus::ResendQueued { msg } => {
-                    let size = if let ReliableMsg::Data(data) = &msg { data.len() } else { 0 };
+                    let size = msg.size();
 
                     self.txed_unacked -= size;
                     self.txed_unconsumable += size;
                     self.resend_queue.retain(|packet| packet.seq != rxed_seq);
 
-                    *status = SentReliableStatus::Received { size };
+                    let msg = resumable.then(|| msg.clone());
+                    *status = SentReliableStatus::Received { size, msg };
                 }
                 _ => (),
             }
@@ -1902,9 +2855,12 @@ where
         while let Some(packet) = self.txed_packets.front() {
             self.txed_last_consumed = packet.seq;
 
-            let status = packet.status.borrow();
-            if let SentReliableStatus::Received { size, .. } = &*status {
-                self.txed_unconsumable -= size;
+            let mut status = packet.status.borrow_mut();
+            if let SentReliableStatus::Received { size, msg } = &mut *status {
+                self.txed_unconsumable -= *size;
+                if let Some(msg @ (ReliableMsg::Data(_) | ReliableMsg::CompressedData(_))) = msg.take() {
+                    self.txed_unconsumed_msgs.push_back(msg);
+                }
 
                 drop(status);
                 self.txed_packets.pop_front();
@@ -1912,6 +2868,25 @@ where
                 break;
             }
         }
+        self.release_parities();
+        self.remove_consumed_msgs();
+    }
+
+    /// Removes data messages that have been consumed by the remote endpoint
+    /// from `txed_unconsumed_msgs`.
+    fn remove_consumed_msgs(&mut self) {
+        while let Some(msg) = self.txed_unconsumed_msgs.front() {
+            // Since consumption is reported in bytes, empty messages are only
+            // removed once a following message has been consumed.
+            let size = msg.size();
+            if size > self.txed_consumed_unprocessed || self.txed_consumed_unprocessed == 0 {
+                break;
+            }
+
+            self.txed_consumed_unprocessed -= size;
+            self.txed_unconsumed_msgs.pop_front();
+            self.txed_consumed_count += 1;
+        }
     }
 
     /// Sends statistics data.
@@ -1963,6 +2938,16 @@ where
         self.link_filter = Box::new(move |link, others| link_filter(link, others).boxed());
     }
 
+    /// Sets the link scheduler.
+    ///
+    /// The link scheduler selects the link over which the next data packet is sent.
+    /// See the [scheduler module](crate::scheduler) for details.
+    ///
+    /// By default the [`DefaultLinkScheduler`] is used.
+    pub fn set_link_scheduler(&mut self, link_scheduler: impl LinkScheduler<TAG> + 'static) {
+        self.link_scheduler = Box::new(link_scheduler);
+    }
+
     /// Enables dumping of analysis data over the provided channel while the aggregator task is running.
     ///
     /// The purpose of the dumped data is to debug connection performance issues
@@ -12,7 +12,7 @@ use tokio::{
     sync::{mpsc, watch},
 };
 
-use super::{Receiver, ReceiverStream, RecvError, SendError, Sender, SenderSink};
+use super::{Mux, Receiver, ReceiverStream, RecvError, SendError, Sender, SenderSink};
 use crate::{
     agg::task::SendReq,
     cfg::{Cfg, ExchangedCfg},
@@ -30,7 +30,7 @@ pub struct Channel {
     cfg: Arc<Cfg>,
     remote_cfg: Option<Arc<ExchangedCfg>>,
     conn_id: ConnId,
-    tx: mpsc::Sender<SendReq>,
+    tx: Vec<mpsc::Sender<SendReq>>,
     tx_error: watch::Receiver<SendError>,
     rx: mpsc::Receiver<Bytes>,
     rx_closed: mpsc::Sender<()>,
@@ -40,7 +40,7 @@ pub struct Channel {
 impl Channel {
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
-        cfg: Arc<Cfg>, remote_cfg: Option<Arc<ExchangedCfg>>, conn_id: ConnId, tx: mpsc::Sender<SendReq>,
+        cfg: Arc<Cfg>, remote_cfg: Option<Arc<ExchangedCfg>>, conn_id: ConnId, tx: Vec<mpsc::Sender<SendReq>>,
         tx_error: watch::Receiver<SendError>, rx: mpsc::Receiver<Bytes>, rx_closed: mpsc::Sender<()>,
         rx_error: watch::Receiver<Option<RecvError>>,
     ) -> Self {
@@ -75,6 +75,13 @@ impl Channel {
         let (tx, rx) = self.into_tx_rx();
         Stream { tx: tx.into_sink(), rx: rx.into_stream() }
     }
+
+    /// Converts this into a [stream multiplexer](Mux) carrying many sub-streams.
+    ///
+    /// The remote endpoint must also convert its channel into a stream multiplexer.
+    pub fn into_mux(self) -> Mux {
+        Mux::new(self)
+    }
 }
 
 /// A bi-directional IO stream backed by a connection of aggregated links,
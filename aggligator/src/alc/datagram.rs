@@ -0,0 +1,166 @@
+//! Unreliable datagrams.
+
+use bytes::Bytes;
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::{
+    agg::task::Datagram,
+    cfg::{Cfg, Extensions},
+    exec::time::Instant,
+    id::ConnId,
+};
+
+/// Error sending a datagram.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatagramError {
+    /// The datagram exceeds the [maximum datagram size](Cfg::datagram_max_size).
+    TooBig,
+    /// The remote endpoint does not support the [datagram extension](Extensions::DATAGRAM).
+    Unsupported,
+    /// The connection has been terminated.
+    TaskTerminated,
+}
+
+impl fmt::Display for DatagramError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooBig => write!(f, "datagram too big"),
+            Self::Unsupported => write!(f, "datagrams not supported by remote endpoint"),
+            Self::TaskTerminated => write!(f, "connection terminated"),
+        }
+    }
+}
+
+impl std::error::Error for DatagramError {}
+
+/// Sender of unreliable datagrams over a connection of aggregated links.
+///
+/// Obtained using [`Control::datagram_sender`](crate::Control::datagram_sender).
+///
+/// Each datagram is sent once over the link that the [link scheduler](crate::scheduler)
+/// would select for the next data packet.
+/// Datagrams are neither acknowledged nor resent, thus they may be lost if
+/// a link fails, and they may arrive out of order, since each link has a different latency.
+/// They are sent in preference to new data of the [reliable channel](super::Channel).
+///
+/// This is useful for data that becomes worthless when delayed, such as live telemetry
+/// or video, which would otherwise be held up by the retransmission of lost data.
+#[derive(Clone)]
+pub struct DatagramSender {
+    cfg: Arc<Cfg>,
+    conn_id: ConnId,
+    tx: mpsc::Sender<Datagram>,
+    extensions: Arc<RwLock<Option<Extensions>>>,
+}
+
+impl fmt::Debug for DatagramSender {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DatagramSender").field("id", &self.conn_id).finish()
+    }
+}
+
+impl DatagramSender {
+    pub(crate) fn new(
+        cfg: Arc<Cfg>, conn_id: ConnId, tx: mpsc::Sender<Datagram>, extensions: Arc<RwLock<Option<Extensions>>>,
+    ) -> Self {
+        Self { cfg, conn_id, tx, extensions }
+    }
+
+    /// Connection id.
+    pub fn id(&self) -> ConnId {
+        self.conn_id
+    }
+
+    /// Maximum datagram size.
+    pub fn max_size(&self) -> usize {
+        self.cfg.datagram_max_size.get()
+    }
+
+    /// Enqueues a datagram for sending.
+    ///
+    /// This waits for space in the send queue.
+    /// Datagrams enqueued before the connection is established are sent once it is.
+    pub async fn send(&self, data: Bytes) -> Result<(), DatagramError> {
+        self.enqueue(Datagram { data, expires: None }).await
+    }
+
+    /// Enqueues a datagram for sending that is discarded if it cannot be sent within
+    /// the specified duration.
+    ///
+    /// The duration includes the time spent waiting for space in the send queue,
+    /// but not the time the datagram spends in transit.
+    pub async fn send_with_expiry(&self, data: Bytes, expiry: Duration) -> Result<(), DatagramError> {
+        self.enqueue(Datagram { data, expires: Some(Instant::now() + expiry) }).await
+    }
+
+    async fn enqueue(&self, datagram: Datagram) -> Result<(), DatagramError> {
+        if datagram.data.len() > self.max_size() {
+            return Err(DatagramError::TooBig);
+        }
+
+        if let Some(extensions) = *self.extensions.read().unwrap() {
+            if !extensions.contains(Extensions::DATAGRAM) {
+                return Err(DatagramError::Unsupported);
+            }
+        }
+
+        self.tx.send(datagram).await.map_err(|_| DatagramError::TaskTerminated)
+    }
+}
+
+/// Receiver of unreliable datagrams over a connection of aggregated links.
+///
+/// Obtained using [`Control::datagram_receiver`](crate::Control::datagram_receiver).
+/// Each receiver obtains all datagrams that arrive after it has been created.
+///
+/// If datagrams are not received fast enough, the oldest queued datagrams are discarded.
+/// Datagrams exceeding the [maximum datagram size](Cfg::datagram_max_size) are discarded on arrival.
+pub struct DatagramReceiver {
+    conn_id: ConnId,
+    rx: broadcast::Receiver<Bytes>,
+    discarded: u64,
+}
+
+impl fmt::Debug for DatagramReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DatagramReceiver").field("id", &self.conn_id).finish()
+    }
+}
+
+impl DatagramReceiver {
+    pub(crate) fn new(conn_id: ConnId, rx: broadcast::Receiver<Bytes>) -> Self {
+        Self { conn_id, rx, discarded: 0 }
+    }
+
+    /// Connection id.
+    pub fn id(&self) -> ConnId {
+        self.conn_id
+    }
+
+    /// Receives the next datagram.
+    ///
+    /// Returns `None` once the connection has been terminated.
+    pub async fn recv(&mut self) -> Option<Bytes> {
+        loop {
+            match self.rx.recv().await {
+                Ok(data) => return Some(data),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::debug!(conn_id =? self.conn_id, "discarded {n} received datagrams");
+                    self.discarded += n;
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Number of received datagrams that have been discarded, because they
+    /// were not received fast enough.
+    pub fn discarded(&self) -> u64 {
+        self.discarded
+    }
+}
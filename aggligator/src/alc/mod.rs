@@ -4,12 +4,19 @@
 //!
 //! An [aggregated link channel](Channel) supports both message-based communication,
 //! using a [Sender] and [Receiver], and [stream-based IO](Stream).
+//! Many lightweight sub-streams can be carried over a single channel using a
+//! [stream multiplexer](Mux).
+//! Unreliable [datagrams](DatagramSender) can be sent alongside the channel.
 //!
 
 mod channel;
+mod datagram;
+mod mux;
 pub(crate) mod receiver;
 pub(crate) mod sender;
 
 pub use channel::{Channel, Stream};
+pub use datagram::{DatagramError, DatagramReceiver, DatagramSender};
+pub use mux::{Mux, MuxCfg, MuxOpener, MuxStream};
 pub use receiver::{Receiver, ReceiverStream, RecvError};
-pub use sender::{SendError, Sender, SenderSink};
+pub use sender::{SendError, SendPriority, Sender, SenderSink};
@@ -0,0 +1,624 @@
+//! Stream multiplexer over an aggregated link channel.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::{ready, FutureExt};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt, io,
+    num::{NonZeroU32, NonZeroUsize},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    select,
+    sync::{mpsc, oneshot},
+};
+
+use super::{Channel, Receiver, RecvError, SendError, Sender};
+use crate::{control::Direction, exec, id::ConnId};
+
+/// Receive window of a sub-stream that both endpoints assume when it is opened.
+const INITIAL_WINDOW: u32 = 262_144;
+
+/// Size of the header of a multiplexer frame.
+const HEADER_LEN: usize = 5;
+
+const FRAME_OPEN: u8 = 1;
+const FRAME_DATA: u8 = 2;
+const FRAME_WINDOW: u8 = 3;
+const FRAME_FINISH: u8 = 4;
+const FRAME_RESET: u8 = 5;
+
+/// Flag set when the sub-stream was opened by the recipient of the frame.
+const FLAG_RECIPIENT_OPENED: u8 = 0x80;
+
+/// Stream multiplexer configuration.
+///
+/// Both endpoints of a connection may use different configurations.
+#[derive(Debug, Clone)]
+#[allow(clippy::manual_non_exhaustive)]
+pub struct MuxCfg {
+    /// Receive window of each sub-stream in bytes.
+    ///
+    /// This is the amount of data the remote endpoint may send over a sub-stream
+    /// before the local endpoint has read it.
+    /// Values below 256 kiB are raised to 256 kiB.
+    pub stream_window: NonZeroU32,
+    /// Number of sub-streams opened by the remote endpoint that are queued
+    /// for acceptance.
+    ///
+    /// If the queue is full, further sub-streams opened by the remote endpoint are reset.
+    pub accept_queue: NonZeroUsize,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Default for MuxCfg {
+    fn default() -> Self {
+        Self {
+            stream_window: NonZeroU32::new(INITIAL_WINDOW).unwrap(),
+            accept_queue: NonZeroUsize::new(64).unwrap(),
+            _non_exhaustive: (),
+        }
+    }
+}
+
+/// Identifies a sub-stream from the perspective of the local endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct StreamKey {
+    id: u32,
+    local: bool,
+}
+
+/// A multiplexer frame.
+#[derive(Debug)]
+enum Frame {
+    Open,
+    Data(Bytes),
+    Window(u32),
+    Finish,
+    Reset,
+}
+
+impl Frame {
+    fn encode(self, key: StreamKey) -> Bytes {
+        let (ty, payload) = match self {
+            Self::Open => (FRAME_OPEN, Bytes::new()),
+            Self::Data(data) => (FRAME_DATA, data),
+            Self::Window(increment) => (FRAME_WINDOW, Bytes::copy_from_slice(&increment.to_be_bytes())),
+            Self::Finish => (FRAME_FINISH, Bytes::new()),
+            Self::Reset => (FRAME_RESET, Bytes::new()),
+        };
+        let flag = if key.local { 0 } else { FLAG_RECIPIENT_OPENED };
+
+        let mut buf = BytesMut::with_capacity(HEADER_LEN + payload.len());
+        buf.put_u8(ty | flag);
+        buf.put_u32(key.id);
+        buf.put(payload);
+        buf.freeze()
+    }
+
+    fn decode(mut data: Bytes) -> Result<(StreamKey, Self), RecvError> {
+        if data.len() < HEADER_LEN {
+            return Err(RecvError::ProtocolError);
+        }
+
+        let ty = data.get_u8();
+        let key = StreamKey { id: data.get_u32(), local: ty & FLAG_RECIPIENT_OPENED != 0 };
+
+        let frame = match ty & !FLAG_RECIPIENT_OPENED {
+            FRAME_OPEN => Self::Open,
+            FRAME_DATA => Self::Data(data),
+            FRAME_WINDOW if data.len() == 4 => Self::Window(data.get_u32()),
+            FRAME_FINISH => Self::Finish,
+            FRAME_RESET => Self::Reset,
+            _ => return Err(RecvError::ProtocolError),
+        };
+
+        Ok((key, frame))
+    }
+}
+
+/// Command for the send task.
+enum Cmd {
+    Send(StreamKey, Frame),
+    Flush(oneshot::Sender<()>),
+    Drop { key: StreamKey, reset: bool },
+}
+
+/// Reason a sub-stream was ended without being finished.
+#[derive(Debug, Clone)]
+enum Failure {
+    Reset,
+    Send(SendError),
+    Recv(RecvError),
+}
+
+impl From<Failure> for io::Error {
+    fn from(failure: Failure) -> Self {
+        match failure {
+            Failure::Reset => io::Error::new(io::ErrorKind::ConnectionReset, "sub-stream was reset"),
+            Failure::Send(err) => err.into(),
+            Failure::Recv(err) => err.into(),
+        }
+    }
+}
+
+/// State of a sub-stream shared between its handle and the multiplexer tasks.
+#[derive(Default)]
+struct StreamState {
+    recv_buf: VecDeque<Bytes>,
+    /// Amount of data the remote endpoint may still send before the local endpoint reads.
+    recv_window: u32,
+    recv_finished: bool,
+    read_waker: Option<Waker>,
+    credit: u32,
+    write_waker: Option<Waker>,
+    failure: Option<Failure>,
+}
+
+impl StreamState {
+    fn wake(&mut self) {
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+        if let Some(waker) = self.write_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn fail(&mut self, failure: Failure) {
+        if self.failure.is_none() {
+            self.failure = Some(failure);
+        }
+        self.wake();
+    }
+}
+
+/// State shared between the multiplexer handles and tasks.
+struct Shared {
+    conn_id: ConnId,
+    window: u32,
+    max_data: usize,
+    next_id: AtomicU32,
+    streams: Mutex<HashMap<StreamKey, Arc<Mutex<StreamState>>>>,
+    tx_error: Mutex<Option<SendError>>,
+    rx_error: Mutex<Option<RecvError>>,
+}
+
+impl Shared {
+    fn stream(&self, key: StreamKey) -> Option<Arc<Mutex<StreamState>>> {
+        self.streams.lock().unwrap().get(&key).cloned()
+    }
+
+    fn send_error(&self) -> SendError {
+        self.tx_error.lock().unwrap().clone().unwrap_or(SendError::Closed)
+    }
+
+    fn fail_all(&self, failure: Failure) {
+        for state in self.streams.lock().unwrap().values() {
+            state.lock().unwrap().fail(failure.clone());
+        }
+    }
+}
+
+/// A multiplexer of lightweight sub-streams over an aggregated link channel.
+///
+/// Many sub-streams can be opened by both endpoints over a single connection.
+/// Each sub-stream is an independent byte stream implementing [`AsyncRead`] and [`AsyncWrite`]
+/// with its own flow control, i.e. a sub-stream that is not read does not stall
+/// the other sub-streams of the connection.
+/// Each direction of a sub-stream can be closed individually using
+/// [`poll_shutdown`](AsyncWrite::poll_shutdown).
+///
+/// Both endpoints of the connection must use a multiplexer.
+///
+/// The connection is closed once the multiplexer, all [openers](MuxOpener) and
+/// all [sub-streams](MuxStream) have been dropped.
+pub struct Mux {
+    opener: MuxOpener,
+    accept_rx: mpsc::Receiver<MuxStream>,
+}
+
+impl fmt::Debug for Mux {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mux").field("id", &self.opener.shared.conn_id).finish()
+    }
+}
+
+impl Mux {
+    /// Creates a stream multiplexer over the specified channel using the default configuration.
+    pub fn new(channel: Channel) -> Self {
+        Self::with_cfg(channel, MuxCfg::default())
+    }
+
+    /// Creates a stream multiplexer over the specified channel using the specified configuration.
+    pub fn with_cfg(channel: Channel, cfg: MuxCfg) -> Self {
+        let (tx, rx) = channel.into_tx_rx();
+
+        let shared = Arc::new(Shared {
+            conn_id: tx.id(),
+            window: cfg.stream_window.get().max(INITIAL_WINDOW),
+            max_data: tx.max_packet_size().min(tx.max_size()).saturating_sub(HEADER_LEN).max(1),
+            next_id: AtomicU32::new(0),
+            streams: Mutex::new(HashMap::new()),
+            tx_error: Mutex::new(None),
+            rx_error: Mutex::new(None),
+        });
+
+        let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+        let (accept_tx, accept_rx) = mpsc::channel(cfg.accept_queue.get());
+        let (done_tx, done_rx) = oneshot::channel();
+
+        exec::spawn(send_task(shared.clone(), tx, cmd_rx, done_tx));
+        exec::spawn(recv_task(shared.clone(), rx, cmd_tx.downgrade(), accept_tx, done_rx));
+
+        Self { opener: MuxOpener { shared, cmd_tx }, accept_rx }
+    }
+
+    /// Connection id.
+    pub fn id(&self) -> ConnId {
+        self.opener.id()
+    }
+
+    /// Opens a new sub-stream to the remote endpoint.
+    ///
+    /// This does not wait for the remote endpoint to accept the sub-stream;
+    /// data can be written to it immediately.
+    pub fn open(&self) -> Result<MuxStream, SendError> {
+        self.opener.open()
+    }
+
+    /// Returns a handle for opening sub-streams that can be moved to other tasks.
+    pub fn opener(&self) -> MuxOpener {
+        self.opener.clone()
+    }
+
+    /// Accepts the next sub-stream opened by the remote endpoint.
+    ///
+    /// Returns `None` when the connection has been closed.
+    pub async fn accept(&mut self) -> Result<Option<MuxStream>, RecvError> {
+        match self.accept_rx.recv().await {
+            Some(stream) => Ok(Some(stream)),
+            None => match self.opener.shared.rx_error.lock().unwrap().clone() {
+                None => Ok(None),
+                Some(err) => Err(err),
+            },
+        }
+    }
+}
+
+/// A handle for opening sub-streams of a [stream multiplexer](Mux).
+#[derive(Clone)]
+pub struct MuxOpener {
+    shared: Arc<Shared>,
+    cmd_tx: mpsc::UnboundedSender<Cmd>,
+}
+
+impl fmt::Debug for MuxOpener {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MuxOpener").field("id", &self.shared.conn_id).finish()
+    }
+}
+
+impl MuxOpener {
+    /// Connection id.
+    pub fn id(&self) -> ConnId {
+        self.shared.conn_id
+    }
+
+    /// Opens a new sub-stream to the remote endpoint.
+    ///
+    /// This does not wait for the remote endpoint to accept the sub-stream;
+    /// data can be written to it immediately.
+    pub fn open(&self) -> Result<MuxStream, SendError> {
+        let id = self.shared.next_id.fetch_add(1, Ordering::Relaxed);
+        let key = StreamKey { id, local: true };
+
+        let stream = MuxStream::new(key, self.shared.clone(), self.cmd_tx.clone());
+        stream.send(Frame::Open).map_err(|_| self.shared.send_error())?;
+        stream.advertise_window().map_err(|_| self.shared.send_error())?;
+
+        Ok(stream)
+    }
+}
+
+/// A sub-stream of a [stream multiplexer](Mux), implementing [`AsyncRead`] and [`AsyncWrite`].
+///
+/// Shutting down the sub-stream for writing signals end of stream to the remote
+/// endpoint, while data can still be received.
+/// Dropping the sub-stream before both directions have been finished resets it.
+pub struct MuxStream {
+    key: StreamKey,
+    shared: Arc<Shared>,
+    state: Arc<Mutex<StreamState>>,
+    cmd_tx: mpsc::UnboundedSender<Cmd>,
+    consumed: u32,
+    finished: bool,
+    flushed_rx: Option<oneshot::Receiver<()>>,
+}
+
+impl fmt::Debug for MuxStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MuxStream")
+            .field("conn_id", &self.shared.conn_id)
+            .field("id", &self.key.id)
+            .field("direction", &self.direction())
+            .field("finished", &self.finished)
+            .finish()
+    }
+}
+
+impl MuxStream {
+    fn new(key: StreamKey, shared: Arc<Shared>, cmd_tx: mpsc::UnboundedSender<Cmd>) -> Self {
+        let state = Arc::new(Mutex::new(StreamState {
+            recv_window: shared.window,
+            credit: INITIAL_WINDOW,
+            ..Default::default()
+        }));
+        shared.streams.lock().unwrap().insert(key, state.clone());
+
+        Self { key, shared, state, cmd_tx, consumed: 0, finished: false, flushed_rx: None }
+    }
+
+    /// Connection id.
+    pub fn conn_id(&self) -> ConnId {
+        self.shared.conn_id
+    }
+
+    /// Sub-stream id.
+    ///
+    /// Ids are only unique together with the [direction](Self::direction).
+    pub fn id(&self) -> u32 {
+        self.key.id
+    }
+
+    /// Whether the sub-stream was opened by the local endpoint
+    /// ([outgoing](Direction::Outgoing)) or by the remote endpoint ([incoming](Direction::Incoming)).
+    pub fn direction(&self) -> Direction {
+        if self.key.local {
+            Direction::Outgoing
+        } else {
+            Direction::Incoming
+        }
+    }
+
+    fn send(&self, frame: Frame) -> io::Result<()> {
+        self.cmd_tx.send(Cmd::Send(self.key, frame)).map_err(|_| self.shared.send_error().into())
+    }
+
+    /// Informs the remote endpoint about the part of the receive window exceeding the initial window.
+    fn advertise_window(&self) -> io::Result<()> {
+        match self.shared.window - INITIAL_WINDOW {
+            0 => Ok(()),
+            increment => self.send(Frame::Window(increment)),
+        }
+    }
+}
+
+impl AsyncRead for MuxStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf) -> Poll<io::Result<()>> {
+        let this = Pin::into_inner(self);
+
+        let mut state = this.state.lock().unwrap();
+        if let Some(data) = state.recv_buf.front_mut() {
+            let len = buf.remaining().min(data.len());
+            buf.put_slice(&data.split_to(len));
+            if data.is_empty() {
+                state.recv_buf.pop_front();
+            }
+            drop(state);
+
+            // Return receive window to remote endpoint.
+            this.consumed += len as u32;
+            if this.consumed >= this.shared.window / 2 {
+                this.send(Frame::Window(this.consumed))?;
+                let mut state = this.state.lock().unwrap();
+                state.recv_window = state.recv_window.saturating_add(this.consumed);
+                this.consumed = 0;
+            }
+
+            return Poll::Ready(Ok(()));
+        }
+
+        if state.recv_finished {
+            return Poll::Ready(Ok(()));
+        }
+
+        match &state.failure {
+            Some(failure) => Poll::Ready(Err(failure.clone().into())),
+            None => {
+                state.read_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl AsyncWrite for MuxStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = Pin::into_inner(self);
+
+        if this.finished {
+            return Poll::Ready(Err(SendError::Shutdown.into()));
+        }
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let mut state = this.state.lock().unwrap();
+        if let Some(failure) = &state.failure {
+            return Poll::Ready(Err(failure.clone().into()));
+        }
+        if state.credit == 0 {
+            state.write_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let len = buf.len().min(state.credit as usize).min(this.shared.max_data);
+        state.credit -= len as u32;
+        drop(state);
+
+        this.send(Frame::Data(Bytes::copy_from_slice(&buf[..len])))?;
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = Pin::into_inner(self);
+
+        if this.flushed_rx.is_none() {
+            let (flushed_tx, flushed_rx) = oneshot::channel();
+            this.cmd_tx.send(Cmd::Flush(flushed_tx)).map_err(|_| io::Error::from(this.shared.send_error()))?;
+            this.flushed_rx = Some(flushed_rx);
+        }
+
+        let flushed_rx = this.flushed_rx.as_mut().unwrap();
+        let res = ready!(flushed_rx.poll_unpin(cx));
+        this.flushed_rx = None;
+        res.map_err(|_| io::Error::from(this.shared.send_error()))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = Pin::into_inner(self);
+
+        if !this.finished {
+            this.send(Frame::Finish)?;
+            this.finished = true;
+        }
+
+        Pin::new(this).poll_flush(cx)
+    }
+}
+
+impl Drop for MuxStream {
+    fn drop(&mut self) {
+        let reset = {
+            let state = self.state.lock().unwrap();
+            state.failure.is_none() && !(self.finished && state.recv_finished)
+        };
+        let _ = self.cmd_tx.send(Cmd::Drop { key: self.key, reset });
+    }
+}
+
+/// Sends frames queued by the multiplexer handles over the channel.
+async fn send_task(
+    shared: Arc<Shared>, tx: Sender, mut cmd_rx: mpsc::UnboundedReceiver<Cmd>, _done_tx: oneshot::Sender<()>,
+) {
+    while let Some(cmd) = cmd_rx.recv().await {
+        let res = match cmd {
+            Cmd::Send(key, frame) => tx.send(frame.encode(key)).await,
+            Cmd::Flush(flushed_tx) => tx.flush().await.map(|()| {
+                let _ = flushed_tx.send(());
+            }),
+            Cmd::Drop { key, reset } => {
+                shared.streams.lock().unwrap().remove(&key);
+                match reset {
+                    true => tx.send(Frame::Reset.encode(key)).await,
+                    false => Ok(()),
+                }
+            }
+        };
+
+        if let Err(err) = res {
+            tracing::debug!(conn_id =% shared.conn_id, %err, "multiplexer send failed");
+            *shared.tx_error.lock().unwrap() = Some(err.clone());
+            shared.fail_all(Failure::Send(err));
+            break;
+        }
+    }
+}
+
+/// Receives frames from the channel and dispatches them to the sub-streams.
+async fn recv_task(
+    shared: Arc<Shared>, mut rx: Receiver, cmd_tx: mpsc::WeakUnboundedSender<Cmd>,
+    accept_tx: mpsc::Sender<MuxStream>, mut done_rx: oneshot::Receiver<()>,
+) {
+    let failure = loop {
+        let res = select! {
+            res = rx.recv() => res,
+            _ = &mut done_rx => break Failure::Reset,
+        };
+
+        let (key, frame) = match res.and_then(|data| data.map(Frame::decode).transpose()) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => break Failure::Reset,
+            Err(err) => {
+                tracing::debug!(conn_id =% shared.conn_id, %err, "multiplexer receive failed");
+                *shared.rx_error.lock().unwrap() = Some(err.clone());
+                break Failure::Recv(err);
+            }
+        };
+
+        match frame {
+            Frame::Open if !key.local && shared.stream(key).is_none() => {
+                let Some(cmd_tx) = cmd_tx.upgrade() else { break Failure::Reset };
+                let stream = MuxStream::new(key, shared.clone(), cmd_tx);
+                let _ = stream.advertise_window();
+                if accept_tx.try_send(stream).is_err() {
+                    tracing::debug!(conn_id =% shared.conn_id, id = key.id, "sub-stream not accepted, resetting");
+                }
+            }
+            Frame::Open => {
+                // Remote endpoint used a local id or an id that is already in use.
+                *shared.rx_error.lock().unwrap() = Some(RecvError::ProtocolError);
+                break Failure::Recv(RecvError::ProtocolError);
+            }
+            Frame::Data(data) => {
+                if let Some(state) = shared.stream(key) {
+                    let mut state = state.lock().unwrap();
+                    if state.recv_finished || state.failure.is_some() {
+                        continue;
+                    }
+
+                    match u32::try_from(data.len()).ok().and_then(|len| state.recv_window.checked_sub(len)) {
+                        Some(recv_window) => {
+                            state.recv_window = recv_window;
+                            state.recv_buf.push_back(data);
+                            state.wake();
+                        }
+                        None => {
+                            // Remote endpoint exceeded receive window.
+                            tracing::debug!(conn_id =% shared.conn_id, id = key.id, "receive window exceeded, resetting");
+                            state.recv_buf.clear();
+                            state.fail(Failure::Recv(RecvError::ProtocolError));
+                            drop(state);
+                            shared.streams.lock().unwrap().remove(&key);
+                            let Some(cmd_tx) = cmd_tx.upgrade() else { break Failure::Reset };
+                            let _ = cmd_tx.send(Cmd::Send(key, Frame::Reset));
+                        }
+                    }
+                }
+            }
+            Frame::Window(increment) => {
+                if let Some(state) = shared.stream(key) {
+                    let mut state = state.lock().unwrap();
+                    state.credit = state.credit.saturating_add(increment);
+                    state.wake();
+                }
+            }
+            Frame::Finish => {
+                if let Some(state) = shared.stream(key) {
+                    let mut state = state.lock().unwrap();
+                    state.recv_finished = true;
+                    state.wake();
+                }
+            }
+            Frame::Reset => {
+                if let Some(state) = shared.streams.lock().unwrap().remove(&key) {
+                    let mut state = state.lock().unwrap();
+                    state.recv_buf.clear();
+                    state.fail(Failure::Reset);
+                }
+            }
+        }
+    };
+
+    shared.fail_all(failure);
+}
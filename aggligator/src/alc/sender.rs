@@ -73,16 +73,52 @@ impl From<SendError> for io::Error {
     }
 }
 
+/// Priority class of data sent over an aggregated link channel.
+///
+/// Data of higher priority overtakes queued data of lower priority.
+/// To prevent starvation, queued data is overtaken by a limited number of messages only,
+/// thus data of lower priority still makes progress while data of higher priority is
+/// sent continuously.
+/// Data of the same priority is sent in order.
+///
+/// Since the remote endpoint receives data in the order it was sent, priorities
+/// only reorder data that is still waiting in the send queue.
+/// In particular, data that must be resent after a link failure is resent in the
+/// order it was originally sent regardless of its priority, because data of higher
+/// priority could not be received before the older data preceding it anyway.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SendPriority {
+    /// High priority, for example for interactive or control messages.
+    High,
+    /// Normal priority.
+    #[default]
+    Normal,
+    /// Low priority, for example for bulk transfers.
+    Low,
+}
+
+impl SendPriority {
+    /// All priorities in descending order.
+    pub(crate) const ALL: [Self; 3] = [Self::High, Self::Normal, Self::Low];
+}
+
 fn max_send_size(remote_cfg: &ExchangedCfg) -> usize {
     (remote_cfg.recv_buffer.get() as usize / 2).max(2) - 1
 }
 
+fn max_packet_size(cfg: &Cfg, remote_cfg: &ExchangedCfg) -> usize {
+    cfg.io_write_size.get().min(remote_cfg.recv_buffer.get() as usize)
+}
+
 /// The sending half of an aggregated link channel.
+///
+/// Each [priority](SendPriority) has its own send queue of
+/// [configured length](Cfg::send_queue).
 pub struct Sender {
     cfg: Arc<Cfg>,
     remote_cfg: Arc<ExchangedCfg>,
     conn_id: ConnId,
-    tx: mpsc::Sender<SendReq>,
+    tx: Vec<mpsc::Sender<SendReq>>,
     error_rx: watch::Receiver<SendError>,
 }
 
@@ -94,7 +130,7 @@ impl fmt::Debug for Sender {
 
 impl Sender {
     pub(crate) fn new(
-        cfg: Arc<Cfg>, remote_cfg: Arc<ExchangedCfg>, conn_id: ConnId, tx: mpsc::Sender<SendReq>,
+        cfg: Arc<Cfg>, remote_cfg: Arc<ExchangedCfg>, conn_id: ConnId, tx: Vec<mpsc::Sender<SendReq>>,
         error_rx: watch::Receiver<SendError>,
     ) -> Self {
         Self { cfg, remote_cfg, conn_id, tx, error_rx }
@@ -105,22 +141,38 @@ impl Sender {
         self.conn_id
     }
 
-    /// Enqueues data for sending.
+    /// Enqueues data for sending with [normal priority](SendPriority::Normal).
     #[inline]
     pub async fn send(&self, data: Bytes) -> Result<(), SendError> {
+        self.send_with_priority(data, SendPriority::Normal).await
+    }
+
+    /// Enqueues data for sending with the specified priority.
+    #[inline]
+    pub async fn send_with_priority(&self, data: Bytes, priority: SendPriority) -> Result<(), SendError> {
         if data.len() > self.max_size() {
             return Err(SendError::DataTooBig);
         }
 
-        self.tx.send(SendReq::Send(data)).await.map_err(|_| self.error_rx.borrow().clone())
+        self.tx[priority as usize].send(SendReq::Send(data)).await.map_err(|_| self.error_rx.borrow().clone())
     }
 
     /// Flushes data queued for sending.
+    ///
+    /// This waits until data of all priorities enqueued before has been sent.
     #[inline]
     pub async fn flush(&self) -> Result<(), SendError> {
-        let (flushed_tx, flushed_rx) = oneshot::channel();
-        self.tx.send(SendReq::Flush(flushed_tx)).await.map_err(|_| self.error_rx.borrow().clone())?;
-        flushed_rx.await.map_err(|_| self.error_rx.borrow().clone())?;
+        // Lower priority data may overtake higher priority data,
+        // thus each queue must be flushed.
+        let mut flushed_rxs = Vec::new();
+        for tx in &self.tx {
+            let (flushed_tx, flushed_rx) = oneshot::channel();
+            tx.send(SendReq::Flush(flushed_tx)).await.map_err(|_| self.error_rx.borrow().clone())?;
+            flushed_rxs.push(flushed_rx);
+        }
+        for flushed_rx in flushed_rxs {
+            flushed_rx.await.map_err(|_| self.error_rx.borrow().clone())?;
+        }
         Ok(())
     }
 
@@ -129,14 +181,27 @@ impl Sender {
         max_send_size(&self.remote_cfg)
     }
 
+    /// Size of a data packet when sending a byte stream.
+    pub(crate) fn max_packet_size(&self) -> usize {
+        max_packet_size(&self.cfg, &self.remote_cfg)
+    }
+
     /// Converts this sender into a [SenderSink], that implements the [Sink] and [AsyncWrite] traits.
+    ///
+    /// The sink sends data with [normal priority](SendPriority::Normal).
     pub fn into_sink(self) -> SenderSink {
-        let Self { cfg, remote_cfg, conn_id, tx, error_rx } = self;
+        self.into_sink_with_priority(SendPriority::Normal)
+    }
+
+    /// Converts this sender into a [SenderSink] that sends data with the specified priority.
+    pub fn into_sink_with_priority(self, priority: SendPriority) -> SenderSink {
+        let Self { cfg, remote_cfg, conn_id, mut tx, error_rx } = self;
         SenderSink {
             cfg,
             remote_cfg,
             conn_id,
-            tx: sync::PollSender::new(tx),
+            priority,
+            tx: sync::PollSender::new(tx.swap_remove(priority as usize)),
             flushed_rx: None,
             error_rx,
             closed: false,
@@ -151,6 +216,7 @@ pub struct SenderSink {
     cfg: Arc<Cfg>,
     remote_cfg: Arc<ExchangedCfg>,
     conn_id: ConnId,
+    priority: SendPriority,
     tx: sync::PollSender<SendReq>,
     flushed_rx: Option<oneshot::Receiver<()>>,
     error_rx: watch::Receiver<SendError>,
@@ -179,6 +245,11 @@ impl SenderSink {
     pub fn max_size(&self) -> usize {
         max_send_size(&self.remote_cfg)
     }
+
+    /// Priority of sent data.
+    pub fn priority(&self) -> SendPriority {
+        self.priority
+    }
 }
 
 impl Sink<Bytes> for SenderSink {
@@ -255,8 +326,7 @@ impl AsyncWrite for SenderSink {
 
         ready!(this.poll_ready_unpin(cx))?;
 
-        let max_packet_size = this.cfg.io_write_size.get().min(this.remote_cfg.recv_buffer.get() as usize);
-        let len = buf.len().min(max_packet_size);
+        let len = buf.len().min(max_packet_size(&this.cfg, &this.remote_cfg));
         let data = Bytes::copy_from_slice(&buf[..len]);
         this.start_send_unpin(data)?;
 
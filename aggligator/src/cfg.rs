@@ -2,8 +2,10 @@
 
 use byteorder::{ReadBytesExt, WriteBytesExt, BE};
 use std::{
-    io,
-    num::{NonZeroU32, NonZeroUsize},
+    fmt, io,
+    num::{NonZeroU32, NonZeroU8, NonZeroUsize},
+    ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign},
+    sync::Arc,
     time::Duration,
 };
 
@@ -22,6 +24,164 @@ pub enum LinkPing {
     WhenTimedOut,
 }
 
+/// Congestion control algorithm for limiting the unacknowledged data of each link.
+#[cfg_attr(feature = "dump", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum CongestionControl {
+    /// Heuristic that increases the limit while data is waiting to be sent and
+    /// decreases it when the send buffer overruns or the roundtrip time approaches
+    /// [`link_max_ping`](Cfg::link_max_ping).
+    #[default]
+    Heuristic,
+    /// Delay-based control in the style of LEDBAT (RFC 6817) with specified target queuing delay.
+    ///
+    /// The limit is adjusted to keep the queuing delay of the link, i.e. its measured roundtrip
+    /// time minus its minimum roundtrip time, near the target.
+    /// It is not decreased below the bandwidth-delay product of the link, estimated from
+    /// the rate of acknowledged data and the minimum roundtrip time.
+    ///
+    /// This avoids filling the buffer at the bottleneck of a link and thus keeps latency under load low.
+    Ledbat(Duration),
+}
+
+/// Set of protocol extensions.
+///
+/// Protocol extensions are optional features of the link aggregation protocol.
+/// When a link is established both endpoints advertise the extensions they
+/// [offer](Cfg::extensions) and the connection uses the extensions offered by both of them.
+///
+/// Extensions unknown to an endpoint are ignored, thus new extensions can be
+/// introduced without breaking compatibility with endpoints that do not support them.
+#[cfg_attr(feature = "dump", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Extensions(u32);
+
+impl Extensions {
+    /// No extensions.
+    pub const NONE: Self = Self(0);
+
+    /// Compression of data using LZ4.
+    ///
+    /// When offered, the endpoint is able to decompress received data.
+    /// Sent data is compressed only if [`Cfg::compression`] is enabled.
+    pub const COMPRESSION: Self = Self(1 << 0);
+
+    /// End-to-end encryption of data.
+    ///
+    /// This is only offered if [`Cfg::encryption`] is enabled.
+    pub const ENCRYPTION: Self = Self(1 << 1);
+
+    /// Mutual authentication of links using a pre-shared key.
+    ///
+    /// This is only offered if [`Cfg::psk`] is specified.
+    pub const AUTHENTICATION: Self = Self(1 << 2);
+
+    /// Forward error correction using parity of groups of sent messages.
+    ///
+    /// When offered, the endpoint is able to rebuild lost messages from received parity.
+    /// Parity is sent only if [`Cfg::fec_group_size`] is specified.
+    pub const FEC: Self = Self(1 << 3);
+
+    /// Unreliable datagrams sent alongside the reliable data.
+    ///
+    /// When offered, the endpoint is able to receive [datagrams](crate::alc::DatagramSender).
+    pub const DATAGRAM: Self = Self(1 << 4);
+
+    /// Timestamps in ping messages for measuring the one-way delay of links.
+    ///
+    /// When offered, the endpoint responds to timestamped pings with timestamped pongs.
+    pub const TIMESTAMPS: Self = Self(1 << 5);
+
+    /// All extensions supported by this implementation.
+    pub const SUPPORTED: Self = Self::COMPRESSION
+        .union(Self::ENCRYPTION)
+        .union(Self::AUTHENTICATION)
+        .union(Self::FEC)
+        .union(Self::DATAGRAM)
+        .union(Self::TIMESTAMPS);
+
+    /// The raw flags of the extensions.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Creates the set of extensions from raw flags, ignoring unsupported extensions.
+    pub const fn from_bits_truncate(bits: u32) -> Self {
+        Self(bits & Self::SUPPORTED.0)
+    }
+
+    /// Whether no extension is contained in the set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether all extensions of `other` are contained in this set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Extensions contained in this set and in `other`.
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// Extensions contained in this set or in `other`.
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitAnd for Extensions {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self {
+        self.intersection(rhs)
+    }
+}
+
+impl BitAndAssign for Extensions {
+    fn bitand_assign(&mut self, rhs: Self) {
+        *self = self.intersection(rhs);
+    }
+}
+
+impl BitOr for Extensions {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+impl BitOrAssign for Extensions {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = self.union(rhs);
+    }
+}
+
+/// A pre-shared key.
+///
+/// The key is not shown in debug output.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PreSharedKey(Arc<[u8]>);
+
+impl fmt::Debug for PreSharedKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PreSharedKey(..)")
+    }
+}
+
+impl PreSharedKey {
+    /// Creates a pre-shared key from the specified bytes.
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self(key.as_ref().into())
+    }
+
+    /// The key bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 /// Configuration of a connection consisting of aggregated links.
 ///
 /// For most use cases the default configuration, i.e. [`Cfg::default()`](Self::default),
@@ -37,7 +197,8 @@ pub enum LinkPing {
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[allow(clippy::manual_non_exhaustive)]
 pub struct Cfg {
-    /// The size of a data packet when sending using [stream-based IO](crate::alc::Stream).
+    /// The size of a data packet when sending using [stream-based IO](crate::alc::Stream)
+    /// or a [stream multiplexer](crate::alc::Mux).
     pub io_write_size: NonZeroUsize,
     /// Maximum number of unacknowledged sent bytes.
     pub send_buffer: NonZeroU32,
@@ -59,6 +220,17 @@ pub struct Cfg {
     pub link_unacked_init: NonZeroUsize,
     /// Maximum amount of sent unacknowledged data per link.
     pub link_unacked_limit: NonZeroUsize,
+    /// Congestion control algorithm adjusting the amount of sent unacknowledged data per link.
+    pub link_congestion_control: CongestionControl,
+    /// Couple the congestion control of links sharing a bottleneck.
+    ///
+    /// Links whose roundtrip times are correlated are assumed to share a bottleneck,
+    /// for example two wireless networks using the same uplink.
+    /// The increase of their unacknowledged data limits is then coupled, so that together they
+    /// are not more aggressive than a single link and leave a fair share to other traffic.
+    ///
+    /// This trades throughput for fairness and is therefore disabled by default.
+    pub link_coupled_congestion_control: bool,
     /// Link pinging mode.
     pub link_ping: LinkPing,
     /// Timeout for waiting for ping response, which when exceeded leads to removal of the link.
@@ -83,8 +255,109 @@ pub struct Cfg {
     pub connect_queue: NonZeroUsize,
     /// Disconnect the aggregated connection when a server id mismatch occurs while connecting a link.
     pub disconnect_on_server_id_mismatch: bool,
+    /// Resume the connection when the server changes, for example because it has been restarted.
+    ///
+    /// A server [suspends](crate::control::Control::suspend) an incoming connection to obtain a
+    /// resumption ticket, which is then [passed to](crate::connect::Server::resume) the server
+    /// that takes over the connection.
+    /// If enabled and a link connects to a different server after all links to the previous
+    /// server have failed, the connection continues over the new server.
+    /// Both endpoints exchange how many data messages they have received and resend only
+    /// the data that the other endpoint is missing, thus no data is lost or duplicated.
+    /// For this, sent data is kept until it has been consumed by the remote endpoint, which
+    /// requires additional memory of up to the [receive buffer](Self::recv_buffer) size of it.
+    /// A server that has no ticket for the connection refuses the link with
+    /// [`AddLinkError::NotResumable`](crate::control::AddLinkError::NotResumable).
+    ///
+    /// This must be enabled on both endpoints and takes precedence over
+    /// [`disconnect_on_server_id_mismatch`](Self::disconnect_on_server_id_mismatch).
+    /// It has no effect if [encryption](Self::encryption) is enabled,
+    /// since the encryption keys are bound to the connection.
+    pub session_resumption: bool,
     /// Link speed statistics interval durations.
     pub stats_intervals: Vec<Duration>,
+    /// Protocol extensions to offer to the remote endpoint.
+    ///
+    /// The connection uses the extensions offered by both endpoints.
+    /// Extensions that require a configuration option, such as [encryption](Self::encryption),
+    /// are only offered if it is enabled, see [`offered_extensions`](Self::offered_extensions).
+    pub extensions: Extensions,
+    /// Compress sent data.
+    ///
+    /// Data is compressed once before it is distributed over the links,
+    /// which is more efficient than compressing each link individually.
+    /// Data that is not compressible is sent uncompressed.
+    ///
+    /// This only takes effect if both endpoints offer the
+    /// [compression extension](Extensions::COMPRESSION).
+    /// Since compression uses processing time of the connection task, it should only
+    /// be enabled when the links are slow compared to the available processing power.
+    pub compression: bool,
+    /// Encrypt and authenticate sent data end-to-end.
+    ///
+    /// Data is encrypted once before it is distributed over the links
+    /// using ChaCha20-Poly1305.
+    /// This avoids the cost of establishing and maintaining a TLS session on each link.
+    /// Protocol control messages are not encrypted.
+    ///
+    /// The encryption keys are derived from the shared secret of the
+    /// [Diffie-Hellman key exchange](crate#connection-security) performed by the link
+    /// that establishes the connection and the [pre-shared key](Self::psk), if specified.
+    /// Without a pre-shared key, the connection is protected from eavesdroppers but
+    /// not from an active man-in-the-middle attacker.
+    ///
+    /// If enabled, links to remote endpoints that do not enable encryption are refused.
+    pub encryption: bool,
+    /// Pre-shared key for authenticating links.
+    ///
+    /// If specified, both endpoints prove knowledge of the pre-shared key to each other
+    /// when establishing a link and links from remote endpoints that do not know it are refused.
+    /// This does not require certificates and thus is well suited for embedded devices.
+    ///
+    /// It is also used for deriving the [encryption](Self::encryption) keys.
+    #[cfg_attr(feature = "dump", serde(skip))]
+    pub psk: Option<PreSharedKey>,
+    /// Number of consecutive sent data messages protected by one parity message
+    /// for forward error correction.
+    ///
+    /// The parity is sent in addition to the data over the link selected by the
+    /// [link scheduler](crate::scheduler) and counts towards its unacknowledged data.
+    /// From it the remote endpoint can rebuild a single message of the group that is lost or
+    /// delayed on a stalled link, without waiting for the acknowledgement timeout
+    /// and the resending of the message.
+    /// This reduces tail latency on links with high or varying latency at the cost
+    /// of additional traffic of one message per group.
+    /// Incomplete groups are protected when no more data is queued for sending,
+    /// thus sporadically sent messages are effectively duplicated.
+    ///
+    /// This only takes effect if both endpoints offer the
+    /// [forward error correction extension](Extensions::FEC).
+    /// `None` disables forward error correction.
+    pub fec_group_size: Option<NonZeroU8>,
+    /// Number of links each data packet is sent over.
+    ///
+    /// By default each data packet is sent over one link, so that the throughput
+    /// of all links is aggregated.
+    /// If greater than one, a copy of each data packet is additionally sent over the
+    /// fastest other working links, up to the specified total number of links.
+    /// The remote endpoint uses the first copy it receives and discards the others.
+    /// Thus latency is determined by the fastest link and a stalled link does
+    /// not delay the data, at the cost of throughput and additional traffic.
+    /// This is useful for latency-critical traffic, such as interactive sessions.
+    ///
+    /// Copies are only sent over links having the same [priority](crate::control::Link::priority)
+    /// as the best working link and count towards the unacknowledged data of the link
+    /// they are sent over, thus a congested link delays sending of copies.
+    /// This requires no support by the remote endpoint.
+    pub redundancy: NonZeroUsize,
+    /// Length of the queues for sending and receiving [datagrams](crate::alc::DatagramSender).
+    ///
+    /// When the receive queue is full, the oldest received datagrams are discarded.
+    pub datagram_queue: NonZeroUsize,
+    /// Maximum size of a [datagram](crate::alc::DatagramSender).
+    ///
+    /// Received datagrams exceeding this size are discarded.
+    pub datagram_max_size: NonZeroUsize,
     #[doc(hidden)]
     pub _non_exhaustive: (),
 }
@@ -103,6 +376,8 @@ impl Default for Cfg {
             link_ack_timeout_max: Duration::from_secs(30),
             link_unacked_init: NonZeroUsize::new(8192).unwrap(),
             link_unacked_limit: NonZeroUsize::new(33_554_432).unwrap(),
+            link_congestion_control: CongestionControl::Heuristic,
+            link_coupled_congestion_control: false,
             link_ping: LinkPing::WhenIdle(Duration::from_secs(15)),
             link_ping_timeout: Duration::from_secs(40),
             link_max_ping: None,
@@ -114,17 +389,42 @@ impl Default for Cfg {
             termination_timeout: Duration::from_secs(300),
             connect_queue: NonZeroUsize::new(32).unwrap(),
             disconnect_on_server_id_mismatch: true,
+            session_resumption: false,
             stats_intervals: vec![
                 Duration::from_millis(100),
                 Duration::from_secs(1),
                 Duration::from_secs(5),
                 Duration::from_secs(10),
             ],
+            extensions: Extensions::SUPPORTED,
+            compression: false,
+            encryption: false,
+            psk: None,
+            fec_group_size: None,
+            redundancy: NonZeroUsize::new(1).unwrap(),
+            datagram_queue: NonZeroUsize::new(256).unwrap(),
+            datagram_max_size: NonZeroUsize::new(65_536).unwrap(),
             _non_exhaustive: (),
         }
     }
 }
 
+impl Cfg {
+    /// Protocol extensions offered to the remote endpoint.
+    ///
+    /// These are the [extensions](Self::extensions) whose required configuration options are enabled.
+    pub fn offered_extensions(&self) -> Extensions {
+        let mut extensions = self.extensions;
+        if !self.encryption {
+            extensions.0 &= !Extensions::ENCRYPTION.0;
+        }
+        if self.psk.is_none() {
+            extensions.0 &= !Extensions::AUTHENTICATION.0;
+        }
+        extensions
+    }
+}
+
 /// Link aggregator configuration exchanged with remote endpoint.
 #[derive(Clone, Debug)]
 pub(crate) struct ExchangedCfg {
@@ -27,8 +27,9 @@ use x25519_dalek::{PublicKey, StaticSecret};
 use crate::{
     agg::{link_int::LinkInt, task::Task, AggParts},
     alc::Channel,
-    cfg::{Cfg, ExchangedCfg},
-    control::{Control, Direction, Link},
+    cfg::{Cfg, ExchangedCfg, Extensions},
+    control::{Control, Direction, Link, ResumptionTicket},
+    crypto::LinkAuth,
     exec::time::{error::Elapsed, timeout, Instant},
     id::{ConnId, OwnedConnId, ServerId},
     io::{IoRx, IoTx},
@@ -72,6 +73,14 @@ pub enum IncomingError {
     Closed,
     /// The link aggregator server was dropped.
     ServerDropped,
+    /// [Encryption](crate::cfg::Cfg::encryption) is required by one endpoint
+    /// but not enabled on the other.
+    EncryptionRequired,
+    /// Authentication using the [pre-shared key](crate::cfg::Cfg::psk) failed.
+    Unauthorized,
+    /// The incoming link resumes a connection, but no [resumption ticket](Server::resume)
+    /// has been given for it.
+    NotResumable,
 }
 
 impl fmt::Display for IncomingError {
@@ -82,6 +91,9 @@ impl fmt::Display for IncomingError {
             Self::NotListening => write!(f, "not listening"),
             Self::Closed => write!(f, "connection was closed"),
             Self::ServerDropped => write!(f, "server dropped"),
+            Self::EncryptionRequired => write!(f, "encryption required"),
+            Self::Unauthorized => write!(f, "unauthorized"),
+            Self::NotResumable => write!(f, "no resumption ticket for connection"),
         }
     }
 }
@@ -108,6 +120,9 @@ impl From<IncomingError> for io::Error {
             IncomingError::NotListening => io::Error::new(io::ErrorKind::ConnectionRefused, err),
             IncomingError::Closed => io::Error::new(io::ErrorKind::ConnectionAborted, err),
             IncomingError::ServerDropped => io::Error::new(io::ErrorKind::ConnectionRefused, err),
+            IncomingError::EncryptionRequired => io::Error::new(io::ErrorKind::ConnectionRefused, err),
+            IncomingError::Unauthorized => io::Error::new(io::ErrorKind::PermissionDenied, err),
+            IncomingError::NotResumable => io::Error::new(io::ErrorKind::ConnectionRefused, err),
         }
     }
 }
@@ -144,9 +159,11 @@ pub struct Incoming<TX, RX, TAG> {
     conn_id: OwnedConnId,
     server_id: ServerId,
     remote_server_id: Option<ServerId>,
+    extensions: Extensions,
     link_tx: mpsc::Sender<LinkInt<TX, RX, TAG>>,
     link_rx: mpsc::Receiver<LinkInt<TX, RX, TAG>>,
     links: Vec<LinkInt<TX, RX, TAG>>,
+    ticket: Option<ResumptionTicket>,
 }
 
 impl<TX, RX, TAG> fmt::Debug for Incoming<TX, RX, TAG>
@@ -160,7 +177,9 @@ where
             .field("id", &self.id())
             .field("server_id", &self.server_id)
             .field("remote_server_id", &self.remote_server_id)
+            .field("extensions", &self.extensions)
             .field("link_tags", &link_tags)
+            .field("resumed", &self.is_resumed())
             .finish()
     }
 }
@@ -183,6 +202,18 @@ impl<TX, RX, TAG> Incoming<TX, RX, TAG> {
         self.remote_server_id
     }
 
+    /// The protocol extensions offered by both endpoints, which will be used by the connection.
+    pub fn extensions(&self) -> Extensions {
+        self.extensions
+    }
+
+    /// Whether the connection resumes a session suspended on another server.
+    ///
+    /// See [`Server::resume`].
+    pub fn is_resumed(&self) -> bool {
+        self.ticket.is_some()
+    }
+
     /// Updates the incoming links for the connection.
     fn update_links(&mut self) {
         while let Ok(link_int) = self.link_rx.try_recv() {
@@ -225,17 +256,22 @@ where
     pub fn accept(mut self) -> (Task<TX, RX, TAG>, Channel, Control<TX, RX, TAG>) {
         self.update_links();
 
-        let Self { cfg, conn_id, server_id, remote_server_id, link_tx, link_rx, links } = self;
+        let Self { cfg, conn_id, server_id, remote_server_id, extensions, link_tx, link_rx, links, ticket } =
+            self;
 
-        let AggParts { task, channel, control, connected_rx: _ } = AggParts::new(
+        let AggParts { mut task, channel, control, connected_rx: _ } = AggParts::new(
             cfg,
             conn_id,
             Direction::Incoming,
             Some(server_id),
             remote_server_id,
+            Some(extensions),
             links,
             Some((link_tx, link_rx)),
         );
+        if let Some(ticket) = ticket {
+            task.resume_from_ticket(ticket);
+        }
 
         (task, channel, control)
     }
@@ -260,13 +296,22 @@ struct ServerInner<TX, RX, TAG> {
     closed_conns_tx: mpsc::UnboundedSender<ConnId>,
     closed_conns_rx: mpsc::UnboundedReceiver<ConnId>,
     listen_tx: mpsc::Sender<Incoming<TX, RX, TAG>>,
+    tickets: HashMap<ConnId, ResumptionTicket>,
 }
 
 impl<TX, RX, TAG> ServerInner<TX, RX, TAG> {
     fn new(cfg: Arc<Cfg>, server_id: ServerId) -> Self {
         let (closed_conns_tx, closed_conns_rx) = mpsc::unbounded_channel();
         let listen_tx = mpsc::channel(cfg.connect_queue.get()).0;
-        Self { cfg, server_id, conns: HashMap::new(), closed_conns_tx, closed_conns_rx, listen_tx }
+        Self {
+            cfg,
+            server_id,
+            conns: HashMap::new(),
+            closed_conns_tx,
+            closed_conns_rx,
+            listen_tx,
+            tickets: HashMap::new(),
+        }
     }
 
     /// Clean up closed connections.
@@ -337,6 +382,7 @@ where
             Direction::Outgoing,
             Some(self.server_id),
             None,
+            None,
             Vec::new(),
             Some((link_tx.clone(), link_rx)),
         );
@@ -364,6 +410,17 @@ where
         Ok(Listener { server_id: inner.server_id, listen_rx })
     }
 
+    /// Accepts the resumption of a session that has been [suspended](Control::suspend) on another server.
+    ///
+    /// When the client connects, the session is continued as a new incoming connection
+    /// obtained from the [`Listener`] without loss or duplication of data.
+    /// Links of clients resuming a session without a ticket on this server are refused
+    /// with [`IncomingError::NotResumable`].
+    pub fn resume(&self, ticket: ResumptionTicket) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.tickets.insert(ticket.id(), ticket);
+    }
+
     /// Adds an incoming, packet-based link.
     ///
     /// If the incoming link belongs to an existing connection, it is added to that connection.
@@ -401,44 +458,107 @@ where
         }
 
         // Perform protocol handshake.
-        let (remote_server_id, conn_id, existing, remote_cfg, roundtrip, remote_user_data) =
-            timeout(cfg.link_ping_timeout, async {
-                let random: [u8; 32] = rand::random();
-                let server_secret = StaticSecret::from(random);
-                let server_public_key = PublicKey::from(&server_secret);
-
-                let start = Instant::now();
-                LinkMsg::Welcome {
-                    extensions: 0,
-                    public_key: server_public_key,
-                    server_id,
-                    user_data: user_data.to_vec(),
-                    cfg: (&*cfg).into(),
-                }
-                .send(&mut tx)
-                .await?;
-
-                let LinkMsg::Connect {
-                    extensions: _,
-                    public_key: client_public_key,
-                    server_id,
-                    connection_id: encrypted_conn_id,
-                    existing_connection,
-                    user_data: remote_user_data,
-                    cfg,
-                } = LinkMsg::recv(&mut rx).await?
-                else {
-                    return Err::<_, IncomingError>(protocol_err!("expected Connect message").into());
+        let (
+            remote_server_id,
+            conn_id,
+            existing,
+            resumed,
+            remote_cfg,
+            extensions,
+            authenticated,
+            roundtrip,
+            remote_user_data,
+            conn_secret,
+        ) = timeout(cfg.link_ping_timeout, async {
+            let random: [u8; 32] = rand::random();
+            let server_secret = StaticSecret::from(random);
+            let server_public_key = PublicKey::from(&server_secret);
+
+            let offered_extensions = cfg.offered_extensions();
+            let psk = &cfg.psk;
+
+            let start = Instant::now();
+            LinkMsg::Welcome {
+                extensions: offered_extensions.bits(),
+                public_key: server_public_key,
+                server_id,
+                user_data: user_data.to_vec(),
+                cfg: (&*cfg).into(),
+            }
+            .send(&mut tx)
+            .await?;
+
+            let LinkMsg::Connect {
+                extensions: remote_extensions,
+                public_key: client_public_key,
+                server_id,
+                connection_id: encrypted_conn_id,
+                existing_connection,
+                resumed_connection,
+                user_data: remote_user_data,
+                cfg,
+            } = LinkMsg::recv(&mut rx).await?
+            else {
+                return Err::<_, IncomingError>(protocol_err!("expected Connect message").into());
+            };
+            let roundtrip = start.elapsed();
+
+            let shared_secret = server_secret.diffie_hellman(&client_public_key);
+            let conn_id = encrypted_conn_id.decrypt(&shared_secret);
+
+            let extensions = offered_extensions & Extensions::from_bits_truncate(remote_extensions);
+
+            let mut authenticated = false;
+            if let (Some(psk), true) = (psk, extensions.contains(Extensions::AUTHENTICATION)) {
+                let LinkMsg::Authenticate { proof } = LinkMsg::recv(&mut rx).await? else {
+                    return Err(protocol_err!("expected Authenticate message").into());
                 };
 
-                let shared_secret = server_secret.diffie_hellman(&client_public_key);
-                let conn_id = encrypted_conn_id.decrypt(&shared_secret);
+                let auth = LinkAuth::new(psk, &server_public_key, &client_public_key, &shared_secret);
+                if auth.verify(Direction::Outgoing, &proof) {
+                    LinkMsg::Authenticate { proof: auth.proof(Direction::Incoming) }.send(&mut tx).await?;
+                    authenticated = true;
+                }
+            }
 
-                Ok((server_id, conn_id, existing_connection, cfg, start.elapsed(), remote_user_data))
-            })
+            Ok((
+                server_id,
+                conn_id,
+                existing_connection,
+                resumed_connection,
+                cfg,
+                extensions,
+                authenticated,
+                roundtrip,
+                remote_user_data,
+                *shared_secret.as_bytes(),
+            ))
+        })
+        .await??;
+
+        tracing::debug!(?server_id, ?conn_id, ?existing, ?resumed, "handling incoming link");
+
+        // Refuse link if authentication failed.
+        if cfg.psk.is_some() && !authenticated {
+            tracing::debug!("refusing unauthorized link");
+            timeout(
+                cfg.link_ping_timeout,
+                LinkMsg::Refused { reason: RefusedReason::Unauthorized }.send(&mut tx),
+            )
             .await??;
+            return Err(IncomingError::Unauthorized);
+        }
 
-        tracing::debug!(?server_id, ?conn_id, ?existing, "handling incoming link");
+        // Refuse link if encryption is required but not enabled by the remote endpoint.
+        if cfg.encryption && !extensions.contains(Extensions::ENCRYPTION) {
+            tracing::debug!("refusing link without encryption");
+            timeout(
+                cfg.link_ping_timeout,
+                LinkMsg::Refused { reason: RefusedReason::EncryptionRequired }.send(&mut tx),
+            )
+            .await??;
+            return Err(IncomingError::EncryptionRequired);
+        }
 
         enum Connection<TX, RX, TAG> {
             Existing {
@@ -456,6 +576,7 @@ where
         }
 
         let mut need_listen_tx_permit = false;
+        let mut ticket = None;
         let connection = loop {
             // Obtain listen queue permit if required.
             let listen_tx_permit = if need_listen_tx_permit {
@@ -467,15 +588,25 @@ where
 
             // Check if link belongs to existing connection.
             let mut inner = self.inner.lock().unwrap();
+            let has_ticket = inner.tickets.contains_key(&conn_id);
             match inner.conns.entry(conn_id) {
                 // Link joins existing connection.
                 Entry::Occupied(ocu) => break Connection::Existing { link_tx: ocu.get().clone() },
 
+                // Link resumes connection that cannot be resumed on this server.
+                Entry::Vacant(_) if resumed && !has_ticket => {
+                    break Connection::Refuse {
+                        reason: RefusedReason::NotResumable,
+                        err: IncomingError::NotResumable,
+                    }
+                }
+
                 // Link belongs to new, incoming connection.
                 Entry::Vacant(vac) if !existing => match listen_tx_permit {
                     Some(Ok(listen_tx_permit)) => {
                         let (link_tx, link_rx) = mpsc::channel(cfg.connect_queue.get());
                         vac.insert(link_tx.clone());
+                        ticket = inner.tickets.remove(&conn_id);
                         break Connection::New { link_tx, link_rx, listen_tx_permit };
                     }
                     Some(Err(_)) => {
@@ -505,6 +636,8 @@ where
                         rx,
                         cfg,
                         remote_cfg,
+                        extensions,
+                        remote_server_id,
                         Direction::Incoming,
                         roundtrip,
                         remote_user_data,
@@ -528,17 +661,20 @@ where
 
             // Link belongs to new, incoming connection.
             Connection::New { link_tx, link_rx, listen_tx_permit } => {
-                let link_int = LinkInt::new(
+                let mut link_int = LinkInt::new(
                     tag,
                     conn_id,
                     tx,
                     rx,
                     cfg.clone(),
                     remote_cfg,
+                    extensions,
+                    remote_server_id,
                     Direction::Incoming,
                     roundtrip,
                     remote_user_data,
                 );
+                link_int.conn_secret = Some(conn_secret);
                 let link = Link::from(&link_int);
                 link_tx.try_send(link_int).unwrap();
 
@@ -547,9 +683,11 @@ where
                     conn_id: OwnedConnId::new(conn_id, closed_conns_tx),
                     server_id: self.server_id,
                     remote_server_id,
+                    extensions,
                     link_tx,
                     link_rx,
                     links: Vec::new(),
+                    ticket,
                 });
 
                 tracing::debug!(?conn_id, "link starts new connection");
@@ -710,6 +848,7 @@ where
         Direction::Outgoing,
         None,
         None,
+        None,
         Vec::new(),
         None,
     );
@@ -1,5 +1,6 @@
 //! Connection and link control.
 
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
 use bytes::Bytes;
 use futures::{Sink, Stream};
 use std::{
@@ -8,20 +9,22 @@ use std::{
     hash::Hash,
     io,
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
+        atomic::{AtomicBool, AtomicU8, Ordering},
+        Arc, RwLock,
     },
     time::Duration,
 };
 use tokio::{
     io::{AsyncRead, AsyncWrite},
-    sync::{mpsc, watch, Mutex},
+    sync::{broadcast, mpsc, oneshot, watch, Mutex},
 };
 use x25519_dalek::{PublicKey, StaticSecret};
 
 use crate::{
-    agg::link_int::LinkInt,
-    cfg::Cfg,
+    agg::{link_int::LinkInt, task::Datagram},
+    alc::{DatagramReceiver, DatagramSender},
+    cfg::{Cfg, Extensions},
+    crypto::LinkAuth,
     exec::time::{error::Elapsed, timeout, Instant},
     id::{ConnId, EncryptedConnId, LinkId, ServerId},
     io::{IoRx, IoTx},
@@ -52,6 +55,16 @@ pub enum AddLinkError {
     ConnectionRefused,
     /// The link was actively refused by the link filter.
     LinkRefused,
+    /// [Encryption](crate::cfg::Cfg::encryption) is required by one endpoint
+    /// but not enabled on the other.
+    EncryptionRequired,
+    /// Authentication using the [pre-shared key](crate::cfg::Cfg::psk) failed.
+    Unauthorized,
+    /// The server cannot resume the session, since it has not been given
+    /// a [resumption ticket](ResumptionTicket) for it.
+    ///
+    /// This will occur when the server is restarted without restoring the state of the session.
+    NotResumable,
 }
 
 impl From<io::Error> for AddLinkError {
@@ -86,6 +99,9 @@ impl fmt::Display for AddLinkError {
             AddLinkError::ConnectionClosed => write!(f, "connection closed"),
             AddLinkError::ConnectionRefused => write!(f, "connection refused"),
             AddLinkError::LinkRefused => write!(f, "link refused"),
+            AddLinkError::EncryptionRequired => write!(f, "encryption required"),
+            AddLinkError::Unauthorized => write!(f, "unauthorized"),
+            AddLinkError::NotResumable => write!(f, "session not resumable by server"),
         }
     }
 }
@@ -99,6 +115,9 @@ impl From<RefusedReason> for AddLinkError {
             RefusedReason::NotListening => Self::NotListening,
             RefusedReason::ConnectionRefused => Self::ConnectionRefused,
             RefusedReason::LinkRefused => Self::LinkRefused,
+            RefusedReason::EncryptionRequired => Self::EncryptionRequired,
+            RefusedReason::Unauthorized => Self::Unauthorized,
+            RefusedReason::NotResumable => Self::NotResumable,
         }
     }
 }
@@ -110,6 +129,118 @@ impl AddLinkError {
     }
 }
 
+/// Error suspending a connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SuspendError {
+    /// The connection cannot be resumed on another server.
+    ///
+    /// Only incoming connections with [session resumption](Cfg::session_resumption)
+    /// enabled and without encryption can be suspended.
+    NotResumable,
+    /// The connection task has terminated.
+    Terminated,
+}
+
+impl fmt::Display for SuspendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotResumable => write!(f, "connection is not resumable"),
+            Self::Terminated => write!(f, "connection task terminated"),
+        }
+    }
+}
+
+impl std::error::Error for SuspendError {}
+
+impl From<SuspendError> for io::Error {
+    fn from(err: SuspendError) -> Self {
+        io::Error::new(io::ErrorKind::Unsupported, err)
+    }
+}
+
+/// State of a suspended incoming connection for resuming it on another server.
+///
+/// It is obtained by [suspending](Control::suspend) the connection and must be
+/// [passed to the server](crate::connect::Server::resume) the client reconnects to.
+#[derive(Clone)]
+pub struct ResumptionTicket {
+    /// Connection id.
+    pub(crate) conn_id: ConnId,
+    /// Number of data messages passed to the receiver.
+    pub(crate) received: u64,
+    /// Number of sent data messages consumed by the remote endpoint before `unconsumed`.
+    pub(crate) sent_consumed: u64,
+    /// Sent data messages that have not been consumed by the remote endpoint.
+    pub(crate) unconsumed: Vec<Bytes>,
+    /// Whether the sender was closed.
+    pub(crate) send_finished: bool,
+}
+
+impl fmt::Debug for ResumptionTicket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ResumptionTicket")
+            .field("id", &self.conn_id)
+            .field("received", &self.received)
+            .field("sent_consumed", &self.sent_consumed)
+            .field("unconsumed", &self.unconsumed.len())
+            .field("send_finished", &self.send_finished)
+            .finish()
+    }
+}
+
+impl ResumptionTicket {
+    /// Format version of the serialized ticket.
+    const VERSION: u8 = 1;
+
+    /// The connection id.
+    pub fn id(&self) -> ConnId {
+        self.conn_id
+    }
+
+    /// Serializes the ticket, for example to pass it to a restarted server process.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = Vec::new();
+        buf.write_u8(Self::VERSION).unwrap();
+        buf.write_u128::<BE>(self.conn_id.0).unwrap();
+        buf.write_u64::<BE>(self.received).unwrap();
+        buf.write_u64::<BE>(self.sent_consumed).unwrap();
+        buf.write_u8(self.send_finished.into()).unwrap();
+        buf.write_u32::<BE>(self.unconsumed.len() as u32).unwrap();
+        for data in &self.unconsumed {
+            buf.write_u32::<BE>(data.len() as u32).unwrap();
+            buf.extend_from_slice(data);
+        }
+        buf.into()
+    }
+
+    /// Deserializes a ticket serialized by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(mut buf: &[u8]) -> Result<Self, io::Error> {
+        let version = buf.read_u8()?;
+        if version != Self::VERSION {
+            return Err(protocol_err!("unsupported resumption ticket version {version}"));
+        }
+
+        let conn_id = ConnId(buf.read_u128::<BE>()?);
+        let received = buf.read_u64::<BE>()?;
+        let sent_consumed = buf.read_u64::<BE>()?;
+        let send_finished = buf.read_u8()? != 0;
+        let count = buf.read_u32::<BE>()?;
+
+        let mut unconsumed = Vec::new();
+        for _ in 0..count {
+            let len = buf.read_u32::<BE>()? as usize;
+            if buf.len() < len {
+                return Err(protocol_err!("resumption ticket is truncated"));
+            }
+            let (data, rest) = buf.split_at(len);
+            unconsumed.push(Bytes::copy_from_slice(data));
+            buf = rest;
+        }
+
+        Ok(Self { conn_id, received, sent_consumed, unconsumed, send_finished })
+    }
+}
+
 /// Direction of a connection or link.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Direction {
@@ -152,11 +283,15 @@ pub struct Control<TX, RX, TAG> {
     pub(crate) direction: Direction,
     pub(crate) terminate_tx: mpsc::Sender<()>,
     pub(crate) connected: Arc<AtomicBool>,
+    pub(crate) extensions: Arc<RwLock<Option<Extensions>>>,
     pub(crate) link_tx: mpsc::Sender<LinkInt<TX, RX, TAG>>,
     pub(crate) links_rx: watch::Receiver<Vec<Link<TAG>>>,
     pub(crate) stats_rx: watch::Receiver<Stats>,
     pub(crate) server_changed_tx: mpsc::Sender<()>,
     pub(crate) result_rx: watch::Receiver<Result<(), TaskError>>,
+    pub(crate) datagram_tx: mpsc::Sender<Datagram>,
+    pub(crate) datagram_rx: Arc<broadcast::Receiver<Bytes>>,
+    pub(crate) suspend_tx: mpsc::Sender<oneshot::Sender<Result<ResumptionTicket, SuspendError>>>,
 }
 
 impl<TX, RX, TAG> Clone for Control<TX, RX, TAG> {
@@ -169,11 +304,15 @@ impl<TX, RX, TAG> Clone for Control<TX, RX, TAG> {
             direction: self.direction,
             terminate_tx: self.terminate_tx.clone(),
             connected: self.connected.clone(),
+            extensions: self.extensions.clone(),
             link_tx: self.link_tx.clone(),
             links_rx: self.links_rx.clone(),
             stats_rx: self.stats_rx.clone(),
             server_changed_tx: self.server_changed_tx.clone(),
             result_rx: self.result_rx.clone(),
+            datagram_tx: self.datagram_tx.clone(),
+            datagram_rx: self.datagram_rx.clone(),
+            suspend_tx: self.suspend_tx.clone(),
         }
     }
 }
@@ -240,6 +379,17 @@ impl<TX, RX, TAG> Control<TX, RX, TAG> {
         &self.cfg
     }
 
+    /// The protocol extensions used by the connection.
+    ///
+    /// These are the extensions offered by both endpoints.
+    /// `None` if the connection is not yet established.
+    ///
+    /// This may change when the session is [resumed](Cfg::session_resumption)
+    /// on a different server.
+    pub fn extensions(&self) -> Option<Extensions> {
+        *self.extensions.read().unwrap()
+    }
+
     /// Forcefully terminates the connection.
     ///
     /// The connection task is terminated and the remote endpoint is
@@ -256,6 +406,24 @@ impl<TX, RX, TAG> Control<TX, RX, TAG> {
         let _ = self.terminate_tx.try_send(());
     }
 
+    /// Suspends the connection for resuming it on another server.
+    ///
+    /// This requires that [session resumption](Cfg::session_resumption) is enabled on both endpoints
+    /// and can only be used for incoming connections.
+    /// The connection task ends with [`TaskError::Suspended`] and all links are closed without
+    /// notifying the client, which will resume the session once it connects to the server
+    /// that the returned ticket has been [passed to](crate::connect::Server::resume).
+    ///
+    /// Data that has been passed to the receiver counts as delivered, thus the application
+    /// must process all data remaining in the receiver.
+    /// Data that the sender has not yet processed is lost; [flush](crate::alc::Sender::flush)
+    /// the sender before suspending the connection to avoid this.
+    pub async fn suspend(&self) -> Result<ResumptionTicket, SuspendError> {
+        let (ticket_tx, ticket_rx) = oneshot::channel();
+        self.suspend_tx.send(ticket_tx).await.map_err(|_| SuspendError::Terminated)?;
+        ticket_rx.await.map_err(|_| SuspendError::Terminated)?
+    }
+
     /// Returns whether the connection has been terminated.
     pub fn is_terminated(&self) -> bool {
         self.link_tx.is_closed()
@@ -300,6 +468,22 @@ impl<TX, RX, TAG> Control<TX, RX, TAG> {
     pub async fn stats_changed(&mut self) {
         let _ = self.stats_rx.changed().await;
     }
+
+    /// Gets a sender of unreliable datagrams over the connection.
+    ///
+    /// Datagrams are sent alongside the data of the [channel](crate::alc::Channel),
+    /// but are neither acknowledged nor resent.
+    /// This requires that both endpoints offer the [datagram extension](Extensions::DATAGRAM).
+    pub fn datagram_sender(&self) -> DatagramSender {
+        DatagramSender::new(self.cfg.clone(), self.conn_id, self.datagram_tx.clone(), self.extensions.clone())
+    }
+
+    /// Gets a receiver of unreliable datagrams sent by the remote endpoint.
+    ///
+    /// The receiver obtains all datagrams that arrive after it has been created.
+    pub fn datagram_receiver(&self) -> DatagramReceiver {
+        DatagramReceiver::new(self.conn_id, self.datagram_rx.resubscribe())
+    }
 }
 
 impl<TX, RX, TAG> Control<TX, RX, TAG>
@@ -329,13 +513,14 @@ where
         assert!(user_data.len() <= u16::MAX as usize, "user_data is too big");
 
         // Perform protocol handshake.
-        let (remote_cfg, roundtrip, remote_user_data) = timeout(self.cfg.link_ping_timeout, async {
+        let mut resumed_from = None;
+        let res = timeout(self.cfg.link_ping_timeout, async {
             let random: [u8; 32] = rand::random();
             let client_secret = StaticSecret::from(random);
             let client_public_key = PublicKey::from(&client_secret);
 
             let LinkMsg::Welcome {
-                extensions: _,
+                extensions: remote_extensions,
                 public_key: server_public_key,
                 server_id,
                 cfg,
@@ -345,11 +530,36 @@ where
                 return Err::<_, AddLinkError>(protocol_err!("expected Welcome message").into());
             };
 
+            let extensions = self.cfg.offered_extensions() & Extensions::from_bits_truncate(remote_extensions);
+            if self.cfg.psk.is_some() && !extensions.contains(Extensions::AUTHENTICATION) {
+                return Err(AddLinkError::Unauthorized);
+            }
+            if self.cfg.encryption && !extensions.contains(Extensions::ENCRYPTION) {
+                return Err(AddLinkError::EncryptionRequired);
+            }
+
             let shared_secret = client_secret.diffie_hellman(&server_public_key);
 
             {
                 let mut remote_server_id = self.remote_server_id.lock().await;
-                match &*remote_server_id {
+                match &mut *remote_server_id {
+                    Some(remote_server_id)
+                        if *remote_server_id != server_id
+                            && self.cfg.session_resumption
+                            && !self.cfg.encryption =>
+                    {
+                        // Only resume once all links to the current server have failed.
+                        if !self.links_rx.borrow().is_empty() {
+                            return Err(AddLinkError::ServerIdMismatch {
+                                expected: *remote_server_id,
+                                present: server_id,
+                            });
+                        }
+
+                        // Resume session on new server by establishing a new connection on it.
+                        resumed_from = Some(*remote_server_id);
+                        *remote_server_id = server_id;
+                    }
                     Some(remote_server_id) if *remote_server_id != server_id => {
                         if self.cfg.disconnect_on_server_id_mismatch {
                             let _ = self.server_changed_tx.try_send(());
@@ -368,40 +578,69 @@ where
 
             let start = Instant::now();
             LinkMsg::Connect {
-                extensions: 0,
+                extensions: self.cfg.offered_extensions().bits(),
                 public_key: client_public_key,
                 server_id: self.server_id,
                 connection_id: EncryptedConnId::new(self.conn_id, &shared_secret),
-                existing_connection: self.connected.load(Ordering::Acquire),
+                existing_connection: resumed_from.is_none() && self.connected.load(Ordering::Acquire),
+                resumed_connection: resumed_from.is_some(),
                 user_data: user_data.to_vec(),
                 cfg: (&*self.cfg).into(),
             }
             .send(&mut tx)
             .await?;
 
+            if let (Some(psk), true) = (&self.cfg.psk, extensions.contains(Extensions::AUTHENTICATION)) {
+                let auth = LinkAuth::new(psk, &server_public_key, &client_public_key, &shared_secret);
+                LinkMsg::Authenticate { proof: auth.proof(Direction::Outgoing) }.send(&mut tx).await?;
+
+                match LinkMsg::recv(&mut rx).await? {
+                    LinkMsg::Authenticate { proof } if auth.verify(Direction::Incoming, &proof) => (),
+                    LinkMsg::Authenticate { .. } => return Err(AddLinkError::Unauthorized),
+                    LinkMsg::Refused { reason } => return Err(reason.into()),
+                    _ => return Err(protocol_err!("expected Authenticate or Refused message").into()),
+                }
+            }
+
             match LinkMsg::recv(&mut rx).await? {
-                LinkMsg::Accepted => {
+                msg @ (LinkMsg::Accepted | LinkMsg::Established) => {
+                    let mut current_extensions = self.extensions.write().unwrap();
+                    if current_extensions.is_none() || resumed_from.is_some() {
+                        *current_extensions = Some(extensions);
+                    }
+                    drop(current_extensions);
                     self.connected.store(true, Ordering::Release);
-                    Ok((cfg, start.elapsed(), remote_user_data))
+                    // The server tells which link established the connection.
+                    let conn_secret = matches!(msg, LinkMsg::Established).then(|| *shared_secret.as_bytes());
+                    Ok((cfg, extensions, server_id, start.elapsed(), remote_user_data, conn_secret))
                 }
                 LinkMsg::Refused { reason } => Err(reason.into()),
-                _ => Err(protocol_err!("expected Accepted or Refused message").into()),
+                _ => Err(protocol_err!("expected Accepted, Established or Refused message").into()),
             }
         })
-        .await??;
+        .await;
+
+        // Keep the previous server if resumption failed.
+        if let (Err(_) | Ok(Err(_)), Some(previous)) = (&res, resumed_from) {
+            *self.remote_server_id.lock().await = Some(previous);
+        }
+        let (remote_cfg, extensions, server_id, roundtrip, remote_user_data, conn_secret) = res??;
 
         // Create link.
-        let link_int = LinkInt::new(
+        let mut link_int = LinkInt::new(
             tag,
             self.conn_id,
             tx,
             rx,
             self.cfg.clone(),
             remote_cfg,
+            extensions,
+            Some(server_id),
             Direction::Outgoing,
             roundtrip,
             remote_user_data,
         );
+        link_int.conn_secret = conn_secret;
         let link = Link::from(&link_int);
         self.link_tx.send(link_int).await.map_err(|_| AddLinkError::ConnectionClosed)?;
 
@@ -483,6 +722,8 @@ pub struct Link<TAG> {
     pub(crate) blocked_changed_tx: mpsc::Sender<()>,
     pub(crate) blocked_changed_rx: watch::Receiver<()>,
     pub(crate) remotely_blocked: Arc<AtomicBool>,
+    pub(crate) priority: Arc<AtomicU8>,
+    pub(crate) priority_changed_tx: mpsc::Sender<()>,
     pub(crate) not_working_rx: watch::Receiver<Option<(Instant, NotWorkingReason)>>,
 }
 
@@ -502,6 +743,8 @@ impl<TAG> Clone for Link<TAG> {
             blocked_changed_tx: self.blocked_changed_tx.clone(),
             blocked_changed_rx: self.blocked_changed_rx.clone(),
             remotely_blocked: self.remotely_blocked.clone(),
+            priority: self.priority.clone(),
+            priority_changed_tx: self.priority_changed_tx.clone(),
             not_working_rx: self.not_working_rx.clone(),
         }
     }
@@ -630,6 +873,29 @@ impl<TAG> Link<TAG> {
         let _ = self.blocked_changed_tx.try_send(());
     }
 
+    /// Returns the priority of the link.
+    ///
+    /// See [`set_priority`](Self::set_priority) for details.
+    pub fn priority(&self) -> u8 {
+        self.priority.load(Ordering::SeqCst)
+    }
+
+    /// Sets the priority of the link.
+    ///
+    /// A lower value means a higher priority, with zero being the highest and default priority.
+    /// Data is only sent over a link when all links with a higher priority are
+    /// not working, i.e. they are unconfirmed or blocked.
+    /// This allows to keep backup links connected and tested while they carry no data
+    /// during normal operation.
+    ///
+    /// Unlike [blocking](Self::set_blocked) the priority is local to this endpoint and
+    /// not signalled to the remote endpoint.
+    /// It thus only affects data sent from this endpoint.
+    pub fn set_priority(&self, priority: u8) {
+        self.priority.store(priority, Ordering::SeqCst);
+        let _ = self.priority_changed_tx.try_send(());
+    }
+
     /// Returns whether the link is blocked by the remote endpoint.
     pub fn is_remotely_blocked(&self) -> bool {
         self.remotely_blocked.load(Ordering::SeqCst)
@@ -748,6 +1014,42 @@ pub struct LinkStats {
     pub hangs: usize,
     /// Statistics over time intervals specified in the [configuration](crate::cfg::Cfg::stats_intervals).
     pub time_stats: Vec<LinkIntervalStats>,
+    /// One-way delay from the local to the remote endpoint.
+    ///
+    /// Only available if the [timestamps extension](crate::cfg::Extensions::TIMESTAMPS)
+    /// is supported by both endpoints and the link has been pinged.
+    pub forward_delay: Option<OneWayDelay>,
+    /// One-way delay from the remote to the local endpoint.
+    ///
+    /// Only available if the [timestamps extension](crate::cfg::Extensions::TIMESTAMPS)
+    /// is supported by both endpoints and the link has been pinged.
+    pub backward_delay: Option<OneWayDelay>,
+    /// Whether the link was detected to share a bottleneck with other links of the connection.
+    ///
+    /// Only detected if [coupled congestion control](crate::cfg::Cfg::link_coupled_congestion_control)
+    /// is enabled.
+    pub shared_bottleneck: bool,
+}
+
+/// One-way delay of a link in one direction.
+///
+/// Since the clocks of both endpoints are not synchronized, the absolute one-way
+/// delay cannot be measured.
+/// Instead it is given relative to the lowest delay in the same direction that
+/// has recently been observed on any link of the connection.
+///
+/// Delays are measured using timestamped pings, thus the link ping
+/// [configuration](crate::cfg::Cfg::link_ping) determines how often they are updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub struct OneWayDelay {
+    /// Delay relative to the lowest delay in the same direction on any link of the connection.
+    pub relative: Duration,
+    /// Change of the delay in microseconds per second.
+    ///
+    /// A positive value indicates that a queue is building up along the path.
+    /// Drift between the clocks of both endpoints contributes a small constant offset.
+    pub trend: i64,
 }
 
 /// Reason why a link is not working.
@@ -0,0 +1,203 @@
+//! End-to-end encryption of data and authentication of links.
+
+use byteorder::{ByteOrder, BE};
+use bytes::Bytes;
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, Key, KeyInit, Nonce};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::io;
+use x25519_dalek::{PublicKey, SharedSecret};
+
+use crate::{cfg::PreSharedKey, control::Direction, protocol_err};
+
+/// Diffie-Hellman shared secret of the link that established a connection.
+pub(crate) type ConnSecret = [u8; 32];
+
+/// Authenticated encryption of data sent over a connection.
+///
+/// Separate keys are used for each direction.
+/// They are derived from the Diffie-Hellman shared secret of the link that established
+/// the connection and the optional pre-shared key.
+///
+/// Each encrypted message uses a nonce derived from a counter, which is incremented
+/// for each message.
+/// Thus messages must be decrypted in the order they were encrypted.
+///
+/// Datagrams may be lost or reordered and thus use a separate nonce space,
+/// derived from the datagram number that is transmitted alongside each datagram.
+/// A [replay window](ReplayWindow) rejects datagrams that have already been received.
+pub(crate) struct Cipher {
+    tx: ChaCha20Poly1305,
+    tx_counter: u64,
+    rx: ChaCha20Poly1305,
+    rx_counter: u64,
+    rx_datagrams: ReplayWindow,
+}
+
+impl Cipher {
+    /// Size of the authentication tag appended to each encrypted message.
+    pub const TAG_SIZE: usize = 16;
+
+    const INFO_CLIENT_TO_SERVER: &'static [u8] = b"aggligator client to server";
+    const INFO_SERVER_TO_CLIENT: &'static [u8] = b"aggligator server to client";
+
+    /// Creates the cipher for the specified end of a connection.
+    pub fn new(conn_secret: &ConnSecret, psk: Option<&PreSharedKey>, direction: Direction) -> Self {
+        let hkdf = Hkdf::<Sha256>::new(psk.map(|psk| psk.as_bytes()), conn_secret);
+
+        let key = |info: &[u8]| {
+            let mut key = Key::default();
+            hkdf.expand(info, &mut key).expect("invalid key length");
+            ChaCha20Poly1305::new(&key)
+        };
+        let client_to_server = key(Self::INFO_CLIENT_TO_SERVER);
+        let server_to_client = key(Self::INFO_SERVER_TO_CLIENT);
+
+        let (tx, rx) = match direction {
+            Direction::Outgoing => (client_to_server, server_to_client),
+            Direction::Incoming => (server_to_client, client_to_server),
+        };
+
+        Self { tx, tx_counter: 0, rx, rx_counter: 0, rx_datagrams: ReplayWindow::default() }
+    }
+
+    fn nonce(counter: u64) -> Nonce {
+        let mut nonce = Nonce::default();
+        BE::write_u64(&mut nonce[4..], counter);
+        nonce
+    }
+
+    fn datagram_nonce(number: u64) -> Nonce {
+        let mut nonce = Self::nonce(number);
+        nonce[0] = 1;
+        nonce
+    }
+
+    /// Encrypts the next message.
+    pub fn encrypt(&mut self, data: &[u8]) -> Bytes {
+        let nonce = Self::nonce(self.tx_counter);
+        self.tx_counter += 1;
+        self.tx.encrypt(&nonce, data).expect("encryption failed").into()
+    }
+
+    /// Decrypts the next message.
+    pub fn decrypt(&mut self, data: &[u8]) -> Result<Bytes, io::Error> {
+        let nonce = Self::nonce(self.rx_counter);
+        self.rx_counter += 1;
+        let data = self.rx.decrypt(&nonce, data).map_err(|_| protocol_err!("decryption of data failed"))?;
+        Ok(data.into())
+    }
+
+    /// Encrypts the datagram with the specified number.
+    pub fn encrypt_datagram(&self, number: u64, data: &[u8]) -> Bytes {
+        self.tx.encrypt(&Self::datagram_nonce(number), data).expect("encryption failed").into()
+    }
+
+    /// Decrypts the datagram with the specified number.
+    ///
+    /// Fails if a datagram with the same number has already been received.
+    pub fn decrypt_datagram(&mut self, number: u64, data: &[u8]) -> Result<Bytes, io::Error> {
+        if !self.rx_datagrams.is_fresh(number) {
+            return Err(protocol_err!("datagram was replayed or is too old"));
+        }
+
+        let data = self
+            .rx
+            .decrypt(&Self::datagram_nonce(number), data)
+            .map_err(|_| protocol_err!("decryption of datagram failed"))?;
+
+        // Only authentic datagrams may advance the window.
+        self.rx_datagrams.insert(number);
+        Ok(data.into())
+    }
+}
+
+/// Sliding window over the numbers of received datagrams.
+///
+/// Bit `i` of the bitmap is set if datagram `top - 1 - i` has been received.
+/// Datagrams older than the window are rejected, since it is unknown whether
+/// they have been received.
+#[derive(Debug, Default)]
+struct ReplayWindow {
+    /// One more than the highest received datagram number.
+    top: u64,
+    bitmap: u128,
+}
+
+impl ReplayWindow {
+    /// Number of datagrams covered by the window.
+    const SIZE: u64 = u128::BITS as u64;
+
+    /// Whether the datagram with the specified number has not been received yet.
+    fn is_fresh(&self, number: u64) -> bool {
+        if number >= self.top {
+            return true;
+        }
+
+        let age = self.top - 1 - number;
+        age < Self::SIZE && self.bitmap & (1 << age) == 0
+    }
+
+    /// Marks the datagram with the specified number as received.
+    fn insert(&mut self, number: u64) {
+        if number >= self.top {
+            let shift = number - self.top + 1;
+            self.bitmap = if shift >= Self::SIZE { 0 } else { self.bitmap << shift };
+            self.bitmap |= 1;
+            self.top = number + 1;
+        } else {
+            self.bitmap |= 1 << (self.top - 1 - number);
+        }
+    }
+}
+
+/// Proof of knowledge of the pre-shared key sent when establishing a link.
+pub(crate) type AuthProof = [u8; 32];
+
+/// Authentication of a link using a pre-shared key.
+///
+/// Each endpoint proves knowledge of the pre-shared key by sending a message authentication
+/// code over the public keys and the shared secret of the Diffie-Hellman exchange of the link.
+/// This binds the proof to the link and thus prevents replay of proofs.
+pub(crate) struct LinkAuth<'a> {
+    psk: &'a PreSharedKey,
+    server_public_key: &'a PublicKey,
+    client_public_key: &'a PublicKey,
+    shared_secret: &'a SharedSecret,
+}
+
+impl<'a> LinkAuth<'a> {
+    const LABEL_CLIENT: &'static [u8] = b"aggligator client proof";
+    const LABEL_SERVER: &'static [u8] = b"aggligator server proof";
+
+    /// Creates the link authentication.
+    pub fn new(
+        psk: &'a PreSharedKey, server_public_key: &'a PublicKey, client_public_key: &'a PublicKey,
+        shared_secret: &'a SharedSecret,
+    ) -> Self {
+        Self { psk, server_public_key, client_public_key, shared_secret }
+    }
+
+    fn mac(&self, direction: Direction) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.psk.as_bytes()).expect("invalid key length");
+        mac.update(match direction {
+            Direction::Outgoing => Self::LABEL_CLIENT,
+            Direction::Incoming => Self::LABEL_SERVER,
+        });
+        mac.update(self.server_public_key.as_bytes());
+        mac.update(self.client_public_key.as_bytes());
+        mac.update(self.shared_secret.as_bytes());
+        mac
+    }
+
+    /// Proof of the endpoint with the specified direction.
+    pub fn proof(&self, direction: Direction) -> AuthProof {
+        self.mac(direction).finalize().into_bytes().into()
+    }
+
+    /// Verifies the proof of the remote endpoint having the specified direction.
+    pub fn verify(&self, direction: Direction, proof: &AuthProof) -> bool {
+        self.mac(direction).verify_slice(proof).is_ok()
+    }
+}
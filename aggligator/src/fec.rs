@@ -0,0 +1,245 @@
+//! Forward error correction of data messages using XOR parity.
+//!
+//! Consecutive data messages are grouped and the parity of each group
+//! is sent unreliably.
+//! The receiver can rebuild a single missing message of a group from its parity
+//! and the other messages of the group, without waiting for it to be resent.
+//! Other reliable messages are not protected and end the current group.
+
+use byteorder::{ByteOrder, BE};
+use bytes::Bytes;
+use std::{collections::VecDeque, io, num::NonZeroU8};
+
+use crate::{msg::ReliableMsg, protocol_err, seq::Seq};
+
+const KIND_DATA: u8 = 0;
+const KIND_COMPRESSED_DATA: u8 = 1;
+
+/// Size of kind and length header of an encoded message.
+const HEADER_SIZE: usize = 5;
+
+/// Kind and payload of a data message or `None` if the message is not protected.
+fn data_payload(msg: &ReliableMsg) -> Option<(u8, &[u8])> {
+    match msg {
+        ReliableMsg::Data(data) => Some((KIND_DATA, data)),
+        ReliableMsg::CompressedData(data) => Some((KIND_COMPRESSED_DATA, data)),
+        _ => None,
+    }
+}
+
+/// XORs the encoded form of a data message into the parity buffer.
+///
+/// The encoded form consists of the message kind, the payload length and the payload.
+/// The parity buffer is extended with zeros as necessary.
+fn xor_into(parity: &mut Vec<u8>, kind: u8, payload: &[u8]) {
+    let len = HEADER_SIZE + payload.len();
+    if parity.len() < len {
+        parity.resize(len, 0);
+    }
+
+    parity[0] ^= kind;
+    for (p, b) in parity[1..HEADER_SIZE].iter_mut().zip((payload.len() as u32).to_be_bytes()) {
+        *p ^= b;
+    }
+    for (p, b) in parity[HEADER_SIZE..].iter_mut().zip(payload) {
+        *p ^= b;
+    }
+}
+
+/// Decodes a data message rebuilt from parity.
+fn decode(buf: &[u8]) -> Result<ReliableMsg, io::Error> {
+    if buf.len() < HEADER_SIZE {
+        return Err(protocol_err!("parity too short"));
+    }
+    let len = BE::read_u32(&buf[1..HEADER_SIZE]) as usize;
+    let Some(payload) = buf.get(HEADER_SIZE..HEADER_SIZE.saturating_add(len)) else {
+        return Err(protocol_err!("invalid length in parity"));
+    };
+
+    let msg = match buf[0] {
+        KIND_DATA => ReliableMsg::Data(Bytes::copy_from_slice(payload)),
+        KIND_COMPRESSED_DATA => ReliableMsg::CompressedData(Bytes::copy_from_slice(payload)),
+        other => return Err(protocol_err!("invalid message kind {other} in parity")),
+    };
+    Ok(msg)
+}
+
+/// Parity of a group of consecutive data messages.
+#[derive(Debug, Clone)]
+pub(crate) struct Parity {
+    /// Sequence number of first message in group.
+    pub first: Seq,
+    /// Number of messages in group.
+    pub count: u8,
+    /// XOR of the encoded messages.
+    pub data: Bytes,
+}
+
+/// Group of sent messages whose parity is being calculated.
+struct EncoderGroup {
+    first: Seq,
+    count: u8,
+    parity: Vec<u8>,
+}
+
+/// Calculates the parity of sent data messages.
+pub(crate) struct FecEncoder {
+    group_size: u8,
+    group: Option<EncoderGroup>,
+    parities: VecDeque<Parity>,
+}
+
+impl FecEncoder {
+    /// Maximum number of parities queued for sending.
+    ///
+    /// Older parities are discarded, since they lose their usefulness over time.
+    const MAX_QUEUED: usize = 16;
+
+    /// Creates a new encoder using the specified number of messages per group.
+    pub fn new(group_size: NonZeroU8) -> Self {
+        Self { group_size: group_size.get(), group: None, parities: VecDeque::new() }
+    }
+
+    /// Adds a data message sent for the first time.
+    ///
+    /// Messages must be added in sequence.
+    /// Other messages are ignored, which ends the current group.
+    pub fn add(&mut self, seq: Seq, msg: &ReliableMsg) {
+        if self.group.as_ref().is_some_and(|group| group.first + u32::from(group.count) != seq) {
+            self.finish();
+        }
+
+        let Some((kind, payload)) = data_payload(msg) else { return };
+        let group = self.group.get_or_insert_with(|| EncoderGroup { first: seq, count: 0, parity: Vec::new() });
+        xor_into(&mut group.parity, kind, payload);
+        group.count += 1;
+
+        if group.count >= self.group_size {
+            self.finish();
+        }
+    }
+
+    /// Finishes the current group, even if it is incomplete, and queues its parity for sending.
+    pub fn finish(&mut self) {
+        let Some(EncoderGroup { first, count, parity }) = self.group.take() else { return };
+
+        if self.parities.len() >= Self::MAX_QUEUED {
+            self.parities.pop_front();
+        }
+        self.parities.push_back(Parity { first, count, data: parity.into() });
+    }
+
+    /// Whether a parity is queued for sending.
+    pub fn has_parity(&self) -> bool {
+        !self.parities.is_empty()
+    }
+
+    /// Whether an incomplete group is present.
+    pub fn has_group(&self) -> bool {
+        self.group.is_some()
+    }
+
+    /// Removes the next parity for sending.
+    pub fn pop(&mut self) -> Option<Parity> {
+        self.parities.pop_front()
+    }
+}
+
+/// Rebuilds missing received data messages from received parity.
+#[derive(Default)]
+pub(crate) struct FecDecoder {
+    /// Received parities of groups that are not yet completely received.
+    parities: VecDeque<Parity>,
+    /// Recently forwarded data messages, as transmitted.
+    history: VecDeque<(Seq, ReliableMsg)>,
+    /// Largest group size seen.
+    max_group_size: usize,
+}
+
+impl FecDecoder {
+    /// Maximum number of parities kept.
+    const MAX_PARITIES: usize = 64;
+
+    /// Adds a received parity.
+    pub fn add_parity(&mut self, parity: Parity) -> Result<(), io::Error> {
+        if parity.count == 0 {
+            return Err(protocol_err!("parity of empty group"));
+        }
+        self.max_group_size = self.max_group_size.max(parity.count.into());
+
+        if self.parities.len() >= Self::MAX_PARITIES {
+            self.parities.pop_front();
+        }
+        self.parities.push_back(parity);
+
+        Ok(())
+    }
+
+    /// Whether no parity is available.
+    pub fn is_empty(&self) -> bool {
+        self.parities.is_empty()
+    }
+
+    /// Records a message that has been forwarded for consumption.
+    ///
+    /// A data message may be required for rebuilding a later message of the same group.
+    pub fn forwarded(&mut self, seq: Seq, msg: &ReliableMsg) {
+        if self.max_group_size == 0 || data_payload(msg).is_none() {
+            return;
+        }
+
+        self.history.push_back((seq, msg.clone()));
+        while self.history.len() > self.max_group_size {
+            self.history.pop_front();
+        }
+    }
+
+    /// Attempts to rebuild a missing message.
+    ///
+    /// `rx_seq` is the sequence number of the next message to forward and
+    /// `received` provides received messages that have not been forwarded yet.
+    /// Parities of completely received groups are discarded.
+    pub fn recover<'a>(
+        &mut self, rx_seq: Seq, received: impl Fn(Seq) -> Option<&'a ReliableMsg>,
+    ) -> Result<Option<(Seq, ReliableMsg)>, io::Error> {
+        let mut idx = 0;
+        while let Some(parity) = self.parities.get(idx) {
+            let msg = |seq: Seq| {
+                if seq < rx_seq {
+                    self.history.iter().find(|(s, _)| *s == seq).map(|(_, msg)| msg)
+                } else {
+                    received(seq)
+                }
+            };
+            let seqs = (0..parity.count).map(|n| parity.first + u32::from(n));
+
+            // A forwarded message that is not in the history cannot be used anymore.
+            let lost = seqs.clone().any(|seq| seq < rx_seq && msg(seq).is_none());
+            let mut missing = seqs.clone().filter(|&seq| msg(seq).is_none());
+
+            match (missing.next(), missing.next()) {
+                _ if lost => {
+                    self.parities.remove(idx);
+                }
+                (None, _) => {
+                    self.parities.remove(idx);
+                }
+                (Some(seq), None) => {
+                    let mut buf = parity.data.to_vec();
+                    for msg in seqs.filter_map(msg) {
+                        let Some((kind, payload)) = data_payload(msg) else {
+                            return Err(protocol_err!("parity covers message that is not data"));
+                        };
+                        xor_into(&mut buf, kind, payload);
+                    }
+
+                    self.parities.remove(idx);
+                    return Ok(Some((seq, decode(&buf)?)));
+                }
+                (Some(_), Some(_)) => idx += 1,
+            }
+        }
+
+        Ok(None)
+    }
+}
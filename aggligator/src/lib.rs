@@ -52,12 +52,22 @@
 //!
 //! # Connection security
 //!
-//! Aggligator does *not* perform cryptographic authentication of the remote endpoint or encryption of data.
+//! By default, Aggligator does *not* perform cryptographic authentication of the remote endpoint
+//! or encryption of data.
 //! If you are sending sensitive data over an untrusted connection you should encrypt it
 //! and authenticate the remote endpoint, for example using [TLS].
 //! The implementation provided in the [tokio-rustls] crate works nicely with Aggligator.
 //!
-//! However, the unique identifier of each connection is encrypted using a shared
+//! Alternatively, [end-to-end encryption](cfg::Cfg::encryption) of data can be enabled
+//! in the configuration.
+//! Data is then encrypted once, before it is distributed over the links, and thus
+//! no TLS session is required per link.
+//!
+//! Links can be mutually authenticated using a [pre-shared key](cfg::Cfg::psk).
+//! Links from remote endpoints that do not know the pre-shared key are then refused.
+//! When combined with encryption this also protects against man-in-the-middle attacks.
+//!
+//! In any case, the unique identifier of each connection is encrypted using a shared
 //! secret that is exchanged via [Diffie-Hellman key exchange].
 //! Thus, an eavesdropper cannot inject fake links to an existing connection by using
 //! the spoofed connection identifier.
@@ -97,6 +107,8 @@ pub mod alc;
 pub mod cfg;
 pub mod connect;
 pub mod control;
+mod crypto;
+mod fec;
 pub mod id;
 pub mod io;
 mod msg;
@@ -108,6 +120,7 @@ pub mod transport;
 #[cfg_attr(docsrs, doc(cfg(feature = "dump")))]
 pub use agg::dump;
 
+pub use agg::scheduler;
 pub use agg::task::{Task, TaskError};
 
 /// Link aggregator protocol error.
@@ -8,6 +8,7 @@ use x25519_dalek::PublicKey;
 
 use crate::{
     cfg::ExchangedCfg,
+    crypto::AuthProof,
     id::{EncryptedConnId, ServerId},
     protocol_err,
     seq::Seq,
@@ -24,6 +25,12 @@ pub(crate) enum RefusedReason {
     ConnectionRefused,
     /// The incoming link was refused by the link filter.
     LinkRefused,
+    /// Encryption is required but was not offered.
+    EncryptionRequired,
+    /// Authentication failed.
+    Unauthorized,
+    /// The server has no resumption ticket for the resumed connection.
+    NotResumable,
 }
 
 impl RefusedReason {
@@ -31,6 +38,9 @@ impl RefusedReason {
     const ID_NOT_LISTENING: u8 = 2;
     const ID_CONNECTION_REFUSED: u8 = 3;
     const ID_LINK_REFUSED: u8 = 4;
+    const ID_ENCRYPTION_REQUIRED: u8 = 5;
+    const ID_UNAUTHORIZED: u8 = 6;
+    const ID_NOT_RESUMABLE: u8 = 7;
 }
 
 impl From<RefusedReason> for u8 {
@@ -40,6 +50,9 @@ impl From<RefusedReason> for u8 {
             RefusedReason::NotListening => RefusedReason::ID_NOT_LISTENING,
             RefusedReason::ConnectionRefused => RefusedReason::ID_CONNECTION_REFUSED,
             RefusedReason::LinkRefused => RefusedReason::ID_LINK_REFUSED,
+            RefusedReason::EncryptionRequired => RefusedReason::ID_ENCRYPTION_REQUIRED,
+            RefusedReason::Unauthorized => RefusedReason::ID_UNAUTHORIZED,
+            RefusedReason::NotResumable => RefusedReason::ID_NOT_RESUMABLE,
         }
     }
 }
@@ -52,6 +65,9 @@ impl TryFrom<u8> for RefusedReason {
             Self::ID_NOT_LISTENING => Ok(Self::NotListening),
             Self::ID_CONNECTION_REFUSED => Ok(Self::ConnectionRefused),
             Self::ID_LINK_REFUSED => Ok(Self::LinkRefused),
+            Self::ID_ENCRYPTION_REQUIRED => Ok(Self::EncryptionRequired),
+            Self::ID_UNAUTHORIZED => Ok(Self::Unauthorized),
+            Self::ID_NOT_RESUMABLE => Ok(Self::NotResumable),
             other => Err(protocol_err!("unknown refused reason {other}")),
         }
     }
@@ -90,13 +106,28 @@ pub(crate) enum LinkMsg {
         connection_id: EncryptedConnId,
         /// Whether connection must already exist on the server.
         existing_connection: bool,
+        /// Whether the connection is resumed from another server.
+        resumed_connection: bool,
         /// User-specified link data.
         user_data: Vec<u8>,
         /// Configuration of client.
         cfg: ExchangedCfg,
     },
+    /// Proof of knowledge of the pre-shared key.
+    ///
+    /// Sent by the client after `Connect` and by the server in response,
+    /// if the authentication extension is used.
+    Authenticate {
+        /// Authentication proof.
+        proof: AuthProof,
+    },
     /// Connection accepted by server.
     Accepted,
+    /// Connection accepted by server and established by this link.
+    ///
+    /// Sent instead of `Accepted` if the encryption extension is used, since
+    /// the encryption keys are derived from the shared secret of this link.
+    Established,
     /// Connection refused by server.
     Refused {
         /// Reason for refusal.
@@ -106,6 +137,22 @@ pub(crate) enum LinkMsg {
     Ping,
     /// Echo reply.
     Pong,
+    /// Echo request carrying a timestamp.
+    ///
+    /// Timestamps are in microseconds since an arbitrary epoch of the sending endpoint.
+    TimestampedPing {
+        /// When the ping was sent.
+        sent: u64,
+    },
+    /// Echo reply to a timestamped ping.
+    TimestampedPong {
+        /// When the ping was sent, as specified in the ping.
+        ping_sent: u64,
+        /// When the ping was received by the responding endpoint.
+        ping_received: u64,
+        /// When the pong was sent by the responding endpoint.
+        sent: u64,
+    },
     /// Data.
     ///
     /// This is followed by one data packet.
@@ -113,6 +160,31 @@ pub(crate) enum LinkMsg {
         /// Sequence number.
         seq: Seq,
     },
+    /// Compressed data.
+    ///
+    /// This is followed by one LZ4-compressed data packet.
+    CompressedData {
+        /// Sequence number.
+        seq: Seq,
+    },
+    /// Parity of a group of consecutive reliable messages for forward error correction.
+    ///
+    /// This is followed by one data packet containing the parity.
+    /// It is not acknowledged.
+    Parity {
+        /// Sequence number of first message in group.
+        first: Seq,
+        /// Number of messages in group.
+        count: u8,
+    },
+    /// Unreliable datagram.
+    ///
+    /// This is followed by one data packet.
+    /// It is neither acknowledged nor resent.
+    Datagram {
+        /// Number of the datagram, used as nonce for encryption.
+        number: u64,
+    },
     /// Acknowledges data received over this link.
     Ack {
         /// Sequence that has been received on this link.
@@ -141,6 +213,13 @@ pub(crate) enum LinkMsg {
         /// Sequence number.
         seq: Seq,
     },
+    /// Resumes a session on another server.
+    Resume {
+        /// Sequence number.
+        seq: Seq,
+        /// Number of data messages received before the session was resumed.
+        received: u64,
+    },
     /// Test data to check link.
     TestData {
         /// Size of data.
@@ -181,6 +260,14 @@ impl LinkMsg {
     const MSG_SET_BLOCK: u8 = 14;
     const MSG_GOODBYE: u8 = 15;
     const MSG_TERMINATE: u8 = 16;
+    const MSG_COMPRESSED_DATA: u8 = 17;
+    const MSG_AUTHENTICATE: u8 = 18;
+    const MSG_PARITY: u8 = 19;
+    const MSG_DATAGRAM: u8 = 20;
+    const MSG_TIMESTAMPED_PING: u8 = 21;
+    const MSG_TIMESTAMPED_PONG: u8 = 22;
+    const MSG_RESUME: u8 = 23;
+    const MSG_ESTABLISHED: u8 = 24;
 
     fn write(&self, mut writer: impl io::Write) -> Result<(), io::Error> {
         match self {
@@ -206,6 +293,7 @@ impl LinkMsg {
                 server_id,
                 connection_id,
                 existing_connection,
+                resumed_connection,
                 user_data,
                 cfg,
             } => {
@@ -216,7 +304,7 @@ impl LinkMsg {
                 writer.write_all(public_key.as_bytes())?;
                 writer.write_u128::<BE>(server_id.map(|si| si.0.get()).unwrap_or(0))?;
                 writer.write_u128::<BE>(connection_id.0)?;
-                writer.write_u8(*existing_connection as u8)?;
+                writer.write_u8(*existing_connection as u8 | (*resumed_connection as u8) << 1)?;
                 writer.write_u16::<BE>(
                     user_data
                         .len()
@@ -226,9 +314,16 @@ impl LinkMsg {
                 writer.write_all(user_data)?;
                 cfg.write(&mut writer)?;
             }
+            LinkMsg::Authenticate { proof } => {
+                writer.write_u8(Self::MSG_AUTHENTICATE)?;
+                writer.write_all(proof)?;
+            }
             LinkMsg::Accepted => {
                 writer.write_u8(Self::MSG_ACCEPTED)?;
             }
+            LinkMsg::Established => {
+                writer.write_u8(Self::MSG_ESTABLISHED)?;
+            }
             LinkMsg::Refused { reason } => {
                 writer.write_u8(Self::MSG_REFUSED)?;
                 writer.write_u8((*reason).into())?;
@@ -239,10 +334,33 @@ impl LinkMsg {
             LinkMsg::Pong => {
                 writer.write_u8(Self::MSG_PONG)?;
             }
+            LinkMsg::TimestampedPing { sent } => {
+                writer.write_u8(Self::MSG_TIMESTAMPED_PING)?;
+                writer.write_u64::<BE>(*sent)?;
+            }
+            LinkMsg::TimestampedPong { ping_sent, ping_received, sent } => {
+                writer.write_u8(Self::MSG_TIMESTAMPED_PONG)?;
+                writer.write_u64::<BE>(*ping_sent)?;
+                writer.write_u64::<BE>(*ping_received)?;
+                writer.write_u64::<BE>(*sent)?;
+            }
             LinkMsg::Data { seq } => {
                 writer.write_u8(Self::MSG_DATA)?;
                 writer.write_u32::<BE>((*seq).into())?;
             }
+            LinkMsg::CompressedData { seq } => {
+                writer.write_u8(Self::MSG_COMPRESSED_DATA)?;
+                writer.write_u32::<BE>((*seq).into())?;
+            }
+            LinkMsg::Parity { first, count } => {
+                writer.write_u8(Self::MSG_PARITY)?;
+                writer.write_u32::<BE>((*first).into())?;
+                writer.write_u8(*count)?;
+            }
+            LinkMsg::Datagram { number } => {
+                writer.write_u8(Self::MSG_DATAGRAM)?;
+                writer.write_u64::<BE>(*number)?;
+            }
             LinkMsg::Ack { received } => {
                 writer.write_u8(Self::MSG_ACK)?;
                 writer.write_u32::<BE>((*received).into())?;
@@ -264,6 +382,11 @@ impl LinkMsg {
                 writer.write_u8(Self::MSG_RECEIVE_FINISH)?;
                 writer.write_u32::<BE>((*seq).into())?;
             }
+            LinkMsg::Resume { seq, received } => {
+                writer.write_u8(Self::MSG_RESUME)?;
+                writer.write_u32::<BE>((*seq).into())?;
+                writer.write_u64::<BE>(*received)?;
+            }
             LinkMsg::TestData { size } => {
                 writer.write_u8(Self::MSG_TEST_DATA)?;
                 for n in 0..*size {
@@ -332,6 +455,7 @@ impl LinkMsg {
                         Self::PROTOCOL_VERSION
                     ));
                 }
+                let connection_flags;
                 Self::Connect {
                     extensions: reader.read_u32::<BE>()?,
                     public_key: {
@@ -341,7 +465,11 @@ impl LinkMsg {
                     },
                     server_id: NonZeroU128::new(reader.read_u128::<BE>()?).map(ServerId),
                     connection_id: EncryptedConnId(reader.read_u128::<BE>()?),
-                    existing_connection: reader.read_u8()? != 0,
+                    existing_connection: {
+                        connection_flags = reader.read_u8()?;
+                        connection_flags & 1 != 0
+                    },
+                    resumed_connection: connection_flags & 2 != 0,
                     user_data: {
                         let len = reader.read_u16::<BE>()?;
                         let mut buf = vec![0; len.into()];
@@ -351,11 +479,26 @@ impl LinkMsg {
                     cfg: ExchangedCfg::read(&mut reader)?,
                 }
             }
+            Self::MSG_AUTHENTICATE => {
+                let mut proof = AuthProof::default();
+                reader.read_exact(&mut proof)?;
+                Self::Authenticate { proof }
+            }
             Self::MSG_ACCEPTED => Self::Accepted,
+            Self::MSG_ESTABLISHED => Self::Established,
             Self::MSG_REFUSED => Self::Refused { reason: RefusedReason::try_from(reader.read_u8()?)? },
             Self::MSG_PING => Self::Ping,
             Self::MSG_PONG => Self::Pong,
+            Self::MSG_TIMESTAMPED_PING => Self::TimestampedPing { sent: reader.read_u64::<BE>()? },
+            Self::MSG_TIMESTAMPED_PONG => Self::TimestampedPong {
+                ping_sent: reader.read_u64::<BE>()?,
+                ping_received: reader.read_u64::<BE>()?,
+                sent: reader.read_u64::<BE>()?,
+            },
             Self::MSG_DATA => Self::Data { seq: reader.read_u32::<BE>()?.into() },
+            Self::MSG_COMPRESSED_DATA => Self::CompressedData { seq: reader.read_u32::<BE>()?.into() },
+            Self::MSG_PARITY => Self::Parity { first: reader.read_u32::<BE>()?.into(), count: reader.read_u8()? },
+            Self::MSG_DATAGRAM => Self::Datagram { number: reader.read_u64::<BE>()? },
             Self::MSG_ACK => Self::Ack { received: reader.read_u32::<BE>()?.into() },
             Self::MSG_CONSUMED => {
                 Self::Consumed { seq: reader.read_u32::<BE>()?.into(), consumed: reader.read_u32::<BE>()? }
@@ -363,6 +506,9 @@ impl LinkMsg {
             Self::MSG_SEND_FINISH => Self::SendFinish { seq: reader.read_u32::<BE>()?.into() },
             Self::MSG_RECEIVE_CLOSE => Self::ReceiveClose { seq: reader.read_u32::<BE>()?.into() },
             Self::MSG_RECEIVE_FINISH => Self::ReceiveFinish { seq: reader.read_u32::<BE>()?.into() },
+            Self::MSG_RESUME => {
+                Self::Resume { seq: reader.read_u32::<BE>()?.into(), received: reader.read_u64::<BE>()? }
+            }
             Self::MSG_TEST_DATA => {
                 // The reader is always a memory buffer.
                 #[allow(clippy::unbuffered_bytes)]
@@ -413,6 +559,8 @@ impl LinkMsg {
 pub(crate) enum ReliableMsg {
     /// Data.
     Data(Bytes),
+    /// LZ4-compressed data prepended by its uncompressed size.
+    CompressedData(Bytes),
     /// Received data was consumed.
     Consumed(u32),
     /// No more data will be sent.
@@ -422,16 +570,20 @@ pub(crate) enum ReliableMsg {
     ReceiveClose,
     /// No more received data will be processed.
     ReceiveFinish,
+    /// Session is resumed after receiving the specified number of data messages.
+    Resume(u64),
 }
 
 impl fmt::Debug for ReliableMsg {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Data(data) => write!(f, "Data({} bytes)", data.len()),
+            Self::CompressedData(data) => write!(f, "CompressedData({} bytes)", data.len()),
             Self::Consumed(n) => write!(f, "Consumed({n} bytes)"),
             Self::SendFinish => write!(f, "SendFinish"),
             Self::ReceiveClose => write!(f, "ReceiveClose"),
             Self::ReceiveFinish => write!(f, "ReceiveFinish"),
+            Self::Resume(n) => write!(f, "Resume({n} messages received)"),
         }
     }
 }
@@ -441,10 +593,12 @@ impl ReliableMsg {
     pub(crate) fn to_link_msg(&self, seq: Seq) -> (LinkMsg, Option<Bytes>) {
         match self {
             ReliableMsg::Data(data) => (LinkMsg::Data { seq }, Some(data.clone())),
+            ReliableMsg::CompressedData(data) => (LinkMsg::CompressedData { seq }, Some(data.clone())),
             ReliableMsg::Consumed(n) => (LinkMsg::Consumed { seq, consumed: *n }, None),
             ReliableMsg::SendFinish => (LinkMsg::SendFinish { seq }, None),
             ReliableMsg::ReceiveClose => (LinkMsg::ReceiveClose { seq }, None),
             ReliableMsg::ReceiveFinish => (LinkMsg::ReceiveFinish { seq }, None),
+            ReliableMsg::Resume(received) => (LinkMsg::Resume { seq, received: *received }, None),
         }
     }
 
@@ -452,11 +606,21 @@ impl ReliableMsg {
     pub(crate) fn from_link_msg(msg: LinkMsg, data: Option<Bytes>) -> (Self, Seq) {
         match msg {
             LinkMsg::Data { seq } => (Self::Data(data.unwrap()), seq),
+            LinkMsg::CompressedData { seq } => (Self::CompressedData(data.unwrap()), seq),
             LinkMsg::Consumed { seq, consumed } => (Self::Consumed(consumed), seq),
             LinkMsg::SendFinish { seq } => (Self::SendFinish, seq),
             LinkMsg::ReceiveClose { seq } => (Self::ReceiveClose, seq),
             LinkMsg::ReceiveFinish { seq } => (Self::ReceiveFinish, seq),
+            LinkMsg::Resume { seq, received } => (Self::Resume(received), seq),
             _ => unreachable!("not a reliable link message"),
         }
     }
+
+    /// Size of the contained data as transmitted.
+    pub(crate) fn size(&self) -> usize {
+        match self {
+            ReliableMsg::Data(data) | ReliableMsg::CompressedData(data) => data.len(),
+            _ => 0,
+        }
+    }
 }
@@ -1,5 +1,6 @@
-//! Peekable MPSC wrapper.
+//! Peekable and prioritized MPSC wrappers.
 
+use futures::future;
 use tokio::sync::mpsc;
 
 /// Receiver that allows peeking at next message.
@@ -55,6 +56,96 @@ impl<T> PeekableReceiver<T> {
 
         Ok(self.peeked.as_ref().unwrap())
     }
+}
+
+/// Number of messages taken from queues of higher priority while a message is waiting
+/// in a queue, after which the next message is taken from that queue.
+const MAX_OVERTAKEN: usize = 16;
+
+/// Receiver that receives from multiple peekable queues in order of priority.
+///
+/// A message is taken from the queue of highest priority that has a message available.
+/// The first queue has the highest priority.
+///
+/// To prevent starvation of queues of lower priority, a waiting message is overtaken
+/// by at most [`MAX_OVERTAKEN`] messages of higher priority.
+#[derive(Debug)]
+pub struct PriorityReceiver<T> {
+    /// Queues; `None` once disconnected.
+    rxs: Vec<Option<PeekableReceiver<T>>>,
+    /// Number of messages that have overtaken the waiting message of each queue.
+    overtaken: Vec<usize>,
+}
+
+impl<T> PriorityReceiver<T> {
+    /// Creates a new priority receiver from queues ordered by descending priority.
+    pub fn new(rxs: impl IntoIterator<Item = mpsc::Receiver<T>>) -> Self {
+        let rxs: Vec<_> = rxs.into_iter().map(|rx| Some(rx.into())).collect();
+        Self { overtaken: vec![0; rxs.len()], rxs }
+    }
+
+    /// Index of the queue that has a message available and is served next.
+    ///
+    /// This is the queue of highest priority, unless the waiting message of a queue
+    /// of lower priority has been overtaken too often.
+    fn ready(&mut self) -> Result<usize, mpsc::error::TryRecvError> {
+        let mut disconnected = true;
+        let mut ready = None;
+
+        for (idx, rx_opt) in self.rxs.iter_mut().enumerate() {
+            let Some(rx) = rx_opt else { continue };
+            match rx.try_peek() {
+                Ok(_) if ready.is_none() => ready = Some(idx),
+                Ok(_) if self.overtaken[idx] >= MAX_OVERTAKEN => return Ok(idx),
+                Ok(_) => (),
+                Err(mpsc::error::TryRecvError::Empty) => disconnected = false,
+                Err(mpsc::error::TryRecvError::Disconnected) => *rx_opt = None,
+            }
+        }
+
+        match ready {
+            Some(idx) => Ok(idx),
+            None if disconnected => Err(mpsc::error::TryRecvError::Disconnected),
+            None => Err(mpsc::error::TryRecvError::Empty),
+        }
+    }
+
+    /// Receives next message, if one is immediately available.
+    pub fn try_recv(&mut self) -> Result<T, mpsc::error::TryRecvError> {
+        let idx = self.ready()?;
+        let msg = self.rxs[idx].as_mut().unwrap().try_recv()?;
+
+        // Account overtaking of waiting messages of lower priority.
+        self.overtaken[idx] = 0;
+        for (rx_opt, overtaken) in self.rxs.iter_mut().zip(&mut self.overtaken).skip(idx + 1) {
+            if rx_opt.as_mut().is_some_and(|rx| rx.try_peek().is_ok()) {
+                *overtaken += 1;
+            }
+        }
+
+        Ok(msg)
+    }
+
+    /// Peeks at the next message.
+    pub async fn peek(&mut self) -> Option<&T> {
+        loop {
+            match self.ready() {
+                Ok(idx) => return self.rxs[idx].as_mut().unwrap().try_peek().ok(),
+                Err(mpsc::error::TryRecvError::Disconnected) => return None,
+                Err(mpsc::error::TryRecvError::Empty) => {
+                    // Wait for a message on any queue, then re-evaluate priorities.
+                    let peeks = self.rxs.iter_mut().flatten().map(|rx| Box::pin(rx.peek()));
+                    future::select_all(peeks).await;
+                }
+            }
+        }
+    }
+
+    /// Peeks at the next message, if one is available.
+    pub fn try_peek(&mut self) -> Result<&T, mpsc::error::TryRecvError> {
+        let idx = self.ready()?;
+        self.rxs[idx].as_mut().unwrap().try_peek()
+    }
 
     /// Receives the next messages if the condition is fulfilled.
     pub async fn recv_if(&mut self, cond: impl FnOnce(&T) -> bool) -> Result<T, RecvIfError> {
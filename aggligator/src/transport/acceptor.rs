@@ -15,6 +15,7 @@ use tracing::Instrument;
 use super::{BoxControl, BoxLink, BoxLinkError, BoxListener, BoxServer, BoxTask, LinkError, LinkTag, LinkTagBox};
 use crate::{
     alc::Channel,
+    control::ResumptionTicket,
     exec,
     exec::time::{sleep_until, Instant},
     io::{StreamBox, TxRxBox},
@@ -211,6 +212,14 @@ impl Acceptor {
             && self.transports_being_added.available_permits() == Semaphore::MAX_PERMITS
     }
 
+    /// Accepts the resumption of a session that has been [suspended](crate::control::Control::suspend)
+    /// on another server.
+    ///
+    /// See [`Server::resume`](crate::connect::Server::resume) for details.
+    pub fn resume(&self, ticket: ResumptionTicket) {
+        self.server.resume(ticket);
+    }
+
     /// Waits for an incoming connection and accepts it.
     ///
     /// Returns the aggregated link channel and control handle.
@@ -261,6 +270,7 @@ impl Acceptor {
         // Configure link filter.
         let active_transports = self.active_transports.clone();
         task.set_link_filter(move |link, others| {
+            link.set_priority(link.tag().priority());
             let active_transports = active_transports.clone();
             async move {
                 let transports = active_transports.read_owned().await;
@@ -25,6 +25,7 @@ use crate::{
     exec,
     exec::time::sleep,
     io::{StreamBox, TxRxBox},
+    scheduler::LinkScheduler,
     Cfg, Link, Outgoing,
 };
 
@@ -96,6 +97,13 @@ impl ConnectorBuilder {
         &mut self.task
     }
 
+    /// Sets the link scheduler of the connection.
+    ///
+    /// See the [scheduler module](crate::scheduler) for details.
+    pub fn set_link_scheduler(&mut self, link_scheduler: impl LinkScheduler<LinkTagBox> + 'static) {
+        self.task.set_link_scheduler(link_scheduler)
+    }
+
     /// Sets the reconnect delay for failed links.
     pub fn set_reconnect_delay(&mut self, reconnect_delay: Duration) {
         self.reconnect_delay = reconnect_delay
@@ -114,6 +122,7 @@ impl ConnectorBuilder {
         let active_transports = Arc::new(RwLock::new(Vec::<Weak<dyn ConnectingTransport>>::new()));
         let active_transports_filter = active_transports.clone();
         task.set_link_filter(move |link, others| {
+            link.set_priority(link.tag().priority());
             let active_transports_filter = active_transports_filter.clone();
             async move {
                 let transports = active_transports_filter.read_owned().await;
@@ -0,0 +1,765 @@
+//! In-memory transport and link simulator.
+//!
+//! This transport establishes links within the same process without using any sockets.
+//! Each link is simulated by a pair of [channels](channel), one for each direction,
+//! whose bandwidth, latency and jitter can be configured and which can be paused,
+//! corrupted or disconnected at any time.
+//! This allows writing deterministic integration tests for code built on
+//! a [`Connector`](super::Connector) and [`Acceptor`](super::Acceptor).
+//!
+//! Use [`transport`] to create a connected pair of [`MemoryConnector`] and [`MemoryAcceptor`]
+//! and add links using [`MemoryConnector::add_link`].
+//! Packets sent over these links are protected by a CRC32 checksum, so that a
+//! corrupted packet makes its link fail instead of being passed to the connection.
+//!
+//! ```
+//! use aggligator::transport::{memory, Acceptor, Connector};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let (memory_connector, memory_acceptor) = memory::transport();
+//! let link = memory_connector.add_link("link", memory::ChannelCfg::default());
+//!
+//! let acceptor = Acceptor::new();
+//! acceptor.add(memory_acceptor);
+//!
+//! let mut connector = Connector::new();
+//! connector.add(memory_connector);
+//! let outgoing = connector.channel().unwrap();
+//!
+//! let (incoming, _control) = acceptor.accept().await.unwrap();
+//! let outgoing = outgoing.await.unwrap();
+//!
+//! // Simulate a slow link.
+//! link.set_speed(10_000).await;
+//! # }
+//! ```
+
+use async_trait::async_trait;
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::{future, ready, Sink, SinkExt, Stream, StreamExt};
+use rand::prelude::*;
+use rand_xoshiro::SplitMix64;
+use std::{
+    any::Any,
+    cmp::Ordering,
+    collections::{BTreeMap, HashSet},
+    fmt,
+    hash::{Hash, Hasher},
+    io::{Error, ErrorKind, Result},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::sync::{mpsc, oneshot, watch, Mutex as AsyncMutex, Semaphore};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::{PollSemaphore, PollSender};
+
+use super::{AcceptedStreamBox, AcceptingTransport, ConnectingTransport, LinkTag, LinkTagBox};
+use crate::{
+    control::Direction,
+    exec,
+    exec::time::{sleep, sleep_until, Instant},
+    io::{StreamBox, TxRxBox},
+};
+
+static NAME: &str = "memory";
+
+/// Simulated channel configuration.
+#[derive(Clone, Debug)]
+pub struct ChannelCfg {
+    /// Speed in bytes per second.
+    ///
+    /// Zero for no throttling.
+    pub speed: usize,
+    /// Maximum buffer size in items.
+    pub buffer_items: usize,
+    /// Maximum buffer size in bytes.
+    pub buffer_size: usize,
+    /// Latency.
+    pub latency: Option<Duration>,
+    /// Maximum random delay added to the latency of each packet.
+    ///
+    /// The order of packets is preserved.
+    pub jitter: Duration,
+    /// Seed of the random number generator used for jitter and corruption.
+    pub seed: u64,
+}
+
+impl Default for ChannelCfg {
+    fn default() -> Self {
+        Self { speed: 0, buffer_items: 128, buffer_size: 16384, latency: None, jitter: Duration::ZERO, seed: 0 }
+    }
+}
+
+struct Packet {
+    data: Bytes,
+    sent: Instant,
+}
+
+enum ControlReq {
+    PauseFor(Duration),
+    PauseThenDisconnect(Duration),
+    LoseThenDisconnect(Duration),
+    Corrupt(usize),
+    SetLatency(Option<Duration>),
+    SetJitter(Duration),
+    SetSpeed(usize),
+    Disconnect,
+}
+
+struct ControlMsg {
+    req: ControlReq,
+    processed_tx: oneshot::Sender<()>,
+}
+
+/// Buffer occupancy shared between both ends of a channel.
+struct Buffer {
+    size: AtomicUsize,
+    consumed: Arc<Semaphore>,
+}
+
+impl Buffer {
+    /// Releases the buffer space occupied by a packet.
+    fn release(&self, size: usize) {
+        self.size.fetch_sub(size, AtomicOrdering::SeqCst);
+        if self.consumed.available_permits() == 0 {
+            self.consumed.add_permits(1);
+        }
+    }
+}
+
+/// Creates a new simulated unidirectional channel using the provided configuration.
+pub fn channel(mut cfg: ChannelCfg) -> (ChannelSender, ChannelReceiver, ChannelControl) {
+    let sender_items = (cfg.buffer_items / 2).max(1);
+    let receiver_items = (cfg.buffer_items - sender_items).max(1);
+    let (sender_tx, mut sender_rx) = mpsc::channel(sender_items);
+    let (receiver_tx, receiver_rx) = mpsc::channel(receiver_items);
+
+    let buffer = Arc::new(Buffer { size: AtomicUsize::new(0), consumed: Arc::new(Semaphore::new(0)) });
+    let disconnected = Arc::new(AtomicBool::new(false));
+
+    let sender = ChannelSender {
+        buffer_limit: cfg.buffer_size,
+        tx: PollSender::new(sender_tx),
+        buffer: buffer.clone(),
+        buffer_consumed: PollSemaphore::new(buffer.consumed.clone()),
+        not_ready_since: None,
+    };
+
+    let receiver = ChannelReceiver {
+        rx: ReceiverStream::new(receiver_rx),
+        buffer: buffer.clone(),
+        disconnected: disconnected.clone(),
+    };
+
+    let (control_tx, control_rx) = mpsc::channel(1);
+    let control = ChannelControl { tx: control_tx };
+
+    exec::spawn(async move {
+        let mut rng = SplitMix64::seed_from_u64(cfg.seed);
+        let mut control_rx_opt = Some(control_rx);
+        let mut sleep_need = Duration::ZERO;
+        let mut corrupt = 0;
+        let mut lose_until = None;
+
+        loop {
+            tokio::select! {
+                packet_opt = sender_rx.recv() => {
+                    let Some(mut packet): Option<Packet> = packet_opt else { break };
+
+                    if lose_until.is_some() {
+                        buffer.release(packet.data.len());
+                        continue;
+                    }
+
+                    let mut delay = cfg.latency.unwrap_or_default();
+                    if !cfg.jitter.is_zero() {
+                        delay += cfg.jitter.mul_f64(rng.random());
+                    }
+                    let until = packet.sent + delay;
+                    if until > Instant::now() {
+                        sleep_until(until).await;
+                    }
+
+                    if cfg.speed > 0 {
+                        sleep_need += Duration::from_secs_f64(packet.data.len() as f64 / cfg.speed as f64);
+                        if sleep_need >= Duration::from_millis(100) {
+                            sleep(sleep_need).await;
+                            sleep_need = Duration::ZERO;
+                        }
+                    }
+
+                    if corrupt > 0 && !packet.data.is_empty() {
+                        let mut data = packet.data.to_vec();
+                        let pos = rng.random_range(0..data.len());
+                        data[pos] ^= 0xff;
+                        packet.data = data.into();
+                        corrupt -= 1;
+                    }
+
+                    if receiver_tx.send(packet).await.is_err() {
+                        break;
+                    }
+                }
+                () = async {
+                    match lose_until {
+                        Some(until) => sleep_until(until).await,
+                        None => future::pending().await,
+                    }
+                } => {
+                    disconnected.store(true, AtomicOrdering::SeqCst);
+                    break;
+                }
+                msg_opt = async {
+                    match control_rx_opt.as_mut() {
+                        Some(control_rx) => control_rx.recv().await,
+                        None => future::pending().await,
+                    }
+                } => {
+                    match msg_opt {
+                        Some(ControlMsg { req, processed_tx }) => {
+                            match req {
+                                ControlReq::PauseFor(dur) => sleep(dur).await,
+                                ControlReq::PauseThenDisconnect(dur) => {
+                                    sleep(dur).await;
+                                    disconnected.store(true, AtomicOrdering::SeqCst);
+                                    break;
+                                }
+                                ControlReq::LoseThenDisconnect(dur) => lose_until = Some(Instant::now() + dur),
+                                ControlReq::Corrupt(count) => corrupt += count,
+                                ControlReq::SetLatency(latency) => cfg.latency = latency,
+                                ControlReq::SetJitter(jitter) => cfg.jitter = jitter,
+                                ControlReq::SetSpeed(speed) => cfg.speed = speed,
+                                ControlReq::Disconnect => {
+                                    disconnected.store(true, AtomicOrdering::SeqCst);
+                                    break;
+                                }
+                            }
+                            let _ = processed_tx.send(());
+                        }
+                        None => control_rx_opt = None,
+                    }
+                }
+            }
+        }
+    });
+
+    (sender, receiver, control)
+}
+
+/// Controls a simulated channel.
+#[derive(Clone)]
+pub struct ChannelControl {
+    tx: mpsc::Sender<ControlMsg>,
+}
+
+impl fmt::Debug for ChannelControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChannelControl").field("disconnected", &self.tx.is_closed()).finish()
+    }
+}
+
+impl ChannelControl {
+    async fn send_req(&self, req: ControlReq) -> Result<()> {
+        let (processed_tx, processed_rx) = oneshot::channel();
+        self.tx.send(ControlMsg { req, processed_tx }).await.map_err(|_| ErrorKind::BrokenPipe)?;
+        let _ = processed_rx.await;
+        Ok(())
+    }
+
+    /// Pauses the channel for the specified amount of time.
+    ///
+    /// Returns when the pause has ended.
+    pub async fn pause_for(&self, duration: Duration) -> Result<()> {
+        self.send_req(ControlReq::PauseFor(duration)).await
+    }
+
+    /// Pauses the channel for the specified amount of time and then disconnects it.
+    ///
+    /// Returns when the channel has been disconnected.
+    pub async fn pause_then_disconnect(&self, duration: Duration) -> Result<()> {
+        self.send_req(ControlReq::PauseThenDisconnect(duration)).await
+    }
+
+    /// Discards all data sent over the channel for the specified amount of time
+    /// and then disconnects it.
+    ///
+    /// This simulates a link that silently stops working.
+    /// Returns as soon as data is being discarded.
+    pub async fn lose_then_disconnect(&self, duration: Duration) -> Result<()> {
+        self.send_req(ControlReq::LoseThenDisconnect(duration)).await
+    }
+
+    /// Corrupts the next `count` non-empty packets sent over the channel.
+    ///
+    /// In each corrupted packet the bits of one randomly chosen byte are inverted.
+    pub async fn corrupt(&self, count: usize) -> Result<()> {
+        self.send_req(ControlReq::Corrupt(count)).await
+    }
+
+    /// Sets the latency.
+    pub async fn set_latency(&self, latency: Option<Duration>) -> Result<()> {
+        self.send_req(ControlReq::SetLatency(latency)).await
+    }
+
+    /// Sets the jitter.
+    pub async fn set_jitter(&self, jitter: Duration) -> Result<()> {
+        self.send_req(ControlReq::SetJitter(jitter)).await
+    }
+
+    /// Sets the speed in bytes per second.
+    ///
+    /// Zero for no throttling.
+    pub async fn set_speed(&self, speed: usize) -> Result<()> {
+        self.send_req(ControlReq::SetSpeed(speed)).await
+    }
+
+    /// Disconnects the channel.
+    pub async fn disconnect(&self) -> Result<()> {
+        self.send_req(ControlReq::Disconnect).await
+    }
+}
+
+/// Sending half of a simulated channel.
+pub struct ChannelSender {
+    buffer_limit: usize,
+    tx: PollSender<Packet>,
+    buffer: Arc<Buffer>,
+    buffer_consumed: PollSemaphore,
+    not_ready_since: Option<Instant>,
+}
+
+impl fmt::Debug for ChannelSender {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChannelSender").finish_non_exhaustive()
+    }
+}
+
+impl Sink<Bytes> for ChannelSender {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        let this = Pin::into_inner(self);
+
+        while this.buffer.size.load(AtomicOrdering::SeqCst) >= this.buffer_limit {
+            match ready!(this.buffer_consumed.poll_acquire(cx)) {
+                Some(permit) => permit.forget(),
+                None => return Poll::Ready(Err(ErrorKind::BrokenPipe.into())),
+            }
+        }
+
+        match this.tx.poll_ready_unpin(cx).map_err(|_| ErrorKind::BrokenPipe)? {
+            Poll::Ready(()) => {
+                if let Some(not_ready_since) = this.not_ready_since.take() {
+                    let elapsed = not_ready_since.elapsed();
+                    if elapsed >= Duration::from_millis(100) {
+                        tracing::trace!("simulated channel was blocked for {:.2} s", elapsed.as_secs_f64());
+                    }
+                }
+                Poll::Ready(Ok(()))
+            }
+            Poll::Pending => {
+                if this.not_ready_since.is_none() {
+                    this.not_ready_since = Some(Instant::now());
+                }
+                Poll::Pending
+            }
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Bytes) -> Result<()> {
+        let this = Pin::into_inner(self);
+
+        let size = item.len();
+        this.tx
+            .start_send_unpin(Packet { data: item, sent: Instant::now() })
+            .map_err(|_| ErrorKind::BrokenPipe)?;
+        this.buffer.size.fetch_add(size, AtomicOrdering::SeqCst);
+
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        let this = Pin::into_inner(self);
+        ready!(this.tx.poll_flush_unpin(cx)).map_err(|_| ErrorKind::BrokenPipe)?;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        let this = Pin::into_inner(self);
+        ready!(this.tx.poll_close_unpin(cx)).map_err(|_| ErrorKind::BrokenPipe)?;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Receiving half of a simulated channel.
+pub struct ChannelReceiver {
+    rx: ReceiverStream<Packet>,
+    buffer: Arc<Buffer>,
+    disconnected: Arc<AtomicBool>,
+}
+
+impl fmt::Debug for ChannelReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChannelReceiver").finish_non_exhaustive()
+    }
+}
+
+impl Stream for ChannelReceiver {
+    type Item = Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = Pin::into_inner(self);
+
+        let packet = match ready!(this.rx.poll_next_unpin(cx)) {
+            Some(packet) => packet,
+            None if this.disconnected.load(AtomicOrdering::SeqCst) => {
+                return Poll::Ready(Some(Err(ErrorKind::BrokenPipe.into())))
+            }
+            None => return Poll::Ready(None),
+        };
+
+        this.buffer.release(packet.data.len());
+        Poll::Ready(Some(Ok(packet.data)))
+    }
+}
+
+/// Link tag for in-memory link.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MemoryLinkTag {
+    /// Link name.
+    pub name: String,
+    /// Link direction.
+    pub direction: Direction,
+}
+
+impl fmt::Display for MemoryLinkTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dir = match self.direction {
+            Direction::Incoming => "<-",
+            Direction::Outgoing => "->",
+        };
+        write!(f, "{dir} {}", &self.name)
+    }
+}
+
+impl MemoryLinkTag {
+    /// Creates a new link tag for an in-memory link.
+    pub fn new(name: impl Into<String>, direction: Direction) -> Self {
+        Self { name: name.into(), direction }
+    }
+}
+
+impl LinkTag for MemoryLinkTag {
+    fn transport_name(&self) -> &str {
+        NAME
+    }
+
+    fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    fn user_data(&self) -> Vec<u8> {
+        self.name.as_bytes().to_vec()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn box_clone(&self) -> LinkTagBox {
+        Box::new(self.clone())
+    }
+
+    fn dyn_cmp(&self, other: &dyn LinkTag) -> Ordering {
+        let other = other.as_any().downcast_ref::<Self>().unwrap();
+        Ord::cmp(self, other)
+    }
+
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        Hash::hash(self, &mut state)
+    }
+}
+
+/// State of a simulated link.
+#[derive(Debug)]
+struct LinkState {
+    cfg: Mutex<ChannelCfg>,
+    /// Controls of both directions of the currently connected link.
+    controls: Mutex<Vec<ChannelControl>>,
+}
+
+/// Controls a simulated link of a [`MemoryConnector`].
+///
+/// Settings are applied to both directions of the link.
+/// Bandwidth, latency and jitter are kept when the link is reconnected,
+/// while all other operations only affect the currently connected link.
+#[derive(Debug, Clone)]
+pub struct MemoryLinkControl {
+    name: String,
+    state: Arc<LinkState>,
+}
+
+impl MemoryLinkControl {
+    /// Link name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the link is currently connected.
+    pub fn is_connected(&self) -> bool {
+        self.state.controls.lock().unwrap().iter().any(|control| !control.tx.is_closed())
+    }
+
+    fn controls(&self) -> Vec<ChannelControl> {
+        self.state.controls.lock().unwrap().clone()
+    }
+
+    /// Sends a request to both directions of the currently connected link.
+    async fn send_req(&self, req: impl Fn() -> ControlReq) -> Result<()> {
+        let controls = self.controls();
+        if controls.is_empty() {
+            return Err(Error::new(ErrorKind::NotConnected, "link is not connected"));
+        }
+
+        let res = future::join_all(controls.iter().map(|control| control.send_req(req()))).await;
+        res.into_iter().collect()
+    }
+
+    /// Applies a setting to both directions of the currently connected link, if any.
+    async fn apply(&self, req: impl Fn() -> ControlReq) {
+        let controls = self.controls();
+        future::join_all(controls.iter().map(|control| control.send_req(req()))).await;
+    }
+
+    /// Sets the speed in bytes per second.
+    ///
+    /// Zero for no throttling.
+    pub async fn set_speed(&self, speed: usize) {
+        self.state.cfg.lock().unwrap().speed = speed;
+        self.apply(|| ControlReq::SetSpeed(speed)).await
+    }
+
+    /// Sets the latency.
+    pub async fn set_latency(&self, latency: Option<Duration>) {
+        self.state.cfg.lock().unwrap().latency = latency;
+        self.apply(|| ControlReq::SetLatency(latency)).await
+    }
+
+    /// Sets the jitter.
+    pub async fn set_jitter(&self, jitter: Duration) {
+        self.state.cfg.lock().unwrap().jitter = jitter;
+        self.apply(|| ControlReq::SetJitter(jitter)).await
+    }
+
+    /// Pauses the link for the specified amount of time.
+    ///
+    /// See [`ChannelControl::pause_for`].
+    pub async fn pause_for(&self, duration: Duration) -> Result<()> {
+        self.send_req(|| ControlReq::PauseFor(duration)).await
+    }
+
+    /// Pauses the link for the specified amount of time and then disconnects it.
+    ///
+    /// See [`ChannelControl::pause_then_disconnect`].
+    pub async fn pause_then_disconnect(&self, duration: Duration) -> Result<()> {
+        self.send_req(|| ControlReq::PauseThenDisconnect(duration)).await
+    }
+
+    /// Discards all data sent over the link for the specified amount of time
+    /// and then disconnects it.
+    ///
+    /// See [`ChannelControl::lose_then_disconnect`].
+    pub async fn lose_then_disconnect(&self, duration: Duration) -> Result<()> {
+        self.send_req(|| ControlReq::LoseThenDisconnect(duration)).await
+    }
+
+    /// Corrupts the next `count` non-empty packets sent in each direction over the link.
+    ///
+    /// See [`ChannelControl::corrupt`].
+    pub async fn corrupt(&self, count: usize) -> Result<()> {
+        self.send_req(|| ControlReq::Corrupt(count)).await
+    }
+
+    /// Disconnects the link.
+    ///
+    /// The connector will reconnect it after its reconnect delay.
+    pub async fn disconnect(&self) -> Result<()> {
+        self.send_req(|| ControlReq::Disconnect).await
+    }
+}
+
+/// Creates a connected pair of in-memory transports.
+///
+/// Links added to the returned [`MemoryConnector`] are accepted by the returned [`MemoryAcceptor`].
+pub fn transport() -> (MemoryConnector, MemoryAcceptor) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let (links_tx, _) = watch::channel(BTreeMap::new());
+    let connector = MemoryConnector { links: Arc::new(links_tx), tx };
+    let acceptor = MemoryAcceptor { rx: AsyncMutex::new(rx) };
+    (connector, acceptor)
+}
+
+/// Creates a link that appends a checksum to each packet and verifies it on reception.
+fn checked_link(tx: ChannelSender, rx: ChannelReceiver) -> TxRxBox {
+    let tx = tx.with(|data: Bytes| {
+        let mut buf = BytesMut::with_capacity(data.len() + 4);
+        buf.put_slice(&data);
+        buf.put_u32(crc32fast::hash(&data));
+        future::ready(Ok::<_, Error>(buf.freeze()))
+    });
+
+    let rx = rx.map(|res| {
+        let mut data = res?;
+        if data.len() < 4 {
+            return Err(Error::new(ErrorKind::InvalidData, "packet too short"));
+        }
+        let crc = data.split_off(data.len() - 4);
+        if crc[..] != crc32fast::hash(&data).to_be_bytes() {
+            return Err(Error::new(ErrorKind::InvalidData, "packet checksum mismatch"));
+        }
+        Ok(data)
+    });
+
+    TxRxBox::new(tx, rx)
+}
+
+/// In-memory transport for outgoing links.
+///
+/// Create it together with its [`MemoryAcceptor`] using [`transport`].
+/// Clones share the same set of links.
+///
+/// This transport is packet-based.
+#[derive(Debug, Clone)]
+pub struct MemoryConnector {
+    links: Arc<watch::Sender<BTreeMap<String, Arc<LinkState>>>>,
+    tx: mpsc::UnboundedSender<AcceptedStreamBox>,
+}
+
+impl fmt::Display for MemoryConnector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<_> = self.links.borrow().keys().cloned().collect();
+        write!(f, "memory [{}]", names.join(", "))
+    }
+}
+
+impl MemoryConnector {
+    /// Adds a simulated link with the specified name and configuration.
+    ///
+    /// If a link with the same name exists, it is replaced.
+    /// Returns a handle for controlling the link.
+    pub fn add_link(&self, name: impl Into<String>, cfg: ChannelCfg) -> MemoryLinkControl {
+        let name = name.into();
+        let state = Arc::new(LinkState { cfg: Mutex::new(cfg), controls: Mutex::new(Vec::new()) });
+        self.links.send_modify(|links| {
+            links.insert(name.clone(), state.clone());
+        });
+        MemoryLinkControl { name, state }
+    }
+
+    /// Removes the simulated link with the specified name.
+    ///
+    /// The link is disconnected if it is currently connected.
+    pub async fn remove_link(&self, name: &str) {
+        let mut removed = None;
+        self.links.send_if_modified(|links| {
+            removed = links.remove(name);
+            removed.is_some()
+        });
+
+        if let Some(state) = removed {
+            MemoryLinkControl { name: name.to_string(), state }.apply(|| ControlReq::Disconnect).await;
+        }
+    }
+
+    /// Names of the simulated links.
+    pub fn link_names(&self) -> Vec<String> {
+        self.links.borrow().keys().cloned().collect()
+    }
+}
+
+#[async_trait]
+impl ConnectingTransport for MemoryConnector {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    async fn link_tags(&self, tx: watch::Sender<HashSet<LinkTagBox>>) -> Result<()> {
+        let mut links_rx = self.links.subscribe();
+
+        loop {
+            let tags: HashSet<LinkTagBox> = links_rx
+                .borrow_and_update()
+                .keys()
+                .map(|name| Box::new(MemoryLinkTag::new(name, Direction::Outgoing)) as LinkTagBox)
+                .collect();
+            tx.send_replace(tags);
+
+            if links_rx.changed().await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn connect(&self, tag: &dyn LinkTag) -> Result<StreamBox> {
+        let tag: &MemoryLinkTag = tag.as_any().downcast_ref().unwrap();
+
+        let Some(state) = self.links.borrow().get(&tag.name).cloned() else {
+            return Err(Error::new(ErrorKind::NotFound, "simulated link was removed"));
+        };
+
+        let cfg = state.cfg.lock().unwrap().clone();
+        let (out_tx, out_rx, out_control) = channel(cfg.clone());
+        let (in_tx, in_rx, in_control) = channel(ChannelCfg { seed: cfg.seed.wrapping_add(1), ..cfg });
+
+        let accepted = AcceptedStreamBox::new(
+            checked_link(in_tx, out_rx).into(),
+            MemoryLinkTag::new(&tag.name, Direction::Incoming),
+        );
+        self.tx
+            .send(accepted)
+            .map_err(|_| Error::new(ErrorKind::ConnectionRefused, "memory acceptor dropped"))?;
+
+        *state.controls.lock().unwrap() = vec![out_control, in_control];
+
+        Ok(checked_link(out_tx, in_rx).into())
+    }
+}
+
+/// In-memory transport for incoming links.
+///
+/// Create it together with its [`MemoryConnector`] using [`transport`].
+///
+/// This transport is packet-based.
+#[derive(Debug)]
+pub struct MemoryAcceptor {
+    rx: AsyncMutex<mpsc::UnboundedReceiver<AcceptedStreamBox>>,
+}
+
+impl fmt::Display for MemoryAcceptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "memory")
+    }
+}
+
+#[async_trait]
+impl AcceptingTransport for MemoryAcceptor {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    async fn listen(&self, tx: mpsc::Sender<AcceptedStreamBox>) -> Result<()> {
+        let mut rx = self.rx.lock().await;
+        while let Some(accepted) = rx.recv().await {
+            if tx.send(accepted).await.is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
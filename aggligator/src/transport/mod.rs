@@ -79,6 +79,7 @@ use crate::{
 
 mod acceptor;
 mod connector;
+pub mod memory;
 
 pub use acceptor::*;
 pub use connector::*;
@@ -140,6 +141,13 @@ pub trait LinkTag: Debug + Display + Send + Sync + 'static {
     /// User data to send to the remote endpoint when connecting.
     fn user_data(&self) -> Vec<u8>;
 
+    /// Initial priority of a link using this tag.
+    ///
+    /// See [`Link::set_priority`](crate::Link::set_priority) for details.
+    fn priority(&self) -> u8 {
+        0
+    }
+
     /// Cast this type as [`Any`].
     fn as_any(&self) -> &dyn Any;
 
@@ -0,0 +1,10 @@
+//! Helpers shared between tests.
+
+use bytes::Bytes;
+
+/// Test data of the specified size for the message with number `n`.
+///
+/// The data repeats a short text containing the message number and is thus compressible.
+pub fn packet(n: usize, size: usize) -> Bytes {
+    format!("packet {n}; ").bytes().cycle().take(size).collect()
+}
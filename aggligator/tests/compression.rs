@@ -0,0 +1,89 @@
+//! Compression tests.
+
+use futures::join;
+use std::future::IntoFuture;
+
+#[cfg(feature = "js")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use aggligator::{
+    cfg::{Cfg, Extensions},
+    connect::{connect, Server},
+    exec,
+};
+
+mod common;
+mod test_channel;
+
+use common::packet;
+
+const COUNT: usize = 200;
+const SIZE: usize = 8192;
+
+/// Sends compressible data from a client with compression enabled to a server
+/// offering the specified extensions.
+///
+/// Returns the number of bytes sent over the link.
+async fn compression_test(server_extensions: Extensions) -> u64 {
+    let (link_a_tx, link_a_rx, _link_a_control) = test_channel::channel(test_channel::Cfg::default());
+    let (link_b_tx, link_b_rx, _link_b_control) = test_channel::channel(test_channel::Cfg::default());
+
+    let server_task = async move {
+        let server = Server::new(Cfg { extensions: server_extensions, ..Default::default() });
+        let mut listener = server.listen().unwrap();
+        server.add_incoming(link_b_tx, link_a_rx, "incoming", &[]).await.unwrap();
+
+        let incoming = listener.next().await.unwrap();
+        assert_eq!(
+            incoming.extensions().contains(Extensions::COMPRESSION),
+            server_extensions.contains(Extensions::COMPRESSION)
+        );
+        let (task, ch, _control) = incoming.accept();
+        exec::spawn(task.into_future());
+
+        let (_tx, mut rx) = ch.into_tx_rx();
+        let mut n = 0;
+        while let Some(data) = rx.recv().await.unwrap() {
+            assert_eq!(data, packet(n, SIZE), "data mismatch in message {n}");
+            n += 1;
+        }
+        assert_eq!(n, COUNT);
+    };
+
+    let client_task = async move {
+        let (task, outgoing, control) = connect(Cfg { compression: true, ..Default::default() });
+        exec::spawn(task.into_future());
+
+        let mut link = control.add(link_a_tx, link_b_rx, "outgoing", &[]).await.unwrap();
+        let ch = outgoing.connect().await.unwrap();
+        let (tx, _rx) = ch.into_tx_rx();
+        for n in 0..COUNT {
+            tx.send(packet(n, SIZE)).await.unwrap();
+        }
+        tx.flush().await.unwrap();
+
+        // Wait for statistics to be updated after flushing.
+        link.stats_update();
+        link.stats_changed().await;
+        link.stats().total_sent
+    };
+
+    let ((), sent) = join!(server_task, client_task);
+    sent
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn compressed() {
+    let sent = compression_test(Extensions::SUPPORTED).await;
+    println!("sent {sent} bytes for {} bytes of data", COUNT * SIZE);
+    assert!(sent < (COUNT * SIZE / 2) as u64, "data was not compressed");
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn compression_not_offered() {
+    let sent = compression_test(Extensions::NONE).await;
+    println!("sent {sent} bytes for {} bytes of data", COUNT * SIZE);
+    assert!(sent >= (COUNT * SIZE) as u64, "data was compressed");
+}
@@ -0,0 +1,206 @@
+//! Congestion control tests.
+
+use bytes::{Buf, Bytes};
+use futures::{channel::mpsc, future, join, stream, SinkExt, StreamExt};
+use std::{
+    future::IntoFuture,
+    io::{Error, ErrorKind},
+    time::Duration,
+};
+
+#[cfg(feature = "js")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use aggligator::{
+    cfg::{Cfg, CongestionControl},
+    connect::{connect, Server},
+    exec::{
+        self,
+        time::{sleep, timeout, Instant},
+    },
+    transport::{
+        memory::{self, ChannelCfg},
+        AcceptorBuilder, ConnectorBuilder,
+    },
+};
+
+mod test_channel;
+
+const SPEED: usize = 500_000;
+const LATENCY: Duration = Duration::from_millis(20);
+const DURATION: Duration = Duration::from_secs(4);
+
+/// Sends bulk data over independent links with large bottleneck buffers and
+/// returns the maximum roundtrip time under load and the achieved throughput.
+async fn bulk_transfer(cfg: Cfg, links: usize) -> (Duration, f64) {
+    let (memory_connector, memory_acceptor) = memory::transport();
+    for n in 0..links {
+        memory_connector.add_link(
+            format!("bloated {n}"),
+            ChannelCfg {
+                speed: SPEED,
+                latency: Some(LATENCY),
+                buffer_items: 100_000,
+                buffer_size: 4 * SPEED,
+                seed: n as u64,
+                ..Default::default()
+            },
+        );
+    }
+
+    let acceptor = AcceptorBuilder::new(cfg.clone()).build();
+    acceptor.add(memory_acceptor);
+
+    let mut connector = ConnectorBuilder::new(cfg).build();
+    connector.add(memory_connector);
+    let outgoing = connector.channel().unwrap();
+
+    let server_task = async {
+        let (ch, _control) = acceptor.accept().await.unwrap();
+        let (_tx, mut rx) = ch.into_tx_rx();
+
+        let start = Instant::now();
+        let mut received = 0;
+        while let Ok(Ok(Some(data))) = timeout(DURATION.saturating_sub(start.elapsed()), rx.recv()).await {
+            received += data.len();
+        }
+        received as f64 / start.elapsed().as_secs_f64()
+    };
+
+    let client_task = async {
+        let (tx, _rx) = outgoing.await.unwrap().into_tx_rx();
+        let control = connector.control();
+        let data = Bytes::from(vec![0; 8192]);
+
+        let send = async { while tx.send(data.clone()).await.is_ok() {} };
+        let measure = async {
+            sleep(DURATION * 3 / 4).await;
+            control.links().iter().map(|link| link.stats().roundtrip).max().unwrap()
+        };
+
+        tokio::select! {
+            () = send => panic!("sending failed"),
+            roundtrip = measure => roundtrip,
+        }
+    };
+
+    let (throughput, roundtrip) = join!(server_task, client_task);
+    tracing::info!(?roundtrip, "throughput {throughput:.0} bytes/s");
+
+    (roundtrip, throughput)
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn ledbat_keeps_latency_low() {
+    let cfg = Cfg {
+        link_congestion_control: CongestionControl::Ledbat(Duration::from_millis(25)),
+        ..Default::default()
+    };
+    let (roundtrip, throughput) = bulk_transfer(cfg, 1).await;
+    assert!(roundtrip < Duration::from_millis(250), "roundtrip under load is {roundtrip:?}");
+    assert!(throughput > SPEED as f64 / 3.0, "throughput is {throughput:.0} bytes/s");
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn coupled_independent_links() {
+    let cfg = Cfg {
+        link_congestion_control: CongestionControl::Ledbat(Duration::from_millis(25)),
+        link_coupled_congestion_control: true,
+        ..Default::default()
+    };
+    let (roundtrip, throughput) = bulk_transfer(cfg, 2).await;
+    assert!(roundtrip < Duration::from_millis(250), "roundtrip under load is {roundtrip:?}");
+
+    // Links that do not share a bottleneck must not be coupled.
+    assert!(throughput > SPEED as f64 * 2.0 / 3.0, "throughput is {throughput:.0} bytes/s");
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn coupled_shared_bottleneck() {
+    const LINKS: u8 = 2;
+
+    let cfg = Cfg { link_coupled_congestion_control: true, ..Default::default() };
+
+    // All links from client to server are multiplexed over one bloated channel.
+    let (bottleneck_tx, mut bottleneck_rx, _bottleneck_control) = test_channel::channel(test_channel::Cfg {
+        speed: SPEED,
+        latency: Some(LATENCY),
+        buffer_items: 100_000,
+        buffer_size: 4 * SPEED,
+        ..Default::default()
+    });
+
+    let mut server_links = Vec::new();
+    let mut client_links = Vec::new();
+    let mut up_rxs = Vec::new();
+    let mut demux_txs = Vec::new();
+    for n in 0..LINKS {
+        let (up_tx, up_rx) = mpsc::channel::<Bytes>(16);
+        let (demux_tx, demux_rx) = mpsc::channel::<Bytes>(16);
+        let (down_tx, down_rx, _down_control) =
+            test_channel::channel(test_channel::Cfg { latency: Some(LATENCY), ..Default::default() });
+
+        up_rxs.push(up_rx.map(move |data| Ok([Bytes::from(vec![n]), data].concat().into())).boxed());
+        demux_txs.push(demux_tx);
+        let up_tx = up_tx.sink_map_err(|_| Error::from(ErrorKind::BrokenPipe));
+        client_links.push((format!("shared {n}"), up_tx, down_rx));
+        server_links.push((format!("shared {n}"), down_tx, demux_rx.map(Ok)));
+    }
+
+    exec::spawn(stream::select_all(up_rxs).forward(bottleneck_tx));
+    exec::spawn(async move {
+        while let Some(Ok(mut data)) = bottleneck_rx.next().await {
+            let n = data.get_u8();
+            if demux_txs[n as usize].send(data).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let server_cfg = cfg.clone();
+    let server_task = async move {
+        let server = Server::new(server_cfg);
+        let mut listener = server.listen().unwrap();
+        for (tag, tx, rx) in server_links {
+            server.add_incoming(tx, rx, tag, &[]).await.unwrap();
+        }
+
+        let (task, ch, _control) = listener.next().await.unwrap().accept();
+        exec::spawn(task.into_future());
+
+        let (_tx, mut rx) = ch.into_tx_rx();
+        while let Ok(Some(_)) = rx.recv().await {}
+    };
+
+    let client_task = async move {
+        let (task, outgoing, control) = connect(cfg);
+        exec::spawn(task.into_future());
+
+        let add_links = client_links.into_iter().map(|(tag, tx, rx)| control.add(tx, rx, tag, &[]));
+        let links = future::try_join_all(add_links).await.unwrap();
+
+        let (tx, _rx) = outgoing.connect().await.unwrap().into_tx_rx();
+        let data = Bytes::from(vec![0; 8192]);
+        let send = async { while tx.send(data.clone()).await.is_ok() {} };
+
+        // Links sharing a bottleneck must be coupled.
+        let detect = async {
+            while !links.iter().all(|link| link.stats().shared_bottleneck) {
+                sleep(Duration::from_millis(100)).await;
+            }
+        };
+
+        tokio::select! {
+            () = send => panic!("sending failed"),
+            res = timeout(Duration::from_secs(20), detect) => res.expect("shared bottleneck not detected"),
+        }
+    };
+
+    tokio::select! {
+        () = server_task => panic!("server terminated"),
+        () = client_task => (),
+    }
+}
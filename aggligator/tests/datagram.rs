@@ -0,0 +1,191 @@
+//! Unreliable datagram tests.
+
+use bytes::Bytes;
+use futures::join;
+use std::{num::NonZeroUsize, time::Duration};
+
+#[cfg(feature = "js")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use aggligator::{
+    alc::DatagramError,
+    cfg::{Cfg, Extensions},
+    exec::time::{sleep, timeout},
+    transport::{
+        memory::{self, ChannelCfg},
+        AcceptorBuilder, ConnectorBuilder,
+    },
+};
+
+const COUNT: usize = 50;
+
+fn payload(n: usize) -> Bytes {
+    format!("datagram {n}").into()
+}
+
+async fn datagrams(client_cfg: Cfg, server_cfg: Cfg) {
+    let (memory_connector, memory_acceptor) = memory::transport();
+    let link_cfg = ChannelCfg { latency: Some(Duration::from_millis(5)), ..Default::default() };
+    memory_connector.add_link("a", link_cfg.clone());
+    memory_connector.add_link("b", link_cfg);
+
+    let acceptor = AcceptorBuilder::new(server_cfg).build();
+    acceptor.add(memory_acceptor);
+
+    let mut connector = ConnectorBuilder::new(client_cfg).build();
+    connector.add(memory_connector);
+    let outgoing = connector.channel().unwrap();
+
+    let server_task = async {
+        let (ch, control) = acceptor.accept().await.unwrap();
+        let mut datagram_rx = control.datagram_receiver();
+        let (tx, mut rx) = ch.into_tx_rx();
+
+        // Echo datagrams until the client closes the reliable channel.
+        let datagram_tx = control.datagram_sender();
+        let echo = async {
+            while let Some(data) = datagram_rx.recv().await {
+                datagram_tx.send(data).await.unwrap();
+            }
+        };
+        let reliable = async {
+            let data = rx.recv().await.unwrap().unwrap();
+            tx.send(data).await.unwrap();
+            assert!(rx.recv().await.unwrap().is_none());
+        };
+        tokio::select! {
+            () = reliable => (),
+            () = echo => panic!("datagram receiver ended"),
+        }
+    };
+
+    let client_task = async {
+        let control = connector.control();
+        let mut datagram_rx = control.datagram_receiver();
+        let datagram_tx = control.datagram_sender();
+        let (tx, mut rx) = outgoing.await.unwrap().into_tx_rx();
+
+        // Expired datagrams are discarded.
+        datagram_tx.send_with_expiry(Bytes::from_static(b"expired"), Duration::ZERO).await.unwrap();
+
+        assert_eq!(
+            datagram_tx.send(vec![0; datagram_tx.max_size() + 1].into()).await,
+            Err(DatagramError::TooBig)
+        );
+
+        let mut received = Vec::new();
+        for n in 0..COUNT {
+            datagram_tx.send(payload(n)).await.unwrap();
+            if let Ok(Some(data)) = timeout(Duration::from_millis(100), datagram_rx.recv()).await {
+                received.push(data);
+            }
+        }
+        while let Ok(Some(data)) = timeout(Duration::from_secs(1), datagram_rx.recv()).await {
+            received.push(data);
+        }
+
+        assert!(!received.iter().any(|data| data.as_ref() == b"expired"), "expired datagram was sent");
+        received.sort();
+        received.dedup();
+        assert_eq!(received.len(), COUNT, "datagrams were lost");
+
+        // Reliable channel keeps working alongside datagrams.
+        tx.send(Bytes::from_static(b"reliable")).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().unwrap(), Bytes::from_static(b"reliable"));
+        drop(tx);
+        drop(rx);
+    };
+
+    join!(server_task, client_task);
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn datagram() {
+    datagrams(Cfg::default(), Cfg::default()).await;
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn datagram_encrypted() {
+    let cfg = Cfg { encryption: true, ..Default::default() };
+    datagrams(cfg.clone(), cfg).await;
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn datagram_unsupported() {
+    let (memory_connector, memory_acceptor) = memory::transport();
+    memory_connector.add_link("a", ChannelCfg::default());
+
+    let server_cfg = Cfg {
+        extensions: Extensions::from_bits_truncate(Extensions::SUPPORTED.bits() & !Extensions::DATAGRAM.bits()),
+        ..Default::default()
+    };
+    let acceptor = AcceptorBuilder::new(server_cfg).build();
+    acceptor.add(memory_acceptor);
+
+    let mut connector = ConnectorBuilder::new(Cfg::default()).build();
+    connector.add(memory_connector);
+    let outgoing = connector.channel().unwrap();
+
+    let server_task = async {
+        let (_ch, control) = acceptor.accept().await.unwrap();
+        assert_eq!(control.datagram_sender().send(payload(0)).await, Err(DatagramError::Unsupported));
+    };
+
+    let client_task = async {
+        let _ch = outgoing.await.unwrap();
+        let control = connector.control();
+        assert_eq!(control.datagram_sender().send(payload(0)).await, Err(DatagramError::Unsupported));
+    };
+
+    join!(server_task, client_task);
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn datagram_too_big_for_receiver() {
+    const MAX_SIZE: usize = 100;
+
+    let (memory_connector, memory_acceptor) = memory::transport();
+    memory_connector.add_link("a", ChannelCfg::default());
+
+    let server_cfg = Cfg { datagram_max_size: NonZeroUsize::new(MAX_SIZE).unwrap(), ..Default::default() };
+    let acceptor = AcceptorBuilder::new(server_cfg).build();
+    acceptor.add(memory_acceptor);
+
+    let mut connector = ConnectorBuilder::new(Cfg::default()).build();
+    connector.add(memory_connector);
+    let outgoing = connector.channel().unwrap();
+
+    let server_task = async {
+        let (_ch, control) = acceptor.accept().await.unwrap();
+        let mut datagram_rx = control.datagram_receiver();
+        control.datagram_sender().send(payload(0)).await.unwrap();
+
+        // Only the datagram within the maximum size is received.
+        let data = datagram_rx.recv().await.unwrap();
+        assert_eq!(data.len(), MAX_SIZE);
+        assert!(
+            timeout(Duration::from_secs(1), datagram_rx.recv()).await.is_err(),
+            "oversized datagram received"
+        );
+    };
+
+    let client_task = async {
+        let _ch = outgoing.await.unwrap();
+        let control = connector.control();
+        let mut datagram_rx = control.datagram_receiver();
+        let datagram_tx = control.datagram_sender();
+
+        // Wait until the server is ready to receive datagrams.
+        assert_eq!(datagram_rx.recv().await.unwrap(), payload(0));
+
+        datagram_tx.send(vec![1; MAX_SIZE + 1].into()).await.unwrap();
+        datagram_tx.send(vec![2; MAX_SIZE].into()).await.unwrap();
+        sleep(Duration::from_secs(2)).await;
+    };
+
+    join!(server_task, client_task);
+}
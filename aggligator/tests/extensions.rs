@@ -0,0 +1,89 @@
+//! Protocol extension negotiation tests.
+
+use futures::join;
+use std::future::IntoFuture;
+
+#[cfg(feature = "js")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use aggligator::{
+    cfg::{Cfg, Extensions},
+    connect::{connect, Server},
+    exec,
+};
+
+mod common;
+mod test_channel;
+
+use common::packet;
+
+const COUNT: usize = 20;
+const SIZE: usize = 1024;
+
+/// Connects a client and a server offering the specified extensions and
+/// checks that both use the intersection of them.
+async fn negotiation_test(client_extensions: Extensions, server_extensions: Extensions) {
+    let expected = client_extensions & server_extensions;
+
+    let (link_a_tx, link_a_rx, _link_a_control) = test_channel::channel(test_channel::Cfg::default());
+    let (link_b_tx, link_b_rx, _link_b_control) = test_channel::channel(test_channel::Cfg::default());
+
+    let server_task = async move {
+        let server = Server::new(Cfg { extensions: server_extensions, ..Default::default() });
+        let mut listener = server.listen().unwrap();
+        server.add_incoming(link_b_tx, link_a_rx, "incoming", &[]).await.unwrap();
+
+        let incoming = listener.next().await.unwrap();
+        assert_eq!(incoming.extensions(), expected);
+        let (task, ch, control) = incoming.accept();
+        exec::spawn(task.into_future());
+        assert_eq!(control.extensions(), Some(expected));
+
+        let (_tx, mut rx) = ch.into_tx_rx();
+        for n in 0..COUNT {
+            assert_eq!(rx.recv().await.unwrap(), Some(packet(n, SIZE)), "data mismatch in message {n}");
+        }
+        assert_eq!(rx.recv().await.unwrap(), None);
+    };
+
+    let client_task = async move {
+        let (task, outgoing, control) = connect(Cfg { extensions: client_extensions, ..Default::default() });
+        exec::spawn(task.into_future());
+
+        assert_eq!(control.extensions(), None);
+        control.add(link_a_tx, link_b_rx, "outgoing", &[]).await.unwrap();
+        assert_eq!(control.extensions(), Some(expected));
+
+        let (tx, _rx) = outgoing.connect().await.unwrap().into_tx_rx();
+        for n in 0..COUNT {
+            tx.send(packet(n, SIZE)).await.unwrap();
+        }
+        tx.flush().await.unwrap();
+    };
+
+    join!(server_task, client_task);
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn same_extensions() {
+    let extensions = Cfg::default().offered_extensions();
+    negotiation_test(extensions, extensions).await;
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn different_extensions() {
+    negotiation_test(
+        Extensions::COMPRESSION | Extensions::FEC | Extensions::DATAGRAM,
+        Extensions::COMPRESSION | Extensions::DATAGRAM | Extensions::TIMESTAMPS,
+    )
+    .await;
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn no_common_extensions() {
+    negotiation_test(Extensions::FEC | Extensions::TIMESTAMPS, Extensions::COMPRESSION | Extensions::DATAGRAM)
+        .await;
+}
@@ -0,0 +1,89 @@
+//! Forward error correction tests.
+
+use futures::join;
+use std::{future::IntoFuture, num::NonZeroU8, time::Duration};
+
+#[cfg(feature = "js")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use aggligator::{
+    cfg::{Cfg, Extensions},
+    connect::{connect, Server},
+    exec,
+};
+
+mod common;
+mod test_channel;
+
+use common::packet;
+
+const COUNT: usize = 500;
+const SIZE: usize = 4096;
+
+/// Sends data from a client with forward error correction enabled over two links
+/// to a server offering the specified extensions.
+///
+/// One link is temporarily paused during the transfer.
+async fn fec_test(server_extensions: Extensions) {
+    let link_cfg = test_channel::Cfg { latency: Some(Duration::from_millis(5)), ..Default::default() };
+    let (link_a_tx, link_a_rx, link_a_control) = test_channel::channel(link_cfg.clone());
+    let (link_b_tx, link_b_rx, _link_b_control) = test_channel::channel(link_cfg.clone());
+    let (link_c_tx, link_c_rx, link_c_control) = test_channel::channel(link_cfg.clone());
+    let (link_d_tx, link_d_rx, _link_d_control) = test_channel::channel(link_cfg);
+
+    let server_task = async move {
+        let server = Server::new(Cfg { extensions: server_extensions, ..Default::default() });
+        let mut listener = server.listen().unwrap();
+        server.add_incoming(link_b_tx, link_a_rx, "incoming 1", &[]).await.unwrap();
+
+        let incoming = listener.next().await.unwrap();
+        assert_eq!(incoming.extensions().contains(Extensions::FEC), server_extensions.contains(Extensions::FEC));
+        let (task, ch, _control) = incoming.accept();
+        exec::spawn(task.into_future());
+        server.add_incoming(link_d_tx, link_c_rx, "incoming 2", &[]).await.unwrap();
+
+        let (_tx, mut rx) = ch.into_tx_rx();
+        let mut n = 0;
+        while let Some(data) = rx.recv().await.unwrap() {
+            assert_eq!(data, packet(n, SIZE), "data mismatch in message {n}");
+            n += 1;
+        }
+        assert_eq!(n, COUNT);
+    };
+
+    let client_task = async move {
+        let (task, outgoing, control) =
+            connect(Cfg { fec_group_size: Some(NonZeroU8::new(4).unwrap()), ..Default::default() });
+        exec::spawn(task.into_future());
+
+        control.add(link_a_tx, link_b_rx, "outgoing 1", &[]).await.unwrap();
+        let ch = outgoing.connect().await.unwrap();
+        control.add(link_c_tx, link_d_rx, "outgoing 2", &[]).await.unwrap();
+
+        let (tx, _rx) = ch.into_tx_rx();
+        for n in 0..COUNT {
+            if n == COUNT / 4 {
+                link_a_control.pause_for(Duration::from_millis(300)).await.unwrap();
+            }
+            if n == COUNT / 2 {
+                link_c_control.pause_for(Duration::from_millis(300)).await.unwrap();
+            }
+            tx.send(packet(n, SIZE)).await.unwrap();
+        }
+        tx.flush().await.unwrap();
+    };
+
+    join!(server_task, client_task);
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn fec() {
+    fec_test(Extensions::SUPPORTED).await;
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn fec_not_offered() {
+    fec_test(Extensions::NONE).await;
+}
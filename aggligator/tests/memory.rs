@@ -0,0 +1,93 @@
+//! In-memory transport tests.
+
+use futures::join;
+use std::time::Duration;
+
+#[cfg(feature = "js")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use aggligator::{
+    cfg::Cfg,
+    exec::time::{sleep, timeout},
+    transport::{
+        memory::{self, ChannelCfg},
+        AcceptorBuilder, ConnectorBuilder,
+    },
+};
+
+mod common;
+
+use common::packet;
+
+const COUNT: usize = 100;
+const SIZE: usize = 512;
+
+/// Transfers data over two simulated links while one is corrupted and the other silently fails.
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn link_failures() {
+    let cfg = Cfg { link_ping_timeout: Duration::from_secs(2), ..Default::default() };
+
+    let (memory_connector, memory_acceptor) = memory::transport();
+    let link_cfg = ChannelCfg {
+        latency: Some(Duration::from_millis(5)),
+        jitter: Duration::from_millis(5),
+        speed: 1_000_000,
+        ..Default::default()
+    };
+    let link_a = memory_connector.add_link("a", link_cfg.clone());
+    let link_b = memory_connector.add_link("b", link_cfg);
+
+    let acceptor = AcceptorBuilder::new(cfg.clone()).build();
+    acceptor.add(memory_acceptor);
+
+    let mut builder = ConnectorBuilder::new(cfg);
+    builder.set_reconnect_delay(Duration::from_millis(100));
+    let mut connector = builder.build();
+    connector.add(memory_connector);
+    let outgoing = connector.channel().unwrap();
+
+    let server_task = async {
+        let (ch, _control) = acceptor.accept().await.unwrap();
+        let (tx, mut rx) = ch.into_tx_rx();
+        for n in 0..COUNT {
+            let data = timeout(Duration::from_secs(30), rx.recv()).await.unwrap().unwrap().unwrap();
+            assert_eq!(data, packet(n, SIZE), "data mismatch in message {n}");
+            tx.send(data).await.unwrap();
+        }
+    };
+
+    let client_task = async {
+        let (tx, mut rx) = outgoing.await.unwrap().into_tx_rx();
+        let mut control = connector.control();
+        while control.links().len() < 2 {
+            control.links_changed().await;
+        }
+        assert!(link_a.is_connected());
+        assert!(link_b.is_connected());
+
+        for n in 0..COUNT {
+            match n {
+                20 => link_a.corrupt(1).await.unwrap(),
+                50 => link_b.lose_then_disconnect(Duration::from_secs(1)).await.unwrap(),
+                _ => (),
+            }
+
+            tx.send(packet(n, SIZE)).await.unwrap();
+            let data = timeout(Duration::from_secs(30), rx.recv()).await.unwrap().unwrap().unwrap();
+            assert_eq!(data, packet(n, SIZE), "echo mismatch in message {n}");
+            sleep(Duration::from_millis(20)).await;
+        }
+
+        // Both links must have been reconnected.
+        timeout(Duration::from_secs(30), async {
+            while control.links().len() < 2 {
+                control.links_changed().await;
+            }
+        })
+        .await
+        .unwrap();
+    };
+
+    join!(server_task, client_task);
+}
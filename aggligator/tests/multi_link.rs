@@ -419,6 +419,7 @@ async fn five_x_very_high_latency() {
             latency: Some(Duration::from_millis(1000)),
             buffer_size: 10_000_000,
             buffer_items: 50_000,
+            ..Default::default()
         },
         ..Default::default()
     };
@@ -605,6 +606,7 @@ async fn forceful_termination() {
             latency: Some(Duration::from_millis(1000)),
             buffer_size: 10_000_000,
             buffer_items: 50_000,
+            ..Default::default()
         },
         ..Default::default()
     };
@@ -0,0 +1,226 @@
+//! Stream multiplexer tests.
+
+use bytes::Bytes;
+use futures::{future, join};
+use std::{future::IntoFuture, io::ErrorKind};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[cfg(feature = "js")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use aggligator::{
+    alc::{Channel, Mux, MuxCfg, RecvError},
+    cfg::Cfg,
+    connect::{connect, Server},
+    control::Direction,
+    exec,
+};
+
+mod test_channel;
+
+/// Establishes a connection consisting of a single link and returns the channels of both ends.
+async fn channel_pair() -> (Channel, Channel) {
+    let (link_a_tx, link_a_rx, _link_a_control) = test_channel::channel(test_channel::Cfg::default());
+    let (link_b_tx, link_b_rx, _link_b_control) = test_channel::channel(test_channel::Cfg::default());
+
+    let server_task = async move {
+        let server = Server::new(Cfg::default());
+        let mut listener = server.listen().unwrap();
+        server.add_incoming(link_b_tx, link_a_rx, "incoming", &[]).await.unwrap();
+
+        let incoming = listener.next().await.unwrap();
+        let (task, ch, _control) = incoming.accept();
+        exec::spawn(task.into_future());
+        ch
+    };
+
+    let client_task = async move {
+        let (task, outgoing, control) = connect(Cfg::default());
+        exec::spawn(task.into_future());
+        control.add(link_a_tx, link_b_rx, "outgoing", &[]).await.unwrap();
+        outgoing.connect().await.unwrap()
+    };
+
+    join!(server_task, client_task)
+}
+
+/// Establishes a connection consisting of a single link and converts both ends into multiplexers.
+async fn mux_pair(mux_cfg: MuxCfg) -> (Mux, Mux) {
+    let (server_ch, client_ch) = channel_pair().await;
+    (Mux::with_cfg(server_ch, mux_cfg.clone()), Mux::with_cfg(client_ch, mux_cfg))
+}
+
+fn test_data(seed: usize, len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i.wrapping_mul(31) ^ seed) as u8).collect()
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn many_streams() {
+    const STREAMS: usize = 32;
+    const LEN: usize = 100_000;
+
+    let (mut server, client) = mux_pair(MuxCfg::default()).await;
+
+    let server_task = exec::spawn(async move {
+        let mut tasks = Vec::new();
+        while let Some(mut stream) = server.accept().await.unwrap() {
+            assert_eq!(stream.direction(), Direction::Incoming);
+            tasks.push(exec::spawn(async move {
+                let mut data = Vec::new();
+                stream.read_to_end(&mut data).await.unwrap();
+                data.reverse();
+                stream.write_all(&data).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }));
+            if tasks.len() == STREAMS {
+                break;
+            }
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+    });
+
+    let tasks: Vec<_> = (0..STREAMS)
+        .map(|i| {
+            let mut stream = client.open().unwrap();
+            assert_eq!(stream.direction(), Direction::Outgoing);
+            assert_eq!(stream.id(), i as u32);
+            exec::spawn(async move {
+                let data = test_data(i, LEN);
+                stream.write_all(&data).await.unwrap();
+                stream.shutdown().await.unwrap();
+
+                let mut reply = Vec::new();
+                stream.read_to_end(&mut reply).await.unwrap();
+                reply.reverse();
+                assert_eq!(reply, data, "data mismatch on sub-stream {i}");
+            })
+        })
+        .collect();
+
+    for res in future::join_all(tasks).await {
+        res.unwrap();
+    }
+    server_task.await.unwrap();
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn stalled_stream_does_not_block_others() {
+    const LEN: usize = 2_000_000;
+
+    let (mut server, client) = mux_pair(MuxCfg::default()).await;
+
+    let mut stalled = client.open().unwrap();
+    let mut active = client.open().unwrap();
+
+    let stalled_write = exec::spawn(async move {
+        let data = test_data(1, LEN);
+        stalled.write_all(&data).await.unwrap();
+        stalled.shutdown().await.unwrap();
+        stalled
+    });
+
+    let active_data = test_data(2, LEN);
+    let active_write = {
+        let data = active_data.clone();
+        exec::spawn(async move {
+            active.write_all(&data).await.unwrap();
+            active.shutdown().await.unwrap();
+            active
+        })
+    };
+
+    let mut stalled_remote = server.accept().await.unwrap().unwrap();
+    let mut active_remote = server.accept().await.unwrap().unwrap();
+
+    let mut received = Vec::new();
+    active_remote.read_to_end(&mut received).await.unwrap();
+    assert_eq!(received, active_data);
+    let _active = active_write.await.unwrap();
+    assert!(!stalled_write.is_finished(), "flow control of stalled sub-stream not enforced");
+
+    let mut received = Vec::new();
+    stalled_remote.read_to_end(&mut received).await.unwrap();
+    assert_eq!(received, test_data(1, LEN));
+    let _stalled = stalled_write.await.unwrap();
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn half_close_and_reset() {
+    let (server, mut client) = mux_pair(MuxCfg::default()).await;
+
+    // Server opens sub-stream and finishes its sending direction.
+    let mut a = server.open().unwrap();
+    a.write_all(b"request").await.unwrap();
+    a.shutdown().await.unwrap();
+    assert_eq!(a.write_all(b"more").await.unwrap_err().kind(), ErrorKind::BrokenPipe);
+
+    let mut b = client.accept().await.unwrap().unwrap();
+    let mut buf = Vec::new();
+    b.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"request");
+
+    // Other direction is still open.
+    b.write_all(b"response").await.unwrap();
+    b.flush().await.unwrap();
+    let mut buf = [0; 8];
+    a.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"response");
+
+    // Dropping an unfinished sub-stream resets it.
+    drop(b);
+    let err = a.read_u8().await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn receive_window_exceeded() {
+    const WINDOW: usize = 262_144;
+    const CHUNK: usize = 16_384;
+
+    let (server_ch, client_ch) = channel_pair().await;
+    let mut server = Mux::new(server_ch);
+    let (tx, mut rx) = client_ch.into_tx_rx();
+
+    // Misbehaving client opens a sub-stream and sends more than the receive window.
+    tx.send(Bytes::from_static(&[1, 0, 0, 0, 0])).await.unwrap();
+    for n in 0..WINDOW / CHUNK + 2 {
+        let mut frame = vec![2, 0, 0, 0, 0];
+        frame.extend(test_data(n, CHUNK));
+        tx.send(frame.into()).await.unwrap();
+    }
+    tx.flush().await.unwrap();
+
+    // Server resets the sub-stream.
+    let reset = rx.recv().await.unwrap().unwrap();
+    assert_eq!(&reset[..], &[0x85, 0, 0, 0, 0]);
+
+    let mut stream = server.accept().await.unwrap().unwrap();
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).await.unwrap_err();
+    assert!(buf.is_empty());
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn duplicate_open() {
+    let (server_ch, client_ch) = channel_pair().await;
+    let mut server = Mux::new(server_ch);
+    let (tx, _rx) = client_ch.into_tx_rx();
+
+    // Misbehaving client opens a sub-stream twice using the same id.
+    tx.send(Bytes::from_static(&[1, 0, 0, 0, 0])).await.unwrap();
+    tx.send(Bytes::from_static(&[1, 0, 0, 0, 0])).await.unwrap();
+    tx.flush().await.unwrap();
+
+    // First sub-stream fails instead of being replaced.
+    let mut stream = server.accept().await.unwrap().unwrap();
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).await.unwrap_err();
+    assert!(matches!(server.accept().await, Err(RecvError::ProtocolError)));
+}
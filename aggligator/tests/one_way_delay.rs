@@ -0,0 +1,154 @@
+//! One-way delay measurement tests.
+
+use bytes::Bytes;
+use futures::{future, join};
+use std::{
+    collections::HashMap,
+    future::IntoFuture,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[cfg(feature = "js")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use aggligator::{
+    cfg::{Cfg, Extensions, LinkPing},
+    connect::{connect, Server},
+    control::{Link, LinkStats, OneWayDelay},
+    exec::{self, time::sleep},
+    scheduler::{LinkScheduler, LinkState, LowestDelayLinkScheduler},
+};
+
+mod test_channel;
+
+/// Latencies of the simulated links from client to server and from server to client.
+const LINKS: [(&str, Duration, Duration); 2] = [
+    ("satellite", Duration::from_millis(20), Duration::from_millis(200)),
+    ("cellular", Duration::from_millis(60), Duration::from_millis(60)),
+];
+
+const TOLERANCE: Duration = Duration::from_millis(20);
+
+fn link_cfg(latency: Duration) -> test_channel::Cfg {
+    test_channel::Cfg { latency: Some(latency), ..Default::default() }
+}
+
+fn is_near(delay: Duration, expected: Duration) -> bool {
+    delay.max(expected) - delay.min(expected) <= TOLERANCE
+}
+
+fn assert_delay(delay: Option<OneWayDelay>, expected: Duration) {
+    let delay = delay.expect("one-way delay not measured");
+    assert!(is_near(delay.relative, expected), "relative delay is {delay:?} instead of {expected:?}");
+}
+
+fn link_stats(links: &[Link<&'static str>], tag: &str) -> LinkStats {
+    links.iter().find(|link| *link.tag() == tag).unwrap().stats()
+}
+
+/// Measures the one-way delays of an asymmetric and a symmetric link.
+async fn one_way_delay_test(server_extensions: Extensions) {
+    let timestamps = server_extensions.contains(Extensions::TIMESTAMPS);
+    let cfg = Cfg { link_ping: LinkPing::Periodic(Duration::from_millis(100)), ..Default::default() };
+
+    let mut server_links = Vec::new();
+    let mut client_links = Vec::new();
+    for (tag, up, down) in LINKS {
+        let (up_tx, up_rx, _up_control) = test_channel::channel(link_cfg(up));
+        let (down_tx, down_rx, _down_control) = test_channel::channel(link_cfg(down));
+        server_links.push((tag, down_tx, up_rx));
+        client_links.push((tag, up_tx, down_rx));
+    }
+
+    let server_cfg = Cfg { extensions: server_extensions, ..cfg.clone() };
+    let server_task = async move {
+        let server = Server::new(server_cfg);
+        let mut listener = server.listen().unwrap();
+        for (tag, tx, rx) in server_links {
+            server.add_incoming(tx, rx, tag, &[]).await.unwrap();
+        }
+
+        let incoming = listener.next().await.unwrap();
+        let (task, ch, control) = incoming.accept();
+        exec::spawn(task.into_future());
+
+        let (_tx, mut rx) = ch.into_tx_rx();
+        while rx.recv().await.unwrap().is_some() {}
+
+        let links = control.links();
+        if timestamps {
+            // From server to client the cellular link is faster.
+            assert_delay(link_stats(&links, "satellite").forward_delay, Duration::from_millis(140));
+            assert_delay(link_stats(&links, "cellular").forward_delay, Duration::ZERO);
+            assert_delay(link_stats(&links, "satellite").backward_delay, Duration::ZERO);
+            assert_delay(link_stats(&links, "cellular").backward_delay, Duration::from_millis(40));
+        } else {
+            assert!(links.iter().all(|link| link.stats().forward_delay.is_none()));
+        }
+    };
+
+    let forward_delays = Arc::new(Mutex::new(HashMap::new()));
+    let client_task = async {
+        let (mut task, outgoing, control) = connect(cfg);
+
+        // Record forward delays presented to the scheduler.
+        {
+            let forward_delays = forward_delays.clone();
+            let mut scheduler = LowestDelayLinkScheduler;
+            task.set_link_scheduler(move |links: &[LinkState<&'static str>]| {
+                let mut forward_delays = forward_delays.lock().unwrap();
+                for link in links {
+                    forward_delays.insert(*link.tag(), link.forward_delay());
+                }
+                scheduler.select(links)
+            });
+        }
+        exec::spawn(task.into_future());
+
+        let add_links = client_links.into_iter().map(|(tag, tx, rx)| control.add(tx, rx, tag, &[]));
+        let links = future::try_join_all(add_links).await.unwrap();
+
+        let ch = outgoing.connect().await.unwrap();
+        let (tx, _rx) = ch.into_tx_rx();
+        for n in 0..30u8 {
+            tx.send(Bytes::from(vec![n; 100])).await.unwrap();
+            sleep(Duration::from_millis(100)).await;
+        }
+        tx.flush().await.unwrap();
+
+        if timestamps {
+            // From client to server the satellite link is faster.
+            assert_delay(link_stats(&links, "satellite").forward_delay, Duration::ZERO);
+            assert_delay(link_stats(&links, "cellular").forward_delay, Duration::from_millis(40));
+            assert_delay(link_stats(&links, "satellite").backward_delay, Duration::from_millis(140));
+            assert_delay(link_stats(&links, "cellular").backward_delay, Duration::ZERO);
+        } else {
+            assert!(links.iter().all(|link| link.stats().forward_delay.is_none()));
+        }
+    };
+
+    join!(server_task, client_task);
+
+    let forward_delays = forward_delays.lock().unwrap();
+    tracing::info!("forward delays: {forward_delays:?}");
+    for (tag, up, down) in LINKS {
+        let expected = if timestamps { up } else { (up + down) / 2 };
+        let delay = forward_delays[tag];
+        assert!(is_near(delay, expected), "forward delay of {tag} is {delay:?} instead of {expected:?}");
+    }
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn one_way_delay() {
+    one_way_delay_test(Extensions::SUPPORTED).await;
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn one_way_delay_unsupported() {
+    let extensions =
+        Extensions::from_bits_truncate(Extensions::SUPPORTED.bits() & !Extensions::TIMESTAMPS.bits());
+    one_way_delay_test(extensions).await;
+}
@@ -0,0 +1,129 @@
+//! Send priority tests.
+
+use bytes::Bytes;
+use futures::join;
+use std::time::Duration;
+
+#[cfg(feature = "js")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use aggligator::{
+    alc::SendPriority,
+    cfg::Cfg,
+    exec::time::sleep,
+    transport::{
+        memory::{self, ChannelCfg},
+        AcceptorBuilder, ConnectorBuilder,
+    },
+};
+
+const BULK_COUNT: usize = 200;
+const BULK_SIZE: usize = 1000;
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn high_priority_overtakes_bulk() {
+    let (memory_connector, memory_acceptor) = memory::transport();
+    memory_connector.add_link(
+        "slow",
+        ChannelCfg { speed: 100_000, latency: Some(Duration::from_millis(5)), ..Default::default() },
+    );
+
+    let acceptor = AcceptorBuilder::new(Cfg::default()).build();
+    acceptor.add(memory_acceptor);
+
+    let mut connector = ConnectorBuilder::new(Cfg::default()).build();
+    connector.add(memory_connector);
+    let outgoing = connector.channel().unwrap();
+
+    let server_task = async {
+        let (ch, _control) = acceptor.accept().await.unwrap();
+        let (_tx, mut rx) = ch.into_tx_rx();
+
+        let mut received = Vec::new();
+        while let Some(data) = rx.recv().await.unwrap() {
+            received.push(data);
+        }
+        received
+    };
+
+    let client_task = async {
+        let (tx, _rx) = outgoing.await.unwrap().into_tx_rx();
+
+        for n in 0..BULK_COUNT {
+            let mut data = vec![0; BULK_SIZE];
+            data[..8].copy_from_slice(&(n as u64).to_be_bytes());
+            tx.send_with_priority(data.into(), SendPriority::Low).await.unwrap();
+        }
+        tx.send_with_priority(Bytes::from_static(b"urgent"), SendPriority::High).await.unwrap();
+
+        // Flushing waits for data of all priorities.
+        tx.flush().await.unwrap();
+    };
+
+    let (received, ()) = join!(server_task, client_task);
+
+    assert_eq!(received.len(), BULK_COUNT + 1);
+    let urgent = received.iter().position(|data| data.as_ref() == b"urgent").unwrap();
+    assert!(urgent < BULK_COUNT / 2, "urgent message received at position {urgent}");
+
+    // Data of the same priority is received in order.
+    let bulk: Vec<_> = received
+        .iter()
+        .filter(|data| data.len() == BULK_SIZE)
+        .map(|data| u64::from_be_bytes(data[..8].try_into().unwrap()))
+        .collect();
+    assert_eq!(bulk, (0..BULK_COUNT as u64).collect::<Vec<_>>());
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn low_priority_not_starved() {
+    let (memory_connector, memory_acceptor) = memory::transport();
+    memory_connector.add_link(
+        "slow",
+        ChannelCfg { speed: 100_000, latency: Some(Duration::from_millis(5)), ..Default::default() },
+    );
+
+    let acceptor = AcceptorBuilder::new(Cfg::default()).build();
+    acceptor.add(memory_acceptor);
+
+    let mut connector = ConnectorBuilder::new(Cfg::default()).build();
+    connector.add(memory_connector);
+    let outgoing = connector.channel().unwrap();
+
+    let server_task = async {
+        let (ch, _control) = acceptor.accept().await.unwrap();
+        let (_tx, mut rx) = ch.into_tx_rx();
+
+        let mut received = Vec::new();
+        while let Some(data) = rx.recv().await.unwrap() {
+            received.push(data);
+        }
+        received
+    };
+
+    let client_task = async {
+        let (tx, _rx) = outgoing.await.unwrap().into_tx_rx();
+
+        // High priority data is sent continuously, while one low priority message is queued.
+        let high = async {
+            for _ in 0..BULK_COUNT {
+                tx.send_with_priority(vec![0; BULK_SIZE].into(), SendPriority::High).await.unwrap();
+            }
+        };
+        let low = async {
+            sleep(Duration::from_millis(200)).await;
+            tx.send_with_priority(Bytes::from_static(b"bulk"), SendPriority::Low).await.unwrap();
+        };
+        join!(high, low);
+
+        tx.flush().await.unwrap();
+    };
+
+    let (received, ()) = join!(server_task, client_task);
+
+    assert_eq!(received.len(), BULK_COUNT + 1);
+    let low = received.iter().position(|data| data.as_ref() == b"bulk").unwrap();
+    assert!(low < BULK_COUNT / 2, "low priority message received at position {low}");
+}
@@ -0,0 +1,133 @@
+//! Redundant sending tests.
+
+use futures::join;
+use std::{future::IntoFuture, num::NonZeroUsize, time::Duration};
+
+#[cfg(feature = "js")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use aggligator::{
+    cfg::Cfg,
+    connect::{connect, Server},
+    exec,
+};
+
+mod common;
+mod test_channel;
+
+use common::packet;
+
+const COUNT: usize = 200;
+const SIZE: usize = 1024;
+
+/// Sends data redundantly over two links, one of which stalls during the transfer.
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn redundant() {
+    let link_cfg = test_channel::Cfg { latency: Some(Duration::from_millis(5)), ..Default::default() };
+    let (link_a_tx, link_a_rx, link_a_control) = test_channel::channel(link_cfg.clone());
+    let (link_b_tx, link_b_rx, _link_b_control) = test_channel::channel(link_cfg.clone());
+    let (link_c_tx, link_c_rx, _link_c_control) = test_channel::channel(link_cfg.clone());
+    let (link_d_tx, link_d_rx, _link_d_control) = test_channel::channel(link_cfg);
+
+    let server_task = async move {
+        let server = Server::new(Cfg::default());
+        let mut listener = server.listen().unwrap();
+        server.add_incoming(link_b_tx, link_a_rx, "incoming 1", &[]).await.unwrap();
+
+        let (task, ch, _control) = listener.next().await.unwrap().accept();
+        exec::spawn(task.into_future());
+        server.add_incoming(link_d_tx, link_c_rx, "incoming 2", &[]).await.unwrap();
+
+        let (_tx, mut rx) = ch.into_tx_rx();
+        for n in 0..COUNT {
+            let data = exec::time::timeout(Duration::from_secs(10), rx.recv())
+                .await
+                .unwrap_or_else(|_| panic!("message {n} was delayed by stalled link"))
+                .unwrap()
+                .unwrap();
+            assert_eq!(data, packet(n, SIZE), "data mismatch in message {n}");
+        }
+    };
+
+    let client_task = async move {
+        // Resending of data sent over the stalled link is delayed by the long acknowledgement timeout.
+        let cfg = Cfg {
+            redundancy: NonZeroUsize::new(2).unwrap(),
+            link_ack_timeout_min: Duration::from_secs(30),
+            ..Default::default()
+        };
+        let (task, outgoing, control) = connect(cfg);
+        exec::spawn(task.into_future());
+
+        control.add(link_a_tx, link_b_rx, "outgoing 1", &[]).await.unwrap();
+        let ch = outgoing.connect().await.unwrap();
+        control.add(link_c_tx, link_d_rx, "outgoing 2", &[]).await.unwrap();
+
+        let (tx, _rx) = ch.into_tx_rx();
+        for n in 0..COUNT {
+            if n == COUNT / 2 {
+                let link_a_control = link_a_control.clone();
+                exec::spawn(async move { link_a_control.pause_for(Duration::from_secs(60)).await });
+            }
+            tx.send(packet(n, SIZE)).await.unwrap();
+            exec::time::sleep(Duration::from_millis(1)).await;
+        }
+        tx
+    };
+
+    join!(server_task, client_task);
+}
+
+/// Checks that redundant copies are released from the unacknowledged data of the links.
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn redundant_copies_acked() {
+    let link_cfg = test_channel::Cfg { latency: Some(Duration::from_millis(5)), ..Default::default() };
+    let (link_a_tx, link_a_rx, _link_a_control) = test_channel::channel(link_cfg.clone());
+    let (link_b_tx, link_b_rx, _link_b_control) = test_channel::channel(link_cfg.clone());
+    let (link_c_tx, link_c_rx, _link_c_control) = test_channel::channel(link_cfg.clone());
+    let (link_d_tx, link_d_rx, _link_d_control) = test_channel::channel(link_cfg);
+
+    let server_task = async move {
+        let server = Server::new(Cfg::default());
+        let mut listener = server.listen().unwrap();
+        server.add_incoming(link_b_tx, link_a_rx, "incoming 1", &[]).await.unwrap();
+
+        let (task, ch, _control) = listener.next().await.unwrap().accept();
+        exec::spawn(task.into_future());
+        server.add_incoming(link_d_tx, link_c_rx, "incoming 2", &[]).await.unwrap();
+
+        let (_tx, mut rx) = ch.into_tx_rx();
+        for n in 0..COUNT {
+            assert_eq!(rx.recv().await.unwrap(), Some(packet(n, SIZE)), "data mismatch in message {n}");
+        }
+        assert_eq!(rx.recv().await.unwrap(), None);
+    };
+
+    let client_task = async move {
+        let cfg = Cfg { redundancy: NonZeroUsize::new(2).unwrap(), ..Default::default() };
+        let (task, outgoing, control) = connect(cfg);
+        exec::spawn(task.into_future());
+
+        control.add(link_a_tx, link_b_rx, "outgoing 1", &[]).await.unwrap();
+        let ch = outgoing.connect().await.unwrap();
+        control.add(link_c_tx, link_d_rx, "outgoing 2", &[]).await.unwrap();
+
+        let (tx, _rx) = ch.into_tx_rx();
+        for n in 0..COUNT {
+            tx.send(packet(n, SIZE)).await.unwrap();
+        }
+        tx.flush().await.unwrap();
+
+        exec::time::timeout(Duration::from_secs(10), async {
+            while control.links().iter().any(|link| link.stats().sent_unacked != 0) {
+                exec::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("redundant copies remain unacknowledged");
+    };
+
+    join!(server_task, client_task);
+}
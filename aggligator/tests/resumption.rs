@@ -0,0 +1,231 @@
+//! Session resumption tests.
+
+use futures::join;
+use std::{future::IntoFuture, num::NonZeroUsize, time::Duration};
+
+#[cfg(feature = "js")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use aggligator::{
+    cfg::Cfg,
+    connect::{connect, IncomingError, Server},
+    control::{AddLinkError, ResumptionTicket},
+    exec::{self, time::sleep},
+    TaskError,
+};
+
+mod common;
+mod test_channel;
+
+use common::packet;
+
+const COUNT: usize = 200;
+const CONSUMED: usize = 50;
+const SIZE: usize = 8192;
+
+fn link_cfg() -> test_channel::Cfg {
+    test_channel::Cfg { latency: Some(Duration::from_millis(20)), buffer_size: 1 << 20, ..Default::default() }
+}
+
+fn slow_link_cfg() -> test_channel::Cfg {
+    test_channel::Cfg { latency: Some(Duration::from_millis(200)), ..link_cfg() }
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn resumed_on_new_server() {
+    let (link_a_tx, link_a_rx, _link_a_control) = test_channel::channel(slow_link_cfg());
+    let (link_b_tx, link_b_rx, link_b_control) = test_channel::channel(slow_link_cfg());
+    let (link_c_tx, link_c_rx, _link_c_control) = test_channel::channel(link_cfg());
+    let (link_d_tx, link_d_rx, _link_d_control) = test_channel::channel(link_cfg());
+
+    let cfg = Cfg {
+        session_resumption: true,
+        link_unacked_init: NonZeroUsize::new(1 << 20).unwrap(),
+        ..Default::default()
+    };
+    let server_a = Server::new(cfg.clone());
+    let server_b = Server::new(cfg.clone());
+    let server_b_id = server_b.id();
+
+    let (task, outgoing, control) = connect(cfg);
+    exec::spawn(task.into_future());
+
+    // Connect to first server.
+    let server_a_task = async move {
+        let mut listener = server_a.listen().unwrap();
+        server_a.add_incoming(link_b_tx, link_a_rx, "incoming a", &[]).await.unwrap();
+
+        let (task, ch, control) = listener.next().await.unwrap().accept();
+        let task = exec::spawn(task.into_future());
+        let (tx, rx) = ch.into_tx_rx();
+        (tx, rx, control, task)
+    };
+    let client_task = async {
+        let link = control.add(link_a_tx, link_b_rx, "outgoing a", &[]).await.unwrap();
+        let ch = outgoing.connect().await.unwrap();
+        (link, ch)
+    };
+    let ((server_a_tx, mut server_a_rx, server_a_control, server_a_task), (link_a, ch)) =
+        join!(server_a_task, client_task);
+
+    // The client must receive the data of both servers exactly once and in order.
+    let (tx, mut rx) = ch.into_tx_rx();
+    let sender = exec::spawn(async move {
+        for n in 0..COUNT {
+            tx.send(packet(n, SIZE)).await.unwrap();
+            sleep(Duration::from_millis(20)).await;
+        }
+    });
+    let receiver = exec::spawn(async move {
+        for n in 0..2 * COUNT {
+            assert_eq!(rx.recv().await.unwrap().unwrap(), packet(n, SIZE), "client data mismatch in message {n}");
+        }
+        assert_eq!(rx.recv().await.unwrap(), None);
+    });
+
+    // Consume part of the data on first server, while the client is still sending.
+    for n in 0..CONSUMED {
+        assert_eq!(
+            server_a_rx.recv().await.unwrap().unwrap(),
+            packet(n, SIZE),
+            "server data mismatch in message {n}"
+        );
+    }
+
+    // Send data from first server and lose the data in flight when it fails.
+    for n in 0..COUNT {
+        server_a_tx.send(packet(n, SIZE)).await.unwrap();
+    }
+    server_a_tx.flush().await.unwrap();
+    link_b_control.disconnect().await.unwrap();
+
+    // Suspend connection on first server.
+    let ticket = server_a_control.suspend().await.unwrap();
+    tracing::info!(?ticket, "suspended connection on first server");
+    assert_eq!(server_a_task.await.unwrap(), Err(TaskError::Suspended));
+    let ticket = ResumptionTicket::from_bytes(&ticket.to_bytes()).unwrap();
+
+    // Data passed to the receiver of the first server counts as delivered.
+    let mut delivered = CONSUMED;
+    while let Ok(Some(data)) = server_a_rx.recv().await {
+        assert_eq!(data, packet(delivered, SIZE), "server data mismatch in message {delivered}");
+        delivered += 1;
+    }
+    tracing::info!("first server received {delivered} messages");
+    link_a.disconnected().await;
+
+    // Connect to second server, which must receive exactly the remaining data.
+    server_b.resume(ticket);
+    let server_b_task = async move {
+        let mut listener = server_b.listen().unwrap();
+        server_b.add_incoming(link_d_tx, link_c_rx, "incoming b", &[]).await.unwrap();
+
+        let incoming = listener.next().await.unwrap();
+        assert!(incoming.is_resumed());
+        let (task, ch, _control) = incoming.accept();
+        let task = exec::spawn(task.into_future());
+
+        let (tx, mut rx) = ch.into_tx_rx();
+        for n in delivered..COUNT {
+            assert_eq!(rx.recv().await.unwrap().unwrap(), packet(n, SIZE), "server data mismatch in message {n}");
+        }
+        assert_eq!(rx.recv().await.unwrap(), None);
+
+        for n in COUNT..2 * COUNT {
+            tx.send(packet(n, SIZE)).await.unwrap();
+        }
+        drop(tx);
+        task.await.unwrap().unwrap();
+    };
+    let client_task = async {
+        control.add(link_c_tx, link_d_rx, "outgoing b", &[]).await.unwrap();
+        assert_eq!(control.remote_server_id().await, Some(server_b_id));
+        sender.await.unwrap();
+        receiver.await.unwrap();
+    };
+    join!(server_b_task, client_task);
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn resumed_without_ticket() {
+    let (link_a_tx, link_a_rx, _link_a_control) = test_channel::channel(link_cfg());
+    let (link_b_tx, link_b_rx, _link_b_control) = test_channel::channel(link_cfg());
+    let (link_c_tx, link_c_rx, _link_c_control) = test_channel::channel(link_cfg());
+    let (link_d_tx, link_d_rx, _link_d_control) = test_channel::channel(link_cfg());
+
+    let cfg = Cfg { session_resumption: true, no_link_timeout: Duration::from_secs(1), ..Default::default() };
+    let server_a = Server::new(cfg.clone());
+    let server_a_id = server_a.id();
+    let server_b = Server::new(cfg.clone());
+
+    let (task, outgoing, control) = connect(cfg);
+    let task = exec::spawn(task.into_future());
+
+    let server_a_task = async move {
+        let mut listener = server_a.listen().unwrap();
+        server_a.add_incoming(link_b_tx, link_a_rx, "incoming a", &[]).await.unwrap();
+
+        let (task, ch, control) = listener.next().await.unwrap().accept();
+        exec::spawn(task.into_future());
+        (ch, control)
+    };
+    let client_task = async {
+        let link = control.add(link_a_tx, link_b_rx, "outgoing a", &[]).await.unwrap();
+        let ch = outgoing.connect().await.unwrap();
+        (link, ch)
+    };
+    let ((_server_a_ch, server_a_control), (link_a, _ch)) = join!(server_a_task, client_task);
+
+    // Suspend connection, but do not pass ticket to second server.
+    server_a_control.suspend().await.unwrap();
+    link_a.disconnected().await;
+
+    // Second server refuses to resume the session.
+    let _listener_b = server_b.listen().unwrap();
+    let (server_res, client_res) = join!(
+        server_b.add_incoming(link_d_tx, link_c_rx, "incoming b", &[]),
+        control.add(link_c_tx, link_d_rx, "outgoing b", &[])
+    );
+    assert!(matches!(server_res, Err(IncomingError::NotResumable)), "wrong server error: {server_res:?}");
+    assert!(matches!(client_res, Err(AddLinkError::NotResumable)), "wrong client error: {client_res:?}");
+    assert_eq!(control.remote_server_id().await, Some(server_a_id));
+
+    assert_eq!(task.await.unwrap(), Err(TaskError::NoLinksTimeout));
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn server_changed_without_resumption() {
+    let (link_a_tx, link_a_rx, _link_a_control) = test_channel::channel(test_channel::Cfg::default());
+    let (link_b_tx, link_b_rx, _link_b_control) = test_channel::channel(test_channel::Cfg::default());
+    let (link_c_tx, link_c_rx, _link_c_control) = test_channel::channel(test_channel::Cfg::default());
+    let (link_d_tx, link_d_rx, _link_d_control) = test_channel::channel(test_channel::Cfg::default());
+
+    let server_a = Server::new(Cfg::default());
+    let server_b = Server::new(Cfg::default());
+    let _listener_b = server_b.listen().unwrap();
+
+    let (task, _outgoing, control) = connect(Cfg::default());
+    let task = exec::spawn(task.into_future());
+
+    let server_a_task = async move {
+        let mut listener = server_a.listen().unwrap();
+        server_a.add_incoming(link_b_tx, link_a_rx, "incoming a", &[]).await.unwrap();
+
+        let (task, _ch, control) = listener.next().await.unwrap().accept();
+        exec::spawn(task.into_future());
+        control
+    };
+    let (_server_a_control, link) = join!(server_a_task, control.add(link_a_tx, link_b_rx, "outgoing a", &[]));
+    link.unwrap();
+
+    exec::spawn(async move {
+        let _ = server_b.add_incoming(link_d_tx, link_c_rx, "incoming b", &[]).await;
+    });
+    let link = control.add(link_c_tx, link_d_rx, "outgoing b", &[]).await;
+    assert!(matches!(link, Err(AddLinkError::ServerIdMismatch { .. })));
+
+    assert_eq!(task.await.unwrap(), Err(TaskError::ServerIdMismatch));
+}
@@ -0,0 +1,156 @@
+//! Link scheduler tests.
+
+use bytes::Bytes;
+use futures::{future, join};
+use std::{
+    future::IntoFuture,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+#[cfg(feature = "js")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use aggligator::{
+    cfg::Cfg,
+    connect::{connect, Server},
+    exec,
+    scheduler::{DefaultLinkScheduler, LinkScheduler, LinkState},
+};
+
+mod test_channel;
+
+/// Sends data over a connection consisting of a primary and a backup link.
+///
+/// Verifies that the backup link is not used for data while the primary link is working
+/// and that it takes over once the primary link has been disconnected.
+async fn primary_backup_test(mut scheduler: impl LinkScheduler<&'static str> + 'static, backup_priority: u8) {
+    const COUNT: usize = 200;
+    const SIZE: usize = 4096;
+
+    let mut server_links = Vec::new();
+    let mut client_links = Vec::new();
+    for tag in ["primary", "backup"] {
+        let (link_a_tx, link_a_rx, _link_a_control) = test_channel::channel(test_channel::Cfg::default());
+        let (link_b_tx, link_b_rx, _link_b_control) = test_channel::channel(test_channel::Cfg::default());
+        server_links.push((tag, link_b_tx, link_a_rx));
+        client_links.push((tag, link_a_tx, link_b_rx));
+    }
+
+    let server_task = async move {
+        let server = Server::new(Cfg::default());
+        let mut listener = server.listen().unwrap();
+        for (tag, tx, rx) in server_links {
+            server.add_incoming(tx, rx, tag, &[]).await.unwrap();
+        }
+
+        let incoming = listener.next().await.unwrap();
+        let (task, ch, _control) = incoming.accept();
+        exec::spawn(task.into_future());
+
+        let (_tx, mut rx) = ch.into_tx_rx();
+        let mut n = 0;
+        while let Some(data) = rx.recv().await.unwrap() {
+            assert_eq!(data, Bytes::from(vec![n as u8; SIZE]), "data mismatch in message {n}");
+            n += 1;
+        }
+        n
+    };
+
+    let primary_selected = Arc::new(AtomicUsize::new(0));
+    let backup_selected = Arc::new(AtomicUsize::new(0));
+
+    let client_task = async {
+        let (mut task, outgoing, control) = connect(Cfg::default());
+
+        // Count links selected for sending data.
+        {
+            let primary_selected = primary_selected.clone();
+            let backup_selected = backup_selected.clone();
+            task.set_link_scheduler(move |links: &[LinkState<&'static str>]| {
+                let idx = scheduler.select(links)?;
+                if links[idx].is_ready() {
+                    match *links[idx].tag() {
+                        "primary" => primary_selected.fetch_add(1, Ordering::SeqCst),
+                        _ => backup_selected.fetch_add(1, Ordering::SeqCst),
+                    };
+                }
+                Some(idx)
+            });
+        }
+        exec::spawn(task.into_future());
+
+        let add_links = client_links.into_iter().map(|(tag, tx, rx)| control.add(tx, rx, tag, &[]));
+        let links = future::try_join_all(add_links).await.unwrap();
+        let primary = links.iter().find(|link| *link.tag() == "primary").unwrap();
+        let backup = links.iter().find(|link| *link.tag() == "backup").unwrap();
+        backup.set_priority(backup_priority);
+        assert_eq!(backup.priority(), backup_priority);
+
+        let ch = outgoing.connect().await.unwrap();
+        let (tx, _rx) = ch.into_tx_rx();
+        let mut n = 0;
+
+        // Send until primary link has been confirmed.
+        while primary_selected.load(Ordering::SeqCst) == 0 {
+            tx.send(Bytes::from(vec![n as u8; SIZE])).await.unwrap();
+            n += 1;
+        }
+        tx.flush().await.unwrap();
+
+        let backup_before = backup_selected.load(Ordering::SeqCst);
+        for _ in 0..COUNT {
+            tx.send(Bytes::from(vec![n as u8; SIZE])).await.unwrap();
+            n += 1;
+        }
+        tx.flush().await.unwrap();
+        assert_eq!(
+            backup_selected.load(Ordering::SeqCst),
+            backup_before,
+            "backup link used while primary link is working"
+        );
+
+        // Fail over to backup link.
+        primary.start_disconnect();
+        primary.disconnected().await;
+
+        let backup_before = backup_selected.load(Ordering::SeqCst);
+        for _ in 0..COUNT {
+            tx.send(Bytes::from(vec![n as u8; SIZE])).await.unwrap();
+            n += 1;
+        }
+        tx.flush().await.unwrap();
+        assert!(
+            backup_selected.load(Ordering::SeqCst) > backup_before,
+            "backup link not used after primary link failed"
+        );
+
+        n
+    };
+
+    let (received, sent) = join!(server_task, client_task);
+    assert_eq!(received, sent);
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn primary_backup_scheduler() {
+    // Use backup link only when primary link is not working.
+    let scheduler = |links: &[LinkState<&'static str>]| {
+        let primary = links.iter().position(|link| *link.tag() == "primary");
+        match primary {
+            Some(idx) if links[idx].is_ready() => Some(idx),
+            Some(idx) if links[idx].is_working() => None,
+            _ => links.iter().position(|link| link.is_ready() && *link.tag() == "backup"),
+        }
+    };
+    primary_backup_test(scheduler, 0).await;
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn primary_backup_priority() {
+    primary_backup_test(DefaultLinkScheduler, 1).await;
+}
@@ -0,0 +1,250 @@
+//! Encryption and authentication tests.
+
+use futures::{future, join};
+use std::future::IntoFuture;
+
+#[cfg(feature = "js")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use aggligator::{
+    cfg::{Cfg, Extensions, PreSharedKey},
+    connect::{connect, IncomingError, Server},
+    control::AddLinkError,
+    exec,
+    transport::{
+        memory::{self, ChannelCfg},
+        AcceptorBuilder, ConnectorBuilder,
+    },
+};
+
+mod common;
+mod test_channel;
+
+use common::packet;
+
+const COUNT: usize = 100;
+const SIZE: usize = 1600;
+
+/// Exchanges data between a client and a server using the specified configuration.
+async fn exchange(cfg: Cfg) {
+    let extensions = cfg.offered_extensions();
+    let client_cfg = cfg.clone();
+    let server_cfg = cfg;
+
+    let (link_a_tx, link_a_rx, _link_a_control) = test_channel::channel(test_channel::Cfg::default());
+    let (link_b_tx, link_b_rx, _link_b_control) = test_channel::channel(test_channel::Cfg::default());
+
+    let server_task = async move {
+        let server = Server::new(server_cfg);
+        let mut listener = server.listen().unwrap();
+        server.add_incoming(link_b_tx, link_a_rx, "incoming", &[]).await.unwrap();
+
+        let incoming = listener.next().await.unwrap();
+        assert_eq!(incoming.extensions(), extensions);
+        let (task, ch, _control) = incoming.accept();
+        exec::spawn(task.into_future());
+
+        let (tx, mut rx) = ch.into_tx_rx();
+        for n in 0..COUNT {
+            assert_eq!(rx.recv().await.unwrap(), Some(packet(n, SIZE)), "data mismatch in message {n}");
+            tx.send(packet(n, SIZE)).await.unwrap();
+        }
+        tx.flush().await.unwrap();
+        assert_eq!(rx.recv().await.unwrap(), None);
+    };
+
+    let client_task = async move {
+        let (task, outgoing, control) = connect(client_cfg);
+        exec::spawn(task.into_future());
+
+        control.add(link_a_tx, link_b_rx, "outgoing", &[]).await.unwrap();
+        assert_eq!(control.extensions(), Some(extensions));
+
+        let ch = outgoing.connect().await.unwrap();
+        let (tx, mut rx) = ch.into_tx_rx();
+        for n in 0..COUNT {
+            tx.send(packet(n, SIZE)).await.unwrap();
+            assert_eq!(rx.recv().await.unwrap(), Some(packet(n, SIZE)), "data mismatch in message {n}");
+        }
+    };
+
+    join!(server_task, client_task);
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn encrypted() {
+    exchange(Cfg { encryption: true, ..Default::default() }).await;
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn encrypted_psk_compressed() {
+    let cfg = Cfg {
+        encryption: true,
+        psk: Some(PreSharedKey::new("swordfish")),
+        compression: true,
+        ..Default::default()
+    };
+    assert!(cfg.offered_extensions().contains(Extensions::ENCRYPTION | Extensions::AUTHENTICATION));
+    exchange(cfg).await;
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn encrypted_parallel_links() {
+    // Links are connected concurrently, but both endpoints must derive
+    // the keys from the same link.
+    let cfg = Cfg { encryption: true, ..Default::default() };
+    let (memory_connector, memory_acceptor) = memory::transport();
+    for name in ["a", "b", "c"] {
+        memory_connector.add_link(name, ChannelCfg::default());
+    }
+
+    let acceptor = AcceptorBuilder::new(cfg.clone()).build();
+    acceptor.add(memory_acceptor);
+
+    let mut connector = ConnectorBuilder::new(cfg).build();
+    connector.add(memory_connector);
+    let outgoing = connector.channel().unwrap();
+
+    let server_task = async {
+        let (ch, _control) = acceptor.accept().await.unwrap();
+        let (tx, mut rx) = ch.into_tx_rx();
+        for n in 0..COUNT {
+            assert_eq!(rx.recv().await.unwrap(), Some(packet(n, SIZE)), "data mismatch in message {n}");
+            tx.send(packet(n, SIZE)).await.unwrap();
+        }
+        tx.flush().await.unwrap();
+    };
+
+    let client_task = async {
+        let (tx, mut rx) = outgoing.await.unwrap().into_tx_rx();
+        for n in 0..COUNT {
+            tx.send(packet(n, SIZE)).await.unwrap();
+            assert_eq!(rx.recv().await.unwrap(), Some(packet(n, SIZE)), "data mismatch in message {n}");
+        }
+        assert_eq!(connector.control().links().len(), 3);
+    };
+
+    join!(server_task, client_task);
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn encrypted_links_added_before_accept() {
+    // All links are added on both endpoints before the server accepts the connection.
+    let cfg = Cfg { encryption: true, ..Default::default() };
+
+    let mut server_links = Vec::new();
+    let mut client_links = Vec::new();
+    for name in ["a", "b", "c"] {
+        let (up_tx, up_rx, _) = test_channel::channel(test_channel::Cfg::default());
+        let (down_tx, down_rx, _) = test_channel::channel(test_channel::Cfg::default());
+        server_links.push((name, down_tx, up_rx));
+        client_links.push((name, up_tx, down_rx));
+    }
+
+    let server_cfg = cfg.clone();
+    let server_task = async move {
+        let server = Server::new(server_cfg);
+        let mut listener = server.listen().unwrap();
+        for (name, tx, rx) in server_links {
+            server.add_incoming(tx, rx, name, &[]).await.unwrap();
+        }
+
+        let (task, ch, _control) = listener.next().await.unwrap().accept();
+        exec::spawn(task.into_future());
+
+        let (tx, mut rx) = ch.into_tx_rx();
+        for n in 0..COUNT {
+            assert_eq!(rx.recv().await.unwrap(), Some(packet(n, SIZE)), "data mismatch in message {n}");
+            tx.send(packet(n, SIZE)).await.unwrap();
+        }
+        tx.flush().await.unwrap();
+    };
+
+    let client_task = async move {
+        let (task, outgoing, control) = connect(cfg);
+        exec::spawn(task.into_future());
+
+        let add_links = client_links.into_iter().map(|(name, tx, rx)| control.add(tx, rx, name, &[]));
+        future::try_join_all(add_links).await.unwrap();
+
+        let (tx, mut rx) = outgoing.connect().await.unwrap().into_tx_rx();
+        for n in 0..COUNT {
+            tx.send(packet(n, SIZE)).await.unwrap();
+            assert_eq!(rx.recv().await.unwrap(), Some(packet(n, SIZE)), "data mismatch in message {n}");
+        }
+    };
+
+    join!(server_task, client_task);
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn authenticated() {
+    exchange(Cfg { psk: Some(PreSharedKey::new("swordfish")), ..Default::default() }).await;
+}
+
+/// Establishes a link between a client and a server and returns the errors.
+async fn refused(client_cfg: Cfg, server_cfg: Cfg) -> (AddLinkError, Option<IncomingError>) {
+    let (link_a_tx, link_a_rx, _link_a_control) = test_channel::channel(test_channel::Cfg::default());
+    let (link_b_tx, link_b_rx, _link_b_control) = test_channel::channel(test_channel::Cfg::default());
+
+    let server = Server::new(server_cfg);
+    let _listener = server.listen().unwrap();
+    let server_task = server.add_incoming(link_b_tx, link_a_rx, "incoming", &[]);
+
+    let (_task, _outgoing, control) = connect(client_cfg);
+    let client_task = control.add(link_a_tx, link_b_rx, "outgoing", &[]);
+
+    let (server_res, client_res) = join!(server_task, client_task);
+    (client_res.unwrap_err(), server_res.err())
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn encryption_required_by_server() {
+    let (client_err, server_err) = refused(Cfg::default(), Cfg { encryption: true, ..Default::default() }).await;
+    assert!(matches!(client_err, AddLinkError::EncryptionRequired), "wrong client error: {client_err}");
+    assert!(matches!(server_err, Some(IncomingError::EncryptionRequired)), "wrong server error: {server_err:?}");
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn encryption_required_by_client() {
+    let (client_err, server_err) = refused(Cfg { encryption: true, ..Default::default() }, Cfg::default()).await;
+    assert!(matches!(client_err, AddLinkError::EncryptionRequired), "wrong client error: {client_err}");
+    assert!(server_err.is_some(), "server accepted link");
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn psk_mismatch() {
+    let (client_err, server_err) = refused(
+        Cfg { psk: Some(PreSharedKey::new("client")), ..Default::default() },
+        Cfg { psk: Some(PreSharedKey::new("server")), ..Default::default() },
+    )
+    .await;
+    assert!(matches!(client_err, AddLinkError::Unauthorized), "wrong client error: {client_err}");
+    assert!(matches!(server_err, Some(IncomingError::Unauthorized)), "wrong server error: {server_err:?}");
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn psk_required_by_server() {
+    let (client_err, server_err) =
+        refused(Cfg::default(), Cfg { psk: Some(PreSharedKey::new("server")), ..Default::default() }).await;
+    assert!(matches!(client_err, AddLinkError::Unauthorized), "wrong client error: {client_err}");
+    assert!(matches!(server_err, Some(IncomingError::Unauthorized)), "wrong server error: {server_err:?}");
+}
+
+#[cfg_attr(not(feature = "js"), test_log::test(tokio::test(flavor = "multi_thread")))]
+#[cfg_attr(feature = "js", wasm_bindgen_test)]
+async fn psk_required_by_client() {
+    let (client_err, server_err) =
+        refused(Cfg { psk: Some(PreSharedKey::new("client")), ..Default::default() }, Cfg::default()).await;
+    assert!(matches!(client_err, AddLinkError::Unauthorized), "wrong client error: {client_err}");
+    assert!(server_err.is_some(), "server accepted link");
+}